@@ -1,6 +1,7 @@
 #!/bin/bash
 
 awslocal s3api create-bucket --bucket pending-csv-files
+awslocal s3api create-bucket --bucket rejected-rows
 
 awslocal s3api put-bucket-notification-configuration --bucket pending-csv-files --notification-configuration '{
   "QueueConfigurations": [
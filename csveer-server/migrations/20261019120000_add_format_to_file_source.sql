@@ -0,0 +1 @@
+ALTER TABLE file_source ADD COLUMN format JSONB;
@@ -0,0 +1,4 @@
+ALTER TABLE file_source ADD COLUMN error_threshold JSONB;
+
+ALTER TABLE data_dispatch ADD COLUMN total_rows INT;
+ALTER TABLE data_dispatch ADD COLUMN rejected_rows INT;
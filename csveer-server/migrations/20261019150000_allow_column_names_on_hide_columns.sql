@@ -0,0 +1 @@
+ALTER TABLE file_source ALTER COLUMN hide_columns TYPE JSONB USING to_jsonb(hide_columns);
@@ -0,0 +1,13 @@
+ALTER TABLE file_source ADD COLUMN header_drift_policy JSONB;
+ALTER TABLE file_source ADD COLUMN header_signature JSONB;
+
+CREATE TABLE header_drift_event(
+  id SERIAL PRIMARY KEY,
+  file_source_id INT NOT NULL,
+  file_name TEXT NOT NULL,
+  expected_headers JSONB NOT NULL,
+  found_headers JSONB NOT NULL,
+  policy VARCHAR(100) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  FOREIGN KEY(file_source_id) REFERENCES file_source(id)
+);
@@ -0,0 +1 @@
+ALTER TABLE file_destination ADD COLUMN transformations JSONB;
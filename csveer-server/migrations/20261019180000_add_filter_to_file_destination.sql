@@ -0,0 +1,2 @@
+ALTER TABLE file_destination ADD COLUMN "filter" TEXT;
+ALTER TABLE data_dispatch ADD COLUMN filtered_rows INT;
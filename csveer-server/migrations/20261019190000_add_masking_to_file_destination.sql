@@ -0,0 +1,2 @@
+ALTER TABLE file_destination ADD COLUMN masking JSONB;
+ALTER TABLE data_dispatch ADD COLUMN masked_columns JSONB;
@@ -0,0 +1,12 @@
+CREATE TABLE reference_dataset (
+  id SERIAL PRIMARY KEY,
+  context VARCHAR(50) NOT NULL,
+  identifier VARCHAR(100) NOT NULL,
+  columns JSONB NOT NULL,
+  "rows" JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  updated_at TIMESTAMPTZ,
+  UNIQUE(context, identifier)
+);
+
+CREATE INDEX idx_reference_dataset_context ON reference_dataset(context);
@@ -0,0 +1,12 @@
+ALTER TABLE file_destination ADD COLUMN deduplication JSONB;
+ALTER TABLE data_dispatch ADD COLUMN duplicate_rows INT;
+
+CREATE TABLE dispatched_row_key(
+  file_destination_id INT NOT NULL,
+  key_hash VARCHAR(64) NOT NULL,
+  dispatched_at TIMESTAMPTZ NOT NULL,
+  PRIMARY KEY(file_destination_id, key_hash),
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id)
+);
+
+CREATE INDEX idx_dispatched_row_key_dispatched_at ON dispatched_row_key(file_destination_id, dispatched_at);
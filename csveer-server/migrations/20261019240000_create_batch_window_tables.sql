@@ -0,0 +1,23 @@
+CREATE TABLE batch_window(
+  id SERIAL PRIMARY KEY,
+  file_destination_id INT NOT NULL,
+  group_id TEXT NOT NULL,
+  group_key JSONB NOT NULL,
+  column_names JSONB NOT NULL,
+  column_types JSONB NOT NULL,
+  headers JSONB NOT NULL,
+  max_rows INT NOT NULL,
+  opened_at TIMESTAMPTZ NOT NULL,
+  flush_at TIMESTAMPTZ NOT NULL,
+  UNIQUE(file_destination_id, group_id),
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id)
+);
+
+CREATE TABLE batch_window_row(
+  id BIGSERIAL PRIMARY KEY,
+  batch_window_id INT NOT NULL,
+  "row" JSONB NOT NULL,
+  FOREIGN KEY(batch_window_id) REFERENCES batch_window(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_batch_window_row_batch_window_id ON batch_window_row(batch_window_id);
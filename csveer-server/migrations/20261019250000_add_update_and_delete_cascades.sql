@@ -0,0 +1,25 @@
+ALTER TABLE context ADD COLUMN updated_at TIMESTAMPTZ;
+
+ALTER TABLE file_destination DROP CONSTRAINT file_destination_file_source_id_fkey;
+ALTER TABLE file_destination ADD CONSTRAINT file_destination_file_source_id_fkey
+  FOREIGN KEY(file_source_id) REFERENCES file_source(id) ON DELETE CASCADE;
+
+ALTER TABLE header_drift_event DROP CONSTRAINT header_drift_event_file_source_id_fkey;
+ALTER TABLE header_drift_event ADD CONSTRAINT header_drift_event_file_source_id_fkey
+  FOREIGN KEY(file_source_id) REFERENCES file_source(id) ON DELETE CASCADE;
+
+ALTER TABLE data_dispatch DROP CONSTRAINT data_dispatch_file_destination_id_fkey;
+ALTER TABLE data_dispatch ADD CONSTRAINT data_dispatch_file_destination_id_fkey
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id) ON DELETE CASCADE;
+
+ALTER TABLE data_dispatch_execution DROP CONSTRAINT data_dispatch_execution_data_dispatch_id_fkey;
+ALTER TABLE data_dispatch_execution ADD CONSTRAINT data_dispatch_execution_data_dispatch_id_fkey
+  FOREIGN KEY(data_dispatch_id) REFERENCES data_dispatch(id) ON DELETE CASCADE;
+
+ALTER TABLE dispatched_row_key DROP CONSTRAINT dispatched_row_key_file_destination_id_fkey;
+ALTER TABLE dispatched_row_key ADD CONSTRAINT dispatched_row_key_file_destination_id_fkey
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id) ON DELETE CASCADE;
+
+ALTER TABLE batch_window DROP CONSTRAINT batch_window_file_destination_id_fkey;
+ALTER TABLE batch_window ADD CONSTRAINT batch_window_file_destination_id_fkey
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id) ON DELETE CASCADE;
@@ -0,0 +1,63 @@
+ALTER TABLE file_source ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE file_destination ADD COLUMN version INT NOT NULL DEFAULT 1;
+
+CREATE TABLE file_source_version(
+  id SERIAL PRIMARY KEY,
+  file_source_id INT NOT NULL,
+  version INT NOT NULL,
+  configuration JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  UNIQUE(file_source_id, version),
+  FOREIGN KEY(file_source_id) REFERENCES file_source(id) ON DELETE CASCADE
+);
+
+CREATE TABLE file_destination_version(
+  id SERIAL PRIMARY KEY,
+  file_destination_id INT NOT NULL,
+  version INT NOT NULL,
+  configuration JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  UNIQUE(file_destination_id, version),
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id) ON DELETE CASCADE
+);
+
+-- The current configuration of existing sources and destinations becomes
+-- their first version
+INSERT INTO file_source_version(file_source_id, version, configuration, created_at)
+SELECT fs.id, fs.version, jsonb_build_object(
+    'context', fs.context,
+    'identifier', fs.identifier,
+    'description', fs.description,
+    'source', fs."source",
+    'headers', fs.headers,
+    'compression', fs.compression,
+    'hide_columns', fs.hide_columns,
+    'format', fs.format,
+    'schema', fs."schema",
+    'error_threshold', fs.error_threshold,
+    'header_drift_policy', fs.header_drift_policy
+  ), COALESCE(fs.updated_at, fs.created_at)
+FROM file_source fs;
+
+INSERT INTO file_destination_version(file_destination_id, version, configuration, created_at)
+SELECT fd.id, fd.version, jsonb_build_object(
+    'identifier', fd.identifier,
+    'destination', fd.destination,
+    'include_headers', fd.include_headers,
+    'grouping', fd."grouping",
+    'batching', fd.batching,
+    'transformations', fd.transformations,
+    'filter', fd."filter",
+    'masking', fd.masking,
+    'deduplication', fd.deduplication,
+    'mode', fd.mode,
+    'sort_by', fd.sort_by
+  ), COALESCE(fd.updated_at, fd.created_at)
+FROM file_destination fd;
+
+ALTER TABLE data_dispatch ADD COLUMN file_source_version INT;
+ALTER TABLE data_dispatch ADD COLUMN file_destination_version INT;
+UPDATE data_dispatch SET file_destination_version = 1;
+ALTER TABLE data_dispatch ADD CONSTRAINT data_dispatch_file_destination_version_fkey
+  FOREIGN KEY(file_destination_id, file_destination_version)
+  REFERENCES file_destination_version(file_destination_id, version);
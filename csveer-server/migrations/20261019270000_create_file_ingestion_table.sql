@@ -0,0 +1,21 @@
+CREATE TABLE file_ingestion(
+  id SERIAL PRIMARY KEY,
+  file_source_id INT NOT NULL,
+  file_name TEXT NOT NULL,
+  s3_key TEXT NOT NULL,
+  checksum VARCHAR(64) NOT NULL,
+  size BIGINT NOT NULL,
+  status VARCHAR(100) NOT NULL,
+  message TEXT,
+  file_destination_count INT,
+  created_at TIMESTAMPTZ NOT NULL,
+  updated_at TIMESTAMPTZ,
+  FOREIGN KEY(file_source_id) REFERENCES file_source(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_file_ingestion_s3_key ON file_ingestion(s3_key);
+
+ALTER TABLE data_dispatch ADD COLUMN file_ingestion_id INT;
+ALTER TABLE data_dispatch ADD CONSTRAINT data_dispatch_file_ingestion_id_fkey
+  FOREIGN KEY(file_ingestion_id) REFERENCES file_ingestion(id) ON DELETE SET NULL;
+CREATE INDEX idx_data_dispatch_file_ingestion_id ON data_dispatch(file_ingestion_id);
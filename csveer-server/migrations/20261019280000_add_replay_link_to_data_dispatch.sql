@@ -0,0 +1,3 @@
+ALTER TABLE data_dispatch ADD COLUMN replayed_data_dispatch_id INT;
+ALTER TABLE data_dispatch ADD CONSTRAINT data_dispatch_replayed_data_dispatch_id_fkey
+  FOREIGN KEY(replayed_data_dispatch_id) REFERENCES data_dispatch(id) ON DELETE SET NULL;
@@ -0,0 +1,14 @@
+ALTER TABLE file_source ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE file_source ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE file_destination ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE file_destination ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE held_dispatch_message(
+  id SERIAL PRIMARY KEY,
+  file_destination_id INT NOT NULL,
+  message TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  FOREIGN KEY(file_destination_id) REFERENCES file_destination(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_held_dispatch_message_file_destination_id ON held_dispatch_message(file_destination_id);
@@ -0,0 +1,405 @@
+use std::cmp::Ordering;
+
+use sqlx::types::JsonValue;
+
+use crate::{
+    app::{file_output::as_decimal, sorting::compare_values},
+    config::server::AppError,
+    data::{
+        file_destination::{AggregateFunction, AggregateMetric},
+        file_source::{ColumnReference, ColumnType},
+    },
+};
+
+pub enum Accumulator {
+    Count(i64),
+    // Summed as unscaled digits and a scale, so decimals stay exact
+    Sum {
+        mantissa: i128,
+        scale: u32,
+        is_decimal: bool,
+        is_empty: bool,
+    },
+    Min(Option<JsonValue>),
+    Max(Option<JsonValue>),
+}
+
+pub struct CompiledAggregation {
+    metrics: Vec<(Option<usize>, AggregateFunction)>,
+    key_column_count: usize,
+    pub column_names: Vec<String>,
+    pub column_types: Vec<Option<ColumnType>>,
+}
+
+impl CompiledAggregation {
+    pub fn new(
+        metrics: &[AggregateMetric],
+        column_names: &[String],
+        column_types: &[Option<ColumnType>],
+        key_columns: &[usize],
+    ) -> anyhow::Result<Self, AppError> {
+        if let Some(column) = key_columns.iter().find(|c| **c >= column_names.len()) {
+            return Err(AppError::Validation(format!(
+                "Cannot aggregate by column {}, rows only have {} columns",
+                column,
+                column_names.len()
+            )));
+        }
+
+        let mut output_names: Vec<String> = key_columns
+            .iter()
+            .map(|column| column_names[*column].clone())
+            .collect();
+        let mut output_types: Vec<Option<ColumnType>> = key_columns
+            .iter()
+            .map(|column| column_types[*column].clone())
+            .collect();
+        let mut compiled = Vec::with_capacity(metrics.len());
+
+        for metric in metrics {
+            if output_names.contains(&metric.name) {
+                return Err(AppError::Validation(format!(
+                    "Aggregate metric '{}' has the same name as another output column",
+                    metric.name
+                )));
+            }
+
+            let column = match &metric.column {
+                Some(reference) => Some(
+                    match reference {
+                        ColumnReference::Index(idx) => Some(*idx as usize),
+                        ColumnReference::Name(name) => column_names.iter().position(|c| c == name),
+                    }
+                    .filter(|column| *column < column_names.len())
+                    .ok_or_else(|| {
+                        AppError::Validation(format!(
+                            "Column {} of aggregate metric '{}' does not exist. Found: {}",
+                            reference,
+                            metric.name,
+                            column_names.join(", ")
+                        ))
+                    })?,
+                ),
+                None => None,
+            };
+            let column_type = column.and_then(|column| column_types[column].clone());
+
+            let output_type = match metric.function {
+                AggregateFunction::Count => Some(ColumnType::Integer {
+                    min: None,
+                    max: None,
+                }),
+                AggregateFunction::Sum => match column_type {
+                    Some(ColumnType::Integer { .. }) => Some(ColumnType::Integer {
+                        min: None,
+                        max: None,
+                    }),
+                    Some(ColumnType::Decimal { .. }) => Some(ColumnType::Decimal {
+                        min: None,
+                        max: None,
+                    }),
+                    Some(_) => {
+                        return Err(AppError::Validation(format!(
+                            "Aggregate metric '{}' can only sum numeric columns",
+                            metric.name
+                        )))
+                    }
+                    None => None,
+                },
+                AggregateFunction::Min | AggregateFunction::Max => column_type,
+            };
+
+            output_names.push(metric.name.clone());
+            output_types.push(output_type);
+            compiled.push((column, metric.function));
+        }
+
+        Ok(Self {
+            metrics: compiled,
+            key_column_count: key_columns.len(),
+            column_names: output_names,
+            column_types: output_types,
+        })
+    }
+
+    pub fn accumulators(&self) -> Vec<Accumulator> {
+        self.metrics
+            .iter()
+            .map(|(_, function)| match function {
+                AggregateFunction::Count => Accumulator::Count(0),
+                AggregateFunction::Sum => Accumulator::Sum {
+                    mantissa: 0,
+                    scale: 0,
+                    is_decimal: false,
+                    is_empty: true,
+                },
+                AggregateFunction::Min => Accumulator::Min(None),
+                AggregateFunction::Max => Accumulator::Max(None),
+            })
+            .collect()
+    }
+
+    pub fn update(
+        &self,
+        accumulators: &mut [Accumulator],
+        row: &[JsonValue],
+    ) -> anyhow::Result<(), AppError> {
+        for ((column, _), accumulator) in self.metrics.iter().zip(accumulators.iter_mut()) {
+            let value = match column {
+                Some(column) => match row.get(*column) {
+                    None | Some(JsonValue::Null) => continue,
+                    Some(JsonValue::String(text)) if text.is_empty() => continue,
+                    Some(value) => Some(value),
+                },
+                None => None,
+            };
+
+            match (accumulator, value) {
+                (Accumulator::Count(count), _) => *count += 1,
+                (
+                    Accumulator::Sum {
+                        mantissa,
+                        scale,
+                        is_decimal,
+                        is_empty,
+                    },
+                    Some(value),
+                ) => {
+                    let (value_mantissa, value_scale) = as_decimal(value).ok_or_else(|| {
+                        AppError::Validation(format!("Cannot sum non numeric value {}", value))
+                    })?;
+                    let overflow =
+                        || AppError::Validation(format!("Sum overflows when adding {}", value));
+                    // Both sides are brought to the larger scale before adding
+                    let value_mantissa = match value_scale < *scale {
+                        true => rescale(value_mantissa, *scale - value_scale),
+                        false => {
+                            *mantissa =
+                                rescale(*mantissa, value_scale - *scale).ok_or_else(overflow)?;
+                            *scale = value_scale;
+                            Some(value_mantissa)
+                        }
+                    }
+                    .ok_or_else(overflow)?;
+                    *mantissa = mantissa.checked_add(value_mantissa).ok_or_else(overflow)?;
+                    *is_decimal |= as_i64(value).is_none();
+                    *is_empty = false;
+                }
+                (Accumulator::Min(current), Some(value)) => {
+                    let replace = match current {
+                        Some(min) => compare_values(value, min) == Ordering::Less,
+                        None => true,
+                    };
+                    if replace {
+                        *current = Some(value.clone());
+                    }
+                }
+                (Accumulator::Max(current), Some(value)) => {
+                    let replace = match current {
+                        Some(max) => compare_values(value, max) == Ordering::Greater,
+                        None => true,
+                    };
+                    if replace {
+                        *current = Some(value.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(
+        &self,
+        key: Option<&[JsonValue]>,
+        accumulators: &[Accumulator],
+    ) -> Vec<JsonValue> {
+        let mut row = Vec::with_capacity(self.column_names.len());
+        row.extend(
+            key.unwrap_or_default()
+                .iter()
+                .take(self.key_column_count)
+                .cloned(),
+        );
+        row.extend(accumulators.iter().map(|accumulator| match accumulator {
+            Accumulator::Count(count) => JsonValue::from(*count),
+            Accumulator::Sum { is_empty: true, .. } => JsonValue::Null,
+            Accumulator::Sum {
+                mantissa,
+                scale,
+                is_decimal,
+                ..
+            } => match (is_decimal, i64::try_from(*mantissa)) {
+                (false, Ok(integer)) => JsonValue::from(integer),
+                // Decimals are written as text, as the schema validation
+                // keeps them, so no digit is lost to a float
+                _ => JsonValue::String(decimal_text(*mantissa, *scale)),
+            },
+            Accumulator::Min(value) | Accumulator::Max(value) => {
+                value.clone().unwrap_or(JsonValue::Null)
+            }
+        }));
+        row
+    }
+}
+
+fn as_i64(value: &JsonValue) -> Option<i64> {
+    match value {
+        JsonValue::Number(n) => n.as_i64(),
+        JsonValue::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn rescale(mantissa: i128, digits: u32) -> Option<i128> {
+    mantissa.checked_mul(10_i128.checked_pow(digits)?)
+}
+
+fn decimal_text(mantissa: i128, scale: u32) -> String {
+    let scale = scale as usize;
+    let digits = format!("{:0>width$}", mantissa.unsigned_abs(), width = scale + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - scale);
+    let sign = match mantissa < 0 {
+        true => "-",
+        false => "",
+    };
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, integer),
+        false => format!("{}{}.{}", sign, integer, fraction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::types::JsonValue;
+
+    use super::CompiledAggregation;
+    use crate::data::{
+        file_destination::{AggregateFunction, AggregateMetric},
+        file_source::{ColumnReference, ColumnType},
+    };
+
+    fn metric(name: &str, function: AggregateFunction, column: Option<&str>) -> AggregateMetric {
+        AggregateMetric {
+            name: String::from(name),
+            function,
+            column: column.map(|column| ColumnReference::Name(String::from(column))),
+        }
+    }
+
+    fn aggregate(metrics: &[AggregateMetric], rows: &[Vec<JsonValue>]) -> Vec<JsonValue> {
+        let column_names = vec![String::from("account"), String::from("amount")];
+        let column_types = vec![
+            None,
+            Some(ColumnType::Integer {
+                min: None,
+                max: None,
+            }),
+        ];
+        let aggregation =
+            CompiledAggregation::new(metrics, &column_names, &column_types, &[0]).unwrap();
+        let mut accumulators = aggregation.accumulators();
+        for row in rows {
+            aggregation.update(&mut accumulators, row).unwrap();
+        }
+        aggregation.finish(rows.first().map(|row| &row[..1]), &accumulators)
+    }
+
+    #[test]
+    fn should_fold_rows_into_metrics() {
+        let rows = vec![
+            vec![json!("a"), json!(3)],
+            vec![json!("a"), json!(null)],
+            vec![json!("a"), json!("-1")],
+        ];
+        let metrics = [
+            metric("rows", AggregateFunction::Count, None),
+            metric("amounts", AggregateFunction::Count, Some("amount")),
+            metric("total", AggregateFunction::Sum, Some("amount")),
+            metric("lowest", AggregateFunction::Min, Some("amount")),
+            metric("highest", AggregateFunction::Max, Some("amount")),
+        ];
+
+        assert_eq!(
+            aggregate(&metrics, &rows),
+            vec![
+                json!("a"),
+                json!(3),
+                json!(2),
+                json!(2),
+                json!("-1"),
+                json!(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn should_switch_sum_to_decimal_on_overflow() {
+        let rows = vec![
+            vec![json!("a"), json!(i64::MAX)],
+            vec![json!("a"), json!(1)],
+        ];
+        let total = aggregate(
+            &[metric("total", AggregateFunction::Sum, Some("amount"))],
+            &rows,
+        );
+
+        assert_eq!(total[1], json!("9223372036854775808"));
+    }
+
+    #[test]
+    fn should_switch_sum_to_decimal_on_decimal_value() {
+        let rows = vec![vec![json!("a"), json!(1)], vec![json!("a"), json!("0.5")]];
+        let total = aggregate(
+            &[metric("total", AggregateFunction::Sum, Some("amount"))],
+            &rows,
+        );
+
+        assert_eq!(total[1], json!("1.5"));
+    }
+
+    #[test]
+    fn should_sum_decimals_exactly() {
+        let rows = vec![
+            vec![json!("a"), json!("0.1")],
+            vec![json!("a"), json!("0.2")],
+            vec![json!("a"), json!("-1.25")],
+        ];
+        let total = aggregate(
+            &[metric("total", AggregateFunction::Sum, Some("amount"))],
+            &rows,
+        );
+
+        assert_eq!(total[1], json!("-0.95"));
+    }
+
+    #[test]
+    fn should_leave_sum_of_empty_values_null() {
+        let rows = vec![vec![json!("a"), json!("")]];
+        let total = aggregate(
+            &[metric("total", AggregateFunction::Sum, Some("amount"))],
+            &rows,
+        );
+
+        assert_eq!(total[1], JsonValue::Null);
+    }
+
+    #[test]
+    fn should_fail_to_sum_non_numeric_values() {
+        let column_names = vec![String::from("account"), String::from("amount")];
+        let aggregation = CompiledAggregation::new(
+            &[metric("total", AggregateFunction::Sum, Some("amount"))],
+            &column_names,
+            &[None, None],
+            &[],
+        )
+        .unwrap();
+        let mut accumulators = aggregation.accumulators();
+
+        assert!(aggregation
+            .update(&mut accumulators, &[json!("a"), json!("abc")])
+            .is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sqs::Client as SQSClient;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{Connection, PgConnection, Pool, Postgres};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, info_span, instrument};
+
+use crate::{
+    app::{data_dispatch_queue::send_batch, dispatch_pipeline::DispatchBatch},
+    config::server::{AppError, AppState},
+    data::{
+        batch_window::{
+            delete_batch_window, delete_batch_window_rows, insert_batch_window_rows,
+            list_batch_window_rows, list_flushable_batch_window_ids, lock_batch_window,
+            upsert_batch_window, BatchWindowCreation,
+        },
+        data_dispatch::{
+            insert_data_dispatch, update_data_dispatch_row_counts, update_data_dispatch_status,
+            DataDispatchCreation, DataDispatchStatus,
+        },
+        data_dispatch_execution::{
+            insert_data_dispatch_execution, DataDispatchExecutionCreation,
+            DataDispatchExecutionStatus,
+        },
+        file_destination, file_source,
+    },
+};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+struct BatchWindowFlushMessage {
+    batch_window_id: i32,
+    file_name: String,
+}
+
+// Rows are appended to the open window of their group, windows are keyed by
+// the group and the output columns so uploads with other columns never share
+// a batch
+pub async fn buffer_batches(
+    file_destination_id: &i32,
+    batches: &[DispatchBatch],
+    max_rows: i32,
+    max_wait_seconds: i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<(), AppError> {
+    let mut tx = executor.begin().await?;
+    for batch in batches.iter().filter(|batch| !batch.rows.is_empty()) {
+        let batch_window = upsert_batch_window(
+            BatchWindowCreation {
+                file_destination_id: *file_destination_id,
+                group_id: serde_json::to_string(&(&batch.group, &batch.column_names))?,
+                group_key: batch.group.clone(),
+                column_names: batch.column_names.clone(),
+                column_types: batch.column_types.clone(),
+                headers: batch.headers.clone(),
+                max_rows,
+                max_wait_seconds,
+            },
+            &mut tx,
+        )
+        .await?;
+        insert_batch_window_rows(&batch_window.id, &batch.rows, &mut tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn listen_batch_windows(
+    token: &CancellationToken,
+    app_state: AppState,
+) -> anyhow::Result<(), AppError> {
+    let flusher_span = info_span!(parent: None, "batch-window-flusher");
+    let _guard = flusher_span.enter();
+
+    loop {
+        if token.is_cancelled() {
+            info!("Shutting down batch window flusher");
+            break;
+        }
+        if let Err(err) = flush_batch_windows(
+            &app_state.db_pool,
+            &app_state.s3_client,
+            &app_state.sqs_client,
+        )
+        .await
+        {
+            error!("Failed to flush batch windows. Error: {:?}", err);
+        }
+        tokio::select! {
+            _ = token.cancelled() => {}
+            _ = tokio::time::sleep(FLUSH_INTERVAL) => {}
+        }
+    }
+    Ok(())
+}
+
+async fn flush_batch_windows(
+    db_pool: &Pool<Postgres>,
+    s3_client: &S3Client,
+    sqs_client: &SQSClient,
+) -> anyhow::Result<(), AppError> {
+    let mut conn = db_pool.acquire().await?;
+    let batch_window_ids = list_flushable_batch_window_ids(&mut conn).await?;
+    drop(conn);
+
+    for batch_window_id in batch_window_ids {
+        if let Err(err) = flush_batch_window(db_pool, s3_client, sqs_client, &batch_window_id).await
+        {
+            error!(
+                "Failed to flush batch window {}. Error: {}",
+                batch_window_id, err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Batches are sent while the window is locked and its rows are only deleted
+// when the transaction commits, so a crash mid flush leaves the rows in place
+// to be sent again once the server is back
+#[instrument(skip(db_pool, s3_client, sqs_client))]
+async fn flush_batch_window(
+    db_pool: &Pool<Postgres>,
+    s3_client: &S3Client,
+    sqs_client: &SQSClient,
+    batch_window_id: &i32,
+) -> anyhow::Result<(), AppError> {
+    let mut tx = db_pool.begin().await?;
+    let batch_window = match lock_batch_window(batch_window_id, &mut tx).await? {
+        Some(batch_window) => batch_window,
+        None => return Ok(()),
+    };
+    let file_destination =
+        match file_destination::find_by_id(&batch_window.file_destination_id, &mut tx).await? {
+            Some(file_destination) => file_destination,
+            None => {
+                error!(
+                    "Could not find file destination {} of batch window {}.",
+                    batch_window.file_destination_id, batch_window.id
+                );
+                return Ok(());
+            }
+        };
+    let file_source =
+        match file_source::find_by_id(&file_destination.file_source_id, &mut tx).await? {
+            Some(file_source) => file_source,
+            None => {
+                error!(
+                    "Could not find file source {} of file destination {}.",
+                    file_destination.file_source_id, file_destination.identifier
+                );
+                return Ok(());
+            }
+        };
+
+    // The window may have been listed before its destination or source was
+    // paused or disabled, its rows stay until it can be flushed again
+    if !file_source.enabled
+        || !file_destination.enabled
+        || file_source.paused
+        || file_destination.paused
+    {
+        info!(
+            "Skipped flush of batch window {} of paused or disabled file destination '{}'",
+            batch_window.id, file_destination.identifier
+        );
+        return Ok(());
+    }
+
+    let rows = list_batch_window_rows(&batch_window.id, &mut tx).await?;
+    let is_due = batch_window.flush_at <= Utc::now();
+    let max_rows = batch_window.max_rows.max(1) as usize;
+    let chunks: Vec<_> = rows
+        .chunks(max_rows)
+        .filter(|chunk| is_due || chunk.len() == max_rows)
+        .collect();
+    let flushed_row_ids: Vec<i64> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.iter().map(|row| row.id))
+        .collect();
+
+    if !flushed_row_ids.is_empty() {
+        // Windows of different groups can be flushed within the same second,
+        // the window id keeps their files apart
+        let file_name = format!(
+            "{}-window-{}-{}",
+            file_destination.identifier,
+            batch_window.id,
+            Utc::now().format("%Y%m%d%H%M%S")
+        );
+        // Rows of a window may come from uploads read with different file
+        // source versions, so only the destination version is recorded
+        let data_dispatch = insert_data_dispatch(
+            DataDispatchCreation {
+                file_destination_id: file_destination.id,
+                file_source_version: None,
+                file_destination_version: file_destination.version,
+                file_ingestion_id: None,
+                replayed_data_dispatch_id: None,
+                message: serde_json::to_string(&BatchWindowFlushMessage {
+                    batch_window_id: batch_window.id,
+                    file_name: file_name.clone(),
+                })?,
+            },
+            &mut tx,
+        )
+        .await?;
+
+        for (batch_index, chunk) in chunks.iter().enumerate() {
+            let batch = DispatchBatch {
+                column_names: batch_window.column_names.clone(),
+                column_types: batch_window.column_types.clone(),
+                group: batch_window.group_key.clone(),
+                headers: batch_window.headers.clone(),
+                rows: chunk.iter().map(|row| row.row.clone()).collect(),
+            };
+            send_batch(
+                s3_client,
+                sqs_client,
+                &file_destination.destination,
+                &file_name,
+                batch_index,
+                &batch,
+            )
+            .await?;
+        }
+
+        delete_batch_window_rows(&flushed_row_ids, &mut tx).await?;
+        update_data_dispatch_row_counts(
+            &data_dispatch.id,
+            flushed_row_ids.len() as i32,
+            0,
+            0,
+            0,
+            &mut tx,
+        )
+        .await?;
+        insert_data_dispatch_execution(
+            DataDispatchExecutionCreation {
+                data_dispatch_id: data_dispatch.id,
+                status: DataDispatchExecutionStatus::Success,
+                message: format!(
+                    "Flushed {} rows of batch window {} in {} batches",
+                    flushed_row_ids.len(),
+                    batch_window.id,
+                    chunks.len()
+                ),
+            },
+            &mut tx,
+        )
+        .await?;
+        update_data_dispatch_status(&data_dispatch.id, DataDispatchStatus::Finished, &mut tx)
+            .await?;
+
+        info!(
+            "Flushed {} rows of batch window {} to file destination '{}'",
+            flushed_row_ids.len(),
+            batch_window.id,
+            file_destination.identifier
+        );
+    }
+
+    if flushed_row_ids.len() == rows.len() {
+        delete_batch_window(&batch_window.id, &mut tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,481 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, HeaderName, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument};
+
+use crate::{
+    app::{
+        configuration_version::{diff_configurations, ConfigurationChange},
+        context::validate_context_name,
+        file_destination::{
+            apply_file_destination_update, find_file_destination_version,
+            insert_new_file_destination, validate_file_destination,
+            validate_file_source_destinations,
+        },
+        file_source::{
+            apply_file_source_update, find_file_source_version, insert_new_file_source,
+            validate_file_source,
+        },
+    },
+    config::{masking::MaskingConfig, server::AppError},
+    data::{
+        context::{get_context_by_name, insert_context, list_contexts, CreatableContext},
+        file_destination::{
+            delete_file_destination, list_by_file_source_id, FileDestination,
+            FileDestinationCreation,
+        },
+        file_source::{
+            delete_file_source, find_by_context_and_identifier, list_file_sources, FileSource,
+            FileSourceCreation,
+        },
+    },
+};
+
+// Only the configuration is part of the document. Runtime state, such as the
+// pause and enable switches or the learned header signature, is left out
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigurationDocument {
+    #[serde(default)]
+    pub contexts: Vec<String>,
+    #[serde(default)]
+    pub file_sources: Vec<FileSourceDocument>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileSourceDocument {
+    #[serde(flatten)]
+    pub file_source: FileSourceCreation,
+    #[serde(default)]
+    pub file_destinations: Vec<FileDestinationCreation>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    Yaml,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    pub format: Option<DocumentFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportParams {
+    pub plan: Option<bool>,
+    pub prune: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportResource {
+    Context,
+    FileSource,
+    FileDestination,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportChange {
+    pub action: ImportAction,
+    pub resource: ImportResource,
+    pub name: String,
+    pub changes: Vec<ConfigurationChange>,
+}
+
+impl ImportChange {
+    fn new(action: ImportAction, resource: ImportResource, name: String) -> Self {
+        Self {
+            action,
+            resource,
+            name,
+            changes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub applied: bool,
+    pub changes: Vec<ImportChange>,
+}
+
+#[instrument(skip(db))]
+pub async fn export_configuration(
+    State(db): State<PgPool>,
+    Query(params): Query<ExportParams>,
+) -> anyhow::Result<(StatusCode, [(HeaderName, &'static str); 1], String), AppError> {
+    let mut conn = db.acquire().await?;
+    let document = export_document(&mut conn).await?;
+
+    let (content_type, body) = match params.format.unwrap_or(DocumentFormat::Yaml) {
+        DocumentFormat::Yaml => ("application/yaml", serde_yaml::to_string(&document)?),
+        DocumentFormat::Json => ("application/json", serde_json::to_string_pretty(&document)?),
+    };
+
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, content_type)], body))
+}
+
+// The document is applied in a single transaction. A plan runs the very same
+// steps, so it reports the validation errors an import would hit, and rolls
+// them back. YAML being a superset of JSON, both are accepted as the body
+#[instrument(skip(db, body))]
+pub async fn import_configuration(
+    State(db): State<PgPool>,
+    Query(params): Query<ImportParams>,
+    body: String,
+) -> anyhow::Result<(StatusCode, Json<ImportResult>), AppError> {
+    let document: ConfigurationDocument = serde_yaml::from_str(&body).map_err(|err| {
+        AppError::DetailedValidation(
+            String::from("Invalid configuration document"),
+            vec![err.to_string()],
+        )
+    })?;
+
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    validate_document(&document, &masking_config)?;
+
+    let plan = params.plan.unwrap_or(false);
+    let mut tx = db.begin().await?;
+    let changes = apply_document(
+        document,
+        params.prune.unwrap_or(false),
+        &masking_config,
+        &mut tx,
+    )
+    .await?;
+
+    match plan {
+        true => tx.rollback().await?,
+        false => {
+            info!("Imported {} configuration changes", changes.len());
+            tx.commit().await?
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(ImportResult {
+            applied: !plan,
+            changes,
+        }),
+    ))
+}
+
+async fn export_document(
+    executor: &mut PgConnection,
+) -> anyhow::Result<ConfigurationDocument, AppError> {
+    let contexts = list_contexts(executor)
+        .await?
+        .into_iter()
+        .map(|context| context.name)
+        .collect();
+
+    let mut file_sources = Vec::new();
+    for file_source in list_file_sources(None, executor).await? {
+        let mut file_destinations = list_by_file_source_id(&file_source.id, executor).await?;
+        file_destinations.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+        let mut file_destination_configurations = Vec::with_capacity(file_destinations.len());
+        for file_destination in &file_destinations {
+            file_destination_configurations
+                .push(file_destination_configuration(file_destination, executor).await?);
+        }
+
+        file_sources.push(FileSourceDocument {
+            file_source: file_source_configuration(&file_source, executor).await?,
+            file_destinations: file_destination_configurations,
+        });
+    }
+
+    Ok(ConfigurationDocument {
+        contexts,
+        file_sources,
+    })
+}
+
+// The configuration stored with the current version. The context may have
+// been renamed since it was stored
+async fn file_source_configuration(
+    file_source: &FileSource,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSourceCreation, AppError> {
+    let version = find_file_source_version(file_source, &file_source.version, executor).await?;
+    let mut configuration: FileSourceCreation = serde_json::from_value(version.configuration)?;
+    configuration.context = file_source.context.clone();
+    configuration.identifier = file_source.identifier.clone();
+
+    Ok(configuration)
+}
+
+async fn file_destination_configuration(
+    file_destination: &FileDestination,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestinationCreation, AppError> {
+    let version =
+        find_file_destination_version(file_destination, &file_destination.version, executor)
+            .await?;
+
+    Ok(serde_json::from_value(version.configuration)?)
+}
+
+fn document_context_names(document: &ConfigurationDocument) -> Vec<String> {
+    let mut names: Vec<String> = document
+        .contexts
+        .iter()
+        .cloned()
+        .chain(
+            document
+                .file_sources
+                .iter()
+                .map(|source| source.file_source.context.clone()),
+        )
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// Everything that can be checked without the database is checked before the
+// transaction starts
+fn validate_document(
+    document: &ConfigurationDocument,
+    masking_config: &MaskingConfig,
+) -> anyhow::Result<(), AppError> {
+    for name in document_context_names(document) {
+        validate_context_name(&name)?;
+    }
+
+    let mut duplicates = Vec::new();
+    let mut file_source_names = HashSet::new();
+    for source in &document.file_sources {
+        let file_source = &source.file_source;
+        if !file_source_names.insert((&file_source.context, &file_source.identifier)) {
+            duplicates.push(format!(
+                "File source {} of context {} is declared more than once",
+                file_source.identifier, file_source.context
+            ));
+        }
+
+        let mut file_destination_names = HashSet::new();
+        for file_destination in &source.file_destinations {
+            if !file_destination_names.insert(&file_destination.identifier) {
+                duplicates.push(format!(
+                    "File destination {} of file source {} is declared more than once",
+                    file_destination.identifier, file_source.identifier
+                ));
+            }
+        }
+    }
+    if !duplicates.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("Invalid configuration document"),
+            duplicates,
+        ));
+    }
+
+    for source in &document.file_sources {
+        validate_file_source(&source.file_source)?;
+        for file_destination in &source.file_destinations {
+            validate_file_destination(file_destination, masking_config)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Resources are only written when their configuration differs from the
+// current version, so importing the same document twice changes nothing.
+// Pruning stays within the contexts the document names, and contexts
+// themselves are never pruned since they also hold reference datasets
+async fn apply_document(
+    document: ConfigurationDocument,
+    prune: bool,
+    masking_config: &MaskingConfig,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<ImportChange>, AppError> {
+    let mut changes = Vec::new();
+
+    let context_names = document_context_names(&document);
+    for name in &context_names {
+        if get_context_by_name(name, executor).await.is_none() {
+            insert_context(CreatableContext::new(name.clone()), executor).await?;
+            changes.push(ImportChange::new(
+                ImportAction::Create,
+                ImportResource::Context,
+                name.clone(),
+            ));
+        }
+    }
+
+    let mut declared_file_sources = HashSet::new();
+    for source in document.file_sources {
+        let FileSourceDocument {
+            file_source: file_source_configuration_update,
+            file_destinations: file_destination_configurations,
+        } = source;
+        let file_source_name = format!(
+            "{}/{}",
+            file_source_configuration_update.context, file_source_configuration_update.identifier
+        );
+        declared_file_sources.insert(file_source_name.clone());
+
+        let mut file_source_updated = false;
+        let file_source = match find_by_context_and_identifier(
+            &file_source_configuration_update.context,
+            &file_source_configuration_update.identifier,
+            executor,
+        )
+        .await?
+        {
+            None => {
+                changes.push(ImportChange::new(
+                    ImportAction::Create,
+                    ImportResource::FileSource,
+                    file_source_name.clone(),
+                ));
+                insert_new_file_source(file_source_configuration_update, executor).await?
+            }
+            Some(file_source) => {
+                let current = file_source_configuration(&file_source, executor).await?;
+                let diff = diff_configurations(
+                    &serde_json::to_value(&current)?,
+                    &serde_json::to_value(&file_source_configuration_update)?,
+                );
+                match diff.is_empty() {
+                    true => file_source,
+                    false => {
+                        changes.push(ImportChange {
+                            changes: diff,
+                            ..ImportChange::new(
+                                ImportAction::Update,
+                                ImportResource::FileSource,
+                                file_source_name.clone(),
+                            )
+                        });
+                        file_source_updated = true;
+                        apply_file_source_update(
+                            file_source,
+                            file_source_configuration_update,
+                            executor,
+                        )
+                        .await?
+                    }
+                }
+            }
+        };
+
+        let file_destinations = list_by_file_source_id(&file_source.id, executor).await?;
+        let declared_file_destinations: HashSet<String> = file_destination_configurations
+            .iter()
+            .map(|file_destination| file_destination.identifier.clone())
+            .collect();
+
+        for file_destination_update in file_destination_configurations {
+            let file_destination_name = format!(
+                "{}/{}",
+                file_source_name, file_destination_update.identifier
+            );
+            match file_destinations
+                .iter()
+                .find(|existing| existing.identifier == file_destination_update.identifier)
+            {
+                None => {
+                    insert_new_file_destination(
+                        &file_source,
+                        file_destination_update,
+                        masking_config,
+                        executor,
+                    )
+                    .await?;
+                    changes.push(ImportChange::new(
+                        ImportAction::Create,
+                        ImportResource::FileDestination,
+                        file_destination_name,
+                    ));
+                }
+                Some(file_destination) => {
+                    let current =
+                        file_destination_configuration(file_destination, executor).await?;
+                    let diff = diff_configurations(
+                        &serde_json::to_value(&current)?,
+                        &serde_json::to_value(&file_destination_update)?,
+                    );
+                    if diff.is_empty() {
+                        continue;
+                    }
+                    apply_file_destination_update(
+                        &file_source,
+                        file_destination,
+                        file_destination_update,
+                        masking_config,
+                        executor,
+                    )
+                    .await?;
+                    changes.push(ImportChange {
+                        changes: diff,
+                        ..ImportChange::new(
+                            ImportAction::Update,
+                            ImportResource::FileDestination,
+                            file_destination_name,
+                        )
+                    });
+                }
+            }
+        }
+
+        if prune {
+            for file_destination in &file_destinations {
+                if declared_file_destinations.contains(&file_destination.identifier) {
+                    continue;
+                }
+                delete_file_destination(&file_destination.id, executor).await?;
+                changes.push(ImportChange::new(
+                    ImportAction::Delete,
+                    ImportResource::FileDestination,
+                    format!("{}/{}", file_source_name, file_destination.identifier),
+                ));
+            }
+        }
+
+        // Checked once the declared destinations are in place, so a document
+        // changing a source together with its destinations is accepted
+        if file_source_updated {
+            validate_file_source_destinations(&file_source, masking_config, executor).await?;
+        }
+    }
+
+    if prune {
+        for name in &context_names {
+            for file_source in list_file_sources(Some(name), executor).await? {
+                let file_source_name =
+                    format!("{}/{}", file_source.context, file_source.identifier);
+                if declared_file_sources.contains(&file_source_name) {
+                    continue;
+                }
+                // Its destinations and their history go with it
+                delete_file_source(&file_source.id, executor).await?;
+                changes.push(ImportChange::new(
+                    ImportAction::Delete,
+                    ImportResource::FileSource,
+                    file_source_name,
+                ));
+            }
+        }
+    }
+
+    Ok(changes)
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::JsonValue;
+
+use crate::config::server::AppError;
+
+#[derive(Debug, Deserialize)]
+pub struct VersionDiffParams {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigurationChange {
+    pub path: String,
+    pub from: JsonValue,
+    pub to: JsonValue,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigurationDiff {
+    pub from_version: i32,
+    pub to_version: i32,
+    pub changes: Vec<ConfigurationChange>,
+}
+
+pub fn version_not_found(version: &i32, owner: &str) -> AppError {
+    AppError::NotFound(format!("Version {} of {} does not exist.", version, owner))
+}
+
+// Objects are compared key by key and arrays of the same length element by
+// element, anything else is reported as a change of the whole value
+pub fn diff_configurations(from: &JsonValue, to: &JsonValue) -> Vec<ConfigurationChange> {
+    let mut changes = Vec::new();
+    collect_changes(String::new(), from, to, &mut changes);
+    changes
+}
+
+fn collect_changes(
+    path: String,
+    from: &JsonValue,
+    to: &JsonValue,
+    changes: &mut Vec<ConfigurationChange>,
+) {
+    match (from, to) {
+        (JsonValue::Object(from_fields), JsonValue::Object(to_fields)) => {
+            let added_keys = to_fields
+                .keys()
+                .filter(|key| !from_fields.contains_key(*key));
+            for key in from_fields.keys().chain(added_keys) {
+                let key_path = match path.is_empty() {
+                    true => key.clone(),
+                    false => format!("{}.{}", path, key),
+                };
+                collect_changes(
+                    key_path,
+                    from_fields.get(key).unwrap_or(&JsonValue::Null),
+                    to_fields.get(key).unwrap_or(&JsonValue::Null),
+                    changes,
+                );
+            }
+        }
+        (JsonValue::Array(from_items), JsonValue::Array(to_items))
+            if from_items.len() == to_items.len() =>
+        {
+            for (idx, (from_item, to_item)) in from_items.iter().zip(to_items).enumerate() {
+                collect_changes(format!("{}[{}]", path, idx), from_item, to_item, changes);
+            }
+        }
+        _ if from != to => changes.push(ConfigurationChange {
+            path,
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        _ => {}
+    }
+}
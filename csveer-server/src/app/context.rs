@@ -1,13 +1,38 @@
 use crate::{
     config::server::AppError,
-    data::context::{get_context_by_name, insert_context, CreatableContext, SourceContext},
+    data::{
+        context::{
+            delete_context as delete_context_by_name, get_context_by_name, insert_context,
+            list_contexts as list_all_contexts, update_context_name, CreatableContext,
+            SourceContext,
+        },
+        file_source::{self, list_file_sources},
+        reference_dataset,
+    },
 };
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
 };
+use serde::Deserialize;
 use sqlx::PgPool;
-use tracing::instrument;
+use tracing::{info, instrument};
+
+#[derive(Debug, Deserialize)]
+pub struct DeletionParams {
+    pub cascade: Option<bool>,
+}
+
+// Contexts are the first segment of file source routes, so a context named
+// after another top level route could never be reached
+const RESERVED_CONTEXT_NAMES: [&str; 6] = [
+    "context",
+    "source",
+    "ingestions",
+    "dispatches",
+    "export",
+    "import",
+];
 
 pub fn validate_context_name(name: &String) -> anyhow::Result<(), AppError> {
     if name.is_empty() {
@@ -16,6 +41,13 @@ pub fn validate_context_name(name: &String) -> anyhow::Result<(), AppError> {
         )));
     }
 
+    if RESERVED_CONTEXT_NAMES.contains(&name.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Context name '{}' is reserved.",
+            name
+        )));
+    }
+
     for char in name.chars() {
         if !&char.is_digit(36) && &char != &'-' {
             return Err(AppError::Validation(format!(
@@ -51,3 +83,104 @@ pub async fn create_context(
     tx.commit().await?;
     Ok((StatusCode::CREATED, Json(context)))
 }
+
+#[instrument(skip(db))]
+pub async fn list_contexts(
+    State(db): State<PgPool>,
+) -> anyhow::Result<(StatusCode, Json<Vec<SourceContext>>), AppError> {
+    let mut conn = db.acquire().await?;
+    let contexts = list_all_contexts(&mut conn).await?;
+
+    Ok((StatusCode::OK, Json(contexts)))
+}
+
+#[instrument(skip(db))]
+pub async fn get_context(
+    State(db): State<PgPool>,
+    Path(name): Path<String>,
+) -> anyhow::Result<(StatusCode, Json<SourceContext>), AppError> {
+    let mut conn = db.acquire().await?;
+    match get_context_by_name(&name, &mut conn).await {
+        Some(context) => Ok((StatusCode::OK, Json(context))),
+        None => Err(AppError::NotFound(format!(
+            "Context with name '{}' does not exist.",
+            name
+        ))),
+    }
+}
+
+// Renaming a context moves its file sources and reference datasets along
+#[instrument(skip(db))]
+pub async fn update_context(
+    State(db): State<PgPool>,
+    Path(name): Path<String>,
+    Json(context_update): Json<CreatableContext>,
+) -> anyhow::Result<(StatusCode, Json<SourceContext>), AppError> {
+    validate_context_name(&context_update.name)?;
+
+    let mut tx = db.begin().await?;
+
+    if get_context_by_name(&name, &mut tx).await.is_none() {
+        return Err(AppError::NotFound(format!(
+            "Context with name '{}' does not exist.",
+            name
+        )));
+    }
+
+    if context_update.name != name
+        && get_context_by_name(&context_update.name, &mut tx)
+            .await
+            .is_some()
+    {
+        return Err(AppError::Validation(format!(
+            "A context with name '{}' already exists.",
+            &context_update.name
+        )));
+    }
+
+    let context = update_context_name(&name, &context_update.name, &mut tx).await?;
+    file_source::update_context(&name, &context_update.name, &mut tx).await?;
+    reference_dataset::update_context(&name, &context_update.name, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(context)))
+}
+
+// A context with file sources is only deleted when `cascade` is set, which
+// also deletes the file sources along with everything under them
+#[instrument(skip(db))]
+pub async fn delete_context(
+    State(db): State<PgPool>,
+    Path(name): Path<String>,
+    Query(params): Query<DeletionParams>,
+) -> anyhow::Result<StatusCode, AppError> {
+    let mut tx = db.begin().await?;
+
+    if get_context_by_name(&name, &mut tx).await.is_none() {
+        return Err(AppError::NotFound(format!(
+            "Context with name '{}' does not exist.",
+            name
+        )));
+    }
+
+    let file_sources = list_file_sources(Some(&name), &mut tx).await?;
+    if !file_sources.is_empty() && !params.cascade.unwrap_or(false) {
+        return Err(AppError::Validation(format!(
+            "Context '{}' still has {} file sources. Delete them first or set cascade=true.",
+            name,
+            file_sources.len()
+        )));
+    }
+
+    info!(
+        "Deleting context {} with {} file sources",
+        name,
+        file_sources.len()
+    );
+    file_source::delete_by_context(&name, &mut tx).await?;
+    reference_dataset::delete_by_context(&name, &mut tx).await?;
+    delete_context_by_name(&name, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::{
+    config::server::AppError,
+    data::{
+        data_dispatch::{
+            count_data_dispatches, find_record_by_id,
+            list_data_dispatches as list_data_dispatch_records, DataDispatchFilter,
+            DataDispatchRecord, DataDispatchStatus,
+        },
+        data_dispatch_execution::{list_by_data_dispatch_id, DataDispatchExecution},
+    },
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DataDispatchListParams {
+    pub context: Option<String>,
+    pub file_source: Option<String>,
+    pub file_destination: Option<String>,
+    pub status: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct DataDispatchPage {
+    pub items: Vec<DataDispatchRecord>,
+    pub page: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+#[derive(Serialize)]
+pub struct DataDispatchDetails {
+    #[serde(flatten)]
+    pub data_dispatch: DataDispatchRecord,
+    pub executions: Vec<DataDispatchExecution>,
+}
+
+fn validate_list_params(
+    params: DataDispatchListParams,
+) -> anyhow::Result<(DataDispatchFilter, i64, i64), AppError> {
+    let mut issues = Vec::new();
+
+    let status = match params.status {
+        Some(status) => match DataDispatchStatus::try_from(status) {
+            Ok(status) => Some(status),
+            Err(err) => {
+                issues.push(err);
+                None
+            }
+        },
+        None => None,
+    };
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        issues.push(format!("Page should be at least 1. Found {}", page));
+    }
+    let page_size = params.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+        issues.push(format!(
+            "Page size should be between 1 and {}. Found {}",
+            MAX_PAGE_SIZE, page_size
+        ));
+    }
+    if let (Some(from), Some(to)) = (&params.from, &params.to) {
+        if from >= to {
+            issues.push(String::from("'from' should be before 'to'"));
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("Invalid data dispatch query"),
+            issues,
+        ));
+    }
+
+    Ok((
+        DataDispatchFilter {
+            context: params.context,
+            file_source_identifier: params.file_source,
+            file_destination_identifier: params.file_destination,
+            status,
+            created_from: params.from,
+            created_to: params.to,
+        },
+        page,
+        page_size,
+    ))
+}
+
+// Dispatches are listed newest first. `from` is inclusive and `to` is
+// exclusive, both are matched against the creation time of the dispatch
+#[instrument(skip(db))]
+pub async fn list_data_dispatches(
+    State(db): State<PgPool>,
+    Query(params): Query<DataDispatchListParams>,
+) -> anyhow::Result<(StatusCode, Json<DataDispatchPage>), AppError> {
+    let (filter, page, page_size) = validate_list_params(params)?;
+
+    let offset = (page - 1).checked_mul(page_size).ok_or_else(|| {
+        AppError::Validation(format!("Page {} is past the last possible page", page))
+    })?;
+
+    let mut conn = db.acquire().await?;
+    let total = count_data_dispatches(&filter, &mut conn).await?;
+    let items = list_data_dispatch_records(&filter, page_size, offset, &mut conn).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DataDispatchPage {
+            items,
+            page,
+            page_size,
+            total,
+        }),
+    ))
+}
+
+#[instrument(skip(db))]
+pub async fn get_data_dispatch(
+    State(db): State<PgPool>,
+    Path(data_dispatch_id): Path<i32>,
+) -> anyhow::Result<(StatusCode, Json<DataDispatchDetails>), AppError> {
+    let mut conn = db.acquire().await?;
+    let data_dispatch = find_record_by_id(&data_dispatch_id, &mut conn)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Data dispatch {} does not exist.",
+                data_dispatch_id
+            ))
+        })?;
+    let executions = list_by_data_dispatch_id(&data_dispatch_id, &mut conn).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DataDispatchDetails {
+            data_dispatch,
+            executions,
+        }),
+    ))
+}
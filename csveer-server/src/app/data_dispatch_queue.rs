@@ -1,4 +1,59 @@
+use std::{
+    collections::HashSet,
+    io::Seek,
+    path::{Component, Path},
+};
+
+use anyhow::Context;
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sqs::types::Message;
+use aws_sdk_sqs::Client as SQSClient;
 use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Pool, Postgres};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, instrument};
+use ulid::Ulid;
+
+use crate::{
+    app::{
+        batch_window::buffer_batches,
+        deduplication::{load_dispatched_key_hashes, record_dispatched_key_hashes},
+        dispatch_pipeline::{DispatchBatch, DispatchPipeline},
+        file_ingestion::{mark_file_ingestion_dispatching, refresh_file_ingestion_status},
+        file_output::{encode_batch, output_file_name},
+        file_reader::{open_file, widest_row},
+        reference_dataset::{load_reference_datasets, ReferenceDatasets},
+        rejected_rows::{encode_rejected_rows, exceeds_threshold, rejects_file_name},
+    },
+    commons::{
+        file_storage::{
+            retrieve_file_to_disk, store_dispatched_file, PENDING_CSV_FILES_BUCKET,
+            REJECTED_ROWS_BUCKET,
+        },
+        queue_listener::{ack_message, poll_message, post_message},
+    },
+    config::{
+        listeners::DataDispatchListenerConfig,
+        masking::MaskingConfig,
+        server::{AppError, AppState},
+    },
+    data::{
+        data_dispatch::{
+            insert_data_dispatch, update_data_dispatch_masked_columns,
+            update_data_dispatch_row_counts, update_data_dispatch_status, DataDispatchCreation,
+            DataDispatchStatus, MaskedColumn,
+        },
+        data_dispatch_execution::{
+            insert_data_dispatch_execution, DataDispatchExecutionCreation,
+            DataDispatchExecutionStatus,
+        },
+        file_destination::{
+            self, BatchingConfiguration, DestinationConfiguration, FileDestination,
+        },
+        file_source::{self, FileSource},
+        held_dispatch_message::{self, delete_held_dispatch_message, insert_held_dispatch_message},
+    },
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DataDispatchMessage {
@@ -6,4 +61,528 @@ pub struct DataDispatchMessage {
     pub file_source_identifier: String,
     pub file_destination_identifier: String,
     pub file_name: String,
+    pub file_ingestion_id: Option<i32>,
+    pub replayed_data_dispatch_id: Option<i32>,
+}
+
+pub async fn listen_data_dispatch(
+    token: &CancellationToken,
+    app_state: AppState,
+) -> anyhow::Result<(), AppError> {
+    let sqs_client = app_state.sqs_client;
+    let s3_client = app_state.s3_client;
+    let data_dispatch_config = envy::from_env::<DataDispatchListenerConfig>()?;
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    let db_pool = app_state.db_pool;
+    let listener_span = info_span!(parent: None, "data-dispatch-listener");
+    let _guard = listener_span.enter();
+
+    loop {
+        if token.is_cancelled() {
+            info!("Shutting down data dispatch listener");
+            break;
+        }
+        let message = poll_message(&sqs_client, &data_dispatch_config.queue_url).await?;
+        if let Some(messages) = message.messages {
+            let message_span =
+                info_span!("data-dispatch-message", trace_id = Ulid::new().to_string());
+            message_span.follows_from(listener_span.clone());
+            let _guard = message_span.enter();
+            // SAFETY: We can unwrap here because we've
+            // guaranteed there is a message with `if let`
+            let message = messages.first().unwrap();
+            let message_body = message.body().unwrap();
+            info!("About to process message: {}", message_body);
+            match process_message(&db_pool, &s3_client, &sqs_client, &masking_config, message).await
+            {
+                Ok(_) => {
+                    ack_message(
+                        &sqs_client,
+                        &data_dispatch_config.queue_url,
+                        message.receipt_handle().unwrap(),
+                    )
+                    .await?;
+                    info!("Successfully processed message: {}", message_body);
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to process message: {}. Error: {:?}",
+                        message_body, err,
+                    );
+                }
+            }
+        }
+        debug!("listener loop");
+    }
+    Ok(())
+}
+
+#[instrument(skip(db_pool, s3_client, sqs_client, masking_config, message))]
+async fn process_message(
+    db_pool: &Pool<Postgres>,
+    s3_client: &S3Client,
+    sqs_client: &SQSClient,
+    masking_config: &MaskingConfig,
+    message: &Message,
+) -> anyhow::Result<(), AppError> {
+    let message_body = match message.body() {
+        Some(b) => b,
+        None => {
+            info!("Could not get event body as string. Aborting processing of message.");
+            return Ok(());
+        }
+    };
+    let data_dispatch_message = match serde_json::from_str::<DataDispatchMessage>(message_body) {
+        Ok(m) => m,
+        Err(_) => {
+            info!("Could not parse message body into data dispatch message. Aborting processing of message.");
+            return Ok(());
+        }
+    };
+
+    let mut conn = db_pool.acquire().await?;
+
+    let file_source = match file_source::find_by_context_and_identifier(
+        &data_dispatch_message.context,
+        &data_dispatch_message.file_source_identifier,
+        &mut conn,
+    )
+    .await?
+    {
+        Some(file_source) => file_source,
+        None => {
+            error!(
+                "Could not find file source with identifier {} on context {}.",
+                data_dispatch_message.file_source_identifier, data_dispatch_message.context
+            );
+            return Ok(());
+        }
+    };
+
+    let file_destination = match file_destination::find_by_file_source_id_and_identifier(
+        &file_source.id,
+        &data_dispatch_message.file_destination_identifier,
+        &mut conn,
+    )
+    .await?
+    {
+        Some(file_destination) => file_destination,
+        None => {
+            error!(
+                "Could not find file destination with identifier {} for file source {}.",
+                data_dispatch_message.file_destination_identifier, file_source.identifier
+            );
+            return Ok(());
+        }
+    };
+
+    // Paused messages are kept aside instead of left on the queue, where
+    // they would be retried until they reach the dead letter queue. The
+    // pause is checked on locked rows, so a concurrent resume waits for the
+    // message to be held and then releases it
+    let mut tx = db_pool.begin().await?;
+    let file_source_paused = file_source::find_by_id_for_share(&file_source.id, &mut tx)
+        .await?
+        .is_some_and(|file_source| file_source.paused);
+    let file_destination_paused =
+        file_destination::find_by_id_for_share(&file_destination.id, &mut tx)
+            .await?
+            .is_some_and(|file_destination| file_destination.paused);
+    if file_source_paused || file_destination_paused {
+        insert_held_dispatch_message(&file_destination.id, message_body, &mut tx).await?;
+        tx.commit().await?;
+        info!(
+            "Held dispatch of file {} to paused file destination '{}'",
+            data_dispatch_message.file_name, file_destination.identifier
+        );
+        return Ok(());
+    }
+    tx.commit().await?;
+
+    let data_dispatch = insert_data_dispatch(
+        DataDispatchCreation {
+            file_destination_id: file_destination.id,
+            file_source_version: Some(file_source.version),
+            file_destination_version: file_destination.version,
+            file_ingestion_id: data_dispatch_message.file_ingestion_id,
+            replayed_data_dispatch_id: data_dispatch_message.replayed_data_dispatch_id,
+            message: serde_json::to_string(&data_dispatch_message)?,
+        },
+        &mut conn,
+    )
+    .await?;
+    if let Some(file_ingestion_id) = &data_dispatch_message.file_ingestion_id {
+        let mut tx = db_pool.begin().await?;
+        mark_file_ingestion_dispatching(file_ingestion_id, &mut tx).await?;
+        tx.commit().await?;
+    }
+
+    let outcome: anyhow::Result<DispatchOutcome, AppError> = async {
+        if !file_source.enabled || !file_destination.enabled {
+            return Err(AppError::Validation(format!(
+                "File destination '{}' or its file source is disabled",
+                file_destination.identifier
+            )));
+        }
+        let reference_datasets = load_reference_datasets(
+            &file_source.context,
+            file_destination
+                .transformations
+                .as_deref()
+                .unwrap_or_default(),
+            &mut conn,
+        )
+        .await?;
+        dispatch_file(
+            s3_client,
+            sqs_client,
+            &file_source,
+            &file_destination,
+            &data_dispatch_message,
+            masking_config,
+            &reference_datasets,
+            &mut conn,
+        )
+        .await
+    }
+    .await;
+
+    let mut key_hashes = Vec::new();
+    let (dispatch_status, execution_creation) = match outcome {
+        Ok(outcome) => {
+            update_data_dispatch_row_counts(
+                &data_dispatch.id,
+                outcome.total_rows as i32,
+                outcome.rejected_rows as i32,
+                outcome.filtered_rows as i32,
+                outcome.duplicate_rows as i32,
+                &mut conn,
+            )
+            .await?;
+            if !outcome.masked_columns.is_empty() {
+                update_data_dispatch_masked_columns(
+                    &data_dispatch.id,
+                    &outcome.masked_columns,
+                    &mut conn,
+                )
+                .await?;
+            }
+            key_hashes = outcome.key_hashes;
+            let execution_status = match outcome.status {
+                DataDispatchStatus::Failed => DataDispatchExecutionStatus::Failure,
+                _ => DataDispatchExecutionStatus::Success,
+            };
+            (
+                outcome.status,
+                DataDispatchExecutionCreation {
+                    data_dispatch_id: data_dispatch.id,
+                    status: execution_status,
+                    message: outcome.message,
+                },
+            )
+        }
+        Err(err) => {
+            error!(
+                "Failed to dispatch file {} to file destination '{}'. Error: {}",
+                data_dispatch_message.file_name, file_destination.identifier, err
+            );
+            (
+                DataDispatchStatus::Failed,
+                DataDispatchExecutionCreation {
+                    data_dispatch_id: data_dispatch.id,
+                    status: DataDispatchExecutionStatus::Failure,
+                    message: err.to_string(),
+                },
+            )
+        }
+    };
+
+    let mut tx = db_pool.begin().await?;
+    insert_data_dispatch_execution(execution_creation, &mut tx).await?;
+    update_data_dispatch_status(&data_dispatch.id, dispatch_status, &mut tx).await?;
+    // A replay sends rows that were already dispatched on purpose, so its
+    // keys are neither checked nor recorded again
+    if data_dispatch_message.replayed_data_dispatch_id.is_none() {
+        record_dispatched_key_hashes(&file_destination, &key_hashes, &mut tx).await?;
+    }
+    if let Some(file_ingestion_id) = &data_dispatch_message.file_ingestion_id {
+        refresh_file_ingestion_status(file_ingestion_id, &mut tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Sends the messages held while the destination was paused back to the data
+// dispatch queue. A message is only dropped once it has been posted again,
+// the ones that fail stay held and are retried by the next resume
+pub async fn release_held_dispatch_messages(
+    sqs_client: &SQSClient,
+    file_destination: &FileDestination,
+    executor: &mut PgConnection,
+) -> anyhow::Result<usize, AppError> {
+    let data_dispatch_config = envy::from_env::<DataDispatchListenerConfig>()?;
+    let held_messages =
+        held_dispatch_message::list_by_file_destination_id(&file_destination.id, executor).await?;
+
+    let mut released_count = 0;
+    for held_message in &held_messages {
+        if let Err(err) = post_message(
+            sqs_client,
+            &data_dispatch_config.queue_url,
+            held_message.message.clone(),
+        )
+        .await
+        {
+            error!(
+                "Failed to release held message {} of file destination '{}'. Error: {}",
+                held_message.id, file_destination.identifier, err
+            );
+            continue;
+        }
+        delete_held_dispatch_message(&held_message.id, executor).await?;
+        released_count += 1;
+    }
+
+    match released_count == held_messages.len() {
+        true => info!(
+            "Released {} held messages of file destination '{}'",
+            released_count, file_destination.identifier
+        ),
+        false => error!(
+            "Released {} of {} held messages of file destination '{}'",
+            released_count,
+            held_messages.len(),
+            file_destination.identifier
+        ),
+    }
+
+    Ok(released_count)
+}
+
+struct DispatchOutcome {
+    status: DataDispatchStatus,
+    total_rows: usize,
+    rejected_rows: usize,
+    filtered_rows: usize,
+    duplicate_rows: usize,
+    masked_columns: Vec<MaskedColumn>,
+    key_hashes: Vec<String>,
+    message: String,
+}
+
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip(
+    s3_client,
+    sqs_client,
+    file_source,
+    file_destination,
+    masking_config,
+    reference_datasets,
+    executor
+))]
+async fn dispatch_file(
+    s3_client: &S3Client,
+    sqs_client: &SQSClient,
+    file_source: &FileSource,
+    file_destination: &FileDestination,
+    data_dispatch_message: &DataDispatchMessage,
+    masking_config: &MaskingConfig,
+    reference_datasets: &ReferenceDatasets,
+    executor: &mut PgConnection,
+) -> anyhow::Result<DispatchOutcome, AppError> {
+    let object_key = format!(
+        "{}/{}/{}",
+        data_dispatch_message.context,
+        data_dispatch_message.file_source_identifier,
+        data_dispatch_message.file_name
+    );
+    let mut file = retrieve_file_to_disk(s3_client, PENDING_CSV_FILES_BUCKET, &object_key).await?;
+
+    let widest_row = widest_row(file_source, &mut file)?;
+    file.rewind().context("Rewinding file for dispatch")?;
+    let records = open_file(file_source, file)?;
+    let pipeline = DispatchPipeline::new(
+        file_source,
+        file_destination,
+        records.headers.as_deref(),
+        widest_row,
+        masking_config,
+        reference_datasets,
+    )?;
+    let prepared = pipeline.prepare(records)?;
+    let dispatched_key_hashes = match data_dispatch_message.replayed_data_dispatch_id {
+        Some(_) => HashSet::new(),
+        None => {
+            load_dispatched_key_hashes(file_destination, &prepared.key_hashes(), executor).await?
+        }
+    };
+    let plan = pipeline.build_batches(prepared, &dispatched_key_hashes)?;
+
+    if !plan.rejected_rows.is_empty() {
+        let rejects_key = rejects_file_name(&object_key, &file_destination.identifier);
+        store_dispatched_file(
+            s3_client,
+            REJECTED_ROWS_BUCKET,
+            encode_rejected_rows(&plan.rejected_rows)?.into(),
+            &rejects_key,
+        )
+        .await?;
+        info!(
+            "Stored {} rejected rows of file {} at {}",
+            plan.rejected_row_count, data_dispatch_message.file_name, rejects_key
+        );
+    }
+
+    if exceeds_threshold(
+        &file_source.error_threshold,
+        plan.rejected_row_count,
+        plan.total_rows,
+    ) {
+        return Ok(DispatchOutcome {
+            status: DataDispatchStatus::Failed,
+            total_rows: plan.total_rows,
+            rejected_rows: plan.rejected_row_count,
+            filtered_rows: plan.filtered_row_count,
+            duplicate_rows: plan.duplicate_row_count,
+            masked_columns: Vec::new(),
+            key_hashes: Vec::new(),
+            message: format!(
+                "Rejected {} of {} rows, which exceeds the error threshold of the file source",
+                plan.rejected_row_count, plan.total_rows
+            ),
+        });
+    }
+
+    let summary = match &file_destination.batching {
+        Some(BatchingConfiguration::TimeWindow {
+            max_rows,
+            max_wait_seconds,
+        }) => {
+            buffer_batches(
+                &file_destination.id,
+                &plan.batches,
+                *max_rows,
+                *max_wait_seconds,
+                executor,
+            )
+            .await?;
+            format!("Buffered {} groups into time windows", plan.batches.len())
+        }
+        _ => {
+            for (batch_index, batch) in plan.batches.iter().enumerate() {
+                send_batch(
+                    s3_client,
+                    sqs_client,
+                    &file_destination.destination,
+                    &data_dispatch_message.file_name,
+                    batch_index,
+                    batch,
+                )
+                .await?;
+            }
+            format!("Dispatched {} batches", plan.batches.len())
+        }
+    };
+
+    info!(
+        "{} of file {} to file destination '{}'",
+        summary, data_dispatch_message.file_name, file_destination.identifier
+    );
+
+    let status = match plan.rejected_row_count {
+        0 => DataDispatchStatus::Finished,
+        _ => DataDispatchStatus::PartialSuccess,
+    };
+
+    Ok(DispatchOutcome {
+        status,
+        total_rows: plan.total_rows,
+        rejected_rows: plan.rejected_row_count,
+        filtered_rows: plan.filtered_row_count,
+        duplicate_rows: plan.duplicate_row_count,
+        masked_columns: plan.masked_columns,
+        key_hashes: plan.key_hashes,
+        message: format!(
+            "{}, rejected {}, filtered out {} and skipped {} duplicates of {} rows",
+            summary,
+            plan.rejected_row_count,
+            plan.filtered_row_count,
+            plan.duplicate_row_count,
+            plan.total_rows
+        ),
+    })
+}
+
+// Where a batch ends up: the queue URL, the S3 URI or the local file path
+pub fn batch_target(
+    destination: &DestinationConfiguration,
+    file_name: &str,
+    batch_index: usize,
+) -> String {
+    match destination {
+        DestinationConfiguration::SQS { queue_url } => queue_url.clone(),
+        DestinationConfiguration::S3 {
+            bucket,
+            prefix,
+            format,
+        } => format!(
+            "s3://{}/{}{}",
+            bucket,
+            prefix.as_deref().unwrap_or_default(),
+            output_file_name(file_name, batch_index, format)
+        ),
+        DestinationConfiguration::LocalFileSystem { directory, format } => Path::new(directory)
+            .join(output_file_name(file_name, batch_index, format))
+            .display()
+            .to_string(),
+    }
+}
+
+pub async fn send_batch(
+    s3_client: &S3Client,
+    sqs_client: &SQSClient,
+    destination: &DestinationConfiguration,
+    file_name: &str,
+    batch_index: usize,
+    batch: &DispatchBatch,
+) -> anyhow::Result<(), AppError> {
+    match destination {
+        DestinationConfiguration::SQS { queue_url } => {
+            post_message(sqs_client, queue_url, serde_json::to_string(batch)?).await?
+        }
+        DestinationConfiguration::S3 {
+            bucket,
+            prefix,
+            format,
+        } => {
+            let key = format!(
+                "{}{}",
+                prefix.as_deref().unwrap_or_default(),
+                output_file_name(file_name, batch_index, format)
+            );
+            store_dispatched_file(s3_client, bucket, encode_batch(batch, format)?.into(), &key)
+                .await?;
+        }
+        DestinationConfiguration::LocalFileSystem { directory, format } => {
+            let path = Path::new(directory).join(output_file_name(file_name, batch_index, format));
+            if path.parent() != Some(Path::new(directory))
+                || !matches!(path.components().last(), Some(Component::Normal(_)))
+            {
+                return Err(AppError::Validation(format!(
+                    "Output file {} is not directly under directory {}",
+                    path.display(),
+                    directory
+                )));
+            }
+            tokio::fs::create_dir_all(directory)
+                .await
+                .with_context(|| format!("Creating directory {}", directory))?;
+            tokio::fs::write(&path, encode_batch(batch, format)?)
+                .await
+                .with_context(|| format!("Writing dispatched file {}", path.display()))?;
+        }
+    }
+
+    Ok(())
 }
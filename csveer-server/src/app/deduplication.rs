@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+use sqlx::{types::JsonValue, PgConnection};
+
+use crate::{
+    config::server::AppError,
+    data::{
+        dispatched_row_key::{
+            delete_expired_key_hashes, list_recent_key_hashes, upsert_key_hashes,
+        },
+        file_destination::{DeduplicationConfiguration, FileDestination},
+        file_source::ColumnReference,
+    },
+};
+
+pub struct CompiledDeduplication {
+    columns: Vec<usize>,
+}
+
+pub struct DeduplicatedRows {
+    pub rows: Vec<Vec<JsonValue>>,
+    pub duplicate_row_count: usize,
+    pub key_hashes: Vec<String>,
+}
+
+impl CompiledDeduplication {
+    pub fn new(
+        deduplication: Option<&DeduplicationConfiguration>,
+        column_names: &[String],
+    ) -> anyhow::Result<Self, AppError> {
+        let columns = deduplication
+            .map(|deduplication| deduplication.columns.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|column| {
+                match column {
+                    ColumnReference::Index(idx) => Some(*idx as usize),
+                    ColumnReference::Name(name) => column_names.iter().position(|c| c == name),
+                }
+                .filter(|idx| *idx < column_names.len())
+                .ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Deduplication key column {} does not exist. Found: {}",
+                        column,
+                        column_names.join(", ")
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { columns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    // The key hash of every row, or none when the destination does not
+    // deduplicate
+    pub fn key_hashes(&self, rows: &[Vec<JsonValue>]) -> Vec<String> {
+        if self.columns.is_empty() {
+            return Vec::new();
+        }
+        rows.iter().map(|row| self.key_hash(row)).collect()
+    }
+
+    // Keeps the first row of every key that was not dispatched before,
+    // returning the hashes of the kept keys so they can be recorded once the
+    // rows are dispatched
+    pub fn apply(
+        &self,
+        rows: Vec<Vec<JsonValue>>,
+        row_key_hashes: Vec<String>,
+        dispatched_key_hashes: &HashSet<String>,
+    ) -> DeduplicatedRows {
+        if self.columns.is_empty() {
+            return DeduplicatedRows {
+                rows,
+                duplicate_row_count: 0,
+                key_hashes: Vec::new(),
+            };
+        }
+
+        let row_count = rows.len();
+        let mut key_hashes = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let rows: Vec<Vec<JsonValue>> = rows
+            .into_iter()
+            .zip(row_key_hashes)
+            .filter_map(|(row, key_hash)| {
+                if dispatched_key_hashes.contains(&key_hash) || !seen.insert(key_hash.clone()) {
+                    return None;
+                }
+                key_hashes.push(key_hash);
+                Some(row)
+            })
+            .collect();
+
+        DeduplicatedRows {
+            duplicate_row_count: row_count - rows.len(),
+            rows,
+            key_hashes,
+        }
+    }
+
+    fn key_hash(&self, row: &[JsonValue]) -> String {
+        let key: Vec<&JsonValue> = self
+            .columns
+            .iter()
+            .map(|column| row.get(*column).unwrap_or(&JsonValue::Null))
+            .collect();
+        // SAFETY: a list of JSON values can always be serialized
+        let key = serde_json::to_string(&key).unwrap();
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+}
+
+// Only the keys found in the current file are looked up, instead of every
+// key dispatched within the retention window
+pub async fn load_dispatched_key_hashes(
+    file_destination: &FileDestination,
+    key_hashes: &[String],
+    executor: &mut PgConnection,
+) -> anyhow::Result<HashSet<String>, AppError> {
+    match &file_destination.deduplication {
+        Some(deduplication) if !key_hashes.is_empty() => Ok(list_recent_key_hashes(
+            &file_destination.id,
+            deduplication.retention_hours,
+            key_hashes,
+            executor,
+        )
+        .await?),
+        _ => Ok(HashSet::new()),
+    }
+}
+
+pub async fn record_dispatched_key_hashes(
+    file_destination: &FileDestination,
+    key_hashes: &[String],
+    executor: &mut PgConnection,
+) -> anyhow::Result<(), AppError> {
+    let deduplication = match &file_destination.deduplication {
+        Some(deduplication) => deduplication,
+        None => return Ok(()),
+    };
+    delete_expired_key_hashes(
+        &file_destination.id,
+        deduplication.retention_hours,
+        executor,
+    )
+    .await?;
+    if !key_hashes.is_empty() {
+        upsert_key_hashes(&file_destination.id, key_hashes, executor).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::json;
+
+    use super::CompiledDeduplication;
+    use crate::data::{file_destination::DeduplicationConfiguration, file_source::ColumnReference};
+
+    fn column_names() -> Vec<String> {
+        vec![String::from("id"), String::from("amount")]
+    }
+
+    fn deduplication(columns: Vec<ColumnReference>) -> CompiledDeduplication {
+        CompiledDeduplication::new(
+            Some(&DeduplicationConfiguration {
+                columns,
+                retention_hours: 24,
+            }),
+            &column_names(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_keep_first_row_of_every_key() {
+        let deduplication = deduplication(vec![ColumnReference::Name(String::from("id"))]);
+        let rows = vec![
+            vec![json!("a"), json!(1)],
+            vec![json!("b"), json!(2)],
+            vec![json!("a"), json!(3)],
+        ];
+        let key_hashes = deduplication.key_hashes(&rows);
+
+        let deduplicated = deduplication.apply(rows, key_hashes.clone(), &HashSet::new());
+
+        assert_eq!(
+            deduplicated.rows,
+            vec![vec![json!("a"), json!(1)], vec![json!("b"), json!(2)]]
+        );
+        assert_eq!(deduplicated.duplicate_row_count, 1);
+        assert_eq!(deduplicated.key_hashes, key_hashes[..2].to_vec());
+    }
+
+    #[test]
+    fn should_skip_keys_dispatched_before() {
+        let deduplication = deduplication(vec![ColumnReference::Index(0)]);
+        let rows = vec![vec![json!("a"), json!(1)], vec![json!("b"), json!(2)]];
+        let key_hashes = deduplication.key_hashes(&rows);
+        let dispatched = HashSet::from([key_hashes[0].clone()]);
+
+        let deduplicated = deduplication.apply(rows, key_hashes.clone(), &dispatched);
+
+        assert_eq!(deduplicated.rows, vec![vec![json!("b"), json!(2)]]);
+        assert_eq!(deduplicated.duplicate_row_count, 1);
+        assert_eq!(deduplicated.key_hashes, vec![key_hashes[1].clone()]);
+    }
+
+    #[test]
+    fn should_tell_keys_apart_by_value_type() {
+        let deduplication = deduplication(vec![ColumnReference::Name(String::from("amount"))]);
+        let key_hashes =
+            deduplication.key_hashes(&[vec![json!("a"), json!(1)], vec![json!("a"), json!("1")]]);
+
+        assert_ne!(key_hashes[0], key_hashes[1]);
+    }
+
+    #[test]
+    fn should_keep_every_row_without_key_columns() {
+        let deduplication = CompiledDeduplication::new(None, &column_names()).unwrap();
+        let rows = vec![vec![json!("a"), json!(1)], vec![json!("a"), json!(1)]];
+
+        assert!(deduplication.key_hashes(&rows).is_empty());
+        let deduplicated = deduplication.apply(rows, Vec::new(), &HashSet::new());
+        assert_eq!(deduplicated.rows.len(), 2);
+        assert_eq!(deduplicated.duplicate_row_count, 0);
+    }
+
+    #[test]
+    fn should_fail_with_unknown_key_column() {
+        assert!(CompiledDeduplication::new(
+            Some(&DeduplicationConfiguration {
+                columns: vec![ColumnReference::Name(String::from("missing"))],
+                retention_hours: 24,
+            }),
+            &column_names(),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use axum::{
+    extract::{Json, Multipart, Path, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{types::JsonValue, PgPool};
+use tracing::{info, instrument};
+
+use crate::{
+    app::{
+        data_dispatch_queue::batch_target,
+        deduplication::load_dispatched_key_hashes,
+        dispatch_pipeline::{DispatchBatch, DispatchPipeline},
+        file_destination::{
+            find_file_destination, validate_against_file_source, validate_file_destination,
+        },
+        file_output::encode_batch,
+        file_reader::{open_file, widest_row},
+        file_source::find_file_source,
+        reference_dataset::load_reference_datasets,
+        rejected_rows::{exceeds_threshold, RejectedRow},
+    },
+    config::{masking::MaskingConfig, server::AppError},
+    data::file_destination::{
+        find_by_file_source_id_and_identifier, BatchingConfiguration, DestinationConfiguration,
+        FileDestination, FileDestinationCreation,
+    },
+};
+
+#[derive(Serialize)]
+pub struct PreviewMessage {
+    pub batch_index: usize,
+    pub target: String,
+    pub group: Option<Vec<JsonValue>>,
+    pub row_count: usize,
+    pub payload: JsonValue,
+    pub payload_size: usize,
+}
+
+#[derive(Serialize)]
+pub struct DestinationPreview {
+    pub total_rows: usize,
+    pub rejected_rows: usize,
+    pub filtered_rows: usize,
+    pub duplicate_rows: usize,
+    pub exceeds_error_threshold: bool,
+    pub rejections: Vec<RejectedRow>,
+    pub messages: Vec<PreviewMessage>,
+}
+
+// Runs a sample file through the dispatch pipeline of a destination and
+// returns what would be sent, without sending it or recording anything.
+// The multipart body holds the sample in a `file` field and, optionally, an
+// unsaved destination configuration as JSON in a `configuration` field
+#[instrument(skip(db, multipart))]
+pub async fn preview_file_destination(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+    mut multipart: Multipart,
+) -> anyhow::Result<(StatusCode, Json<DestinationPreview>), AppError> {
+    let mut sample = None;
+    let mut configuration = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name() {
+            Some("configuration") => {
+                let contents = field.bytes().await?;
+                configuration = Some(
+                    serde_json::from_slice::<FileDestinationCreation>(&contents).map_err(
+                        |err| {
+                            AppError::Validation(format!(
+                                "Invalid file destination configuration. {}",
+                                err
+                            ))
+                        },
+                    )?,
+                );
+            }
+            _ => sample = Some(field.bytes().await?),
+        }
+    }
+    let sample = sample.ok_or_else(|| {
+        AppError::Validation(String::from(
+            "A sample file should be provided to preview a file destination",
+        ))
+    })?;
+
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &file_source, &mut conn).await?;
+
+    let file_destination = match configuration {
+        Some(configuration) => {
+            if configuration.identifier != identifier {
+                return Err(AppError::Validation(String::from(
+                    "The identifier of the configuration should match the previewed file destination.",
+                )));
+            }
+            validate_file_destination(&configuration, &masking_config)?;
+            let reference_datasets = load_reference_datasets(
+                &context,
+                configuration.transformations.as_deref().unwrap_or_default(),
+                &mut conn,
+            )
+            .await?;
+            validate_against_file_source(
+                &configuration,
+                &file_source,
+                &masking_config,
+                &reference_datasets,
+            )?;
+            let saved_file_destination =
+                find_by_file_source_id_and_identifier(&file_source.id, &identifier, &mut conn)
+                    .await?;
+            unsaved_file_destination(&file_source.id, saved_file_destination, configuration)
+        }
+        None => find_file_destination(&file_source, &identifier, &mut conn).await?,
+    };
+
+    let reference_datasets = load_reference_datasets(
+        &context,
+        file_destination
+            .transformations
+            .as_deref()
+            .unwrap_or_default(),
+        &mut conn,
+    )
+    .await?;
+    info!(
+        "Previewing a {} bytes sample for file destination {}",
+        sample.len(),
+        file_destination.identifier
+    );
+
+    let widest_row = widest_row(&file_source, &sample[..])?;
+    let records = open_file(&file_source, &sample[..])?;
+    let pipeline = DispatchPipeline::new(
+        &file_source,
+        &file_destination,
+        records.headers.as_deref(),
+        widest_row,
+        &masking_config,
+        &reference_datasets,
+    )?;
+    let prepared = pipeline.prepare(records)?;
+    let dispatched_key_hashes =
+        load_dispatched_key_hashes(&file_destination, &prepared.key_hashes(), &mut conn).await?;
+    drop(conn);
+    let plan = pipeline.build_batches(prepared, &dispatched_key_hashes)?;
+
+    let exceeds_error_threshold = exceeds_threshold(
+        &file_source.error_threshold,
+        plan.rejected_row_count,
+        plan.total_rows,
+    );
+    let messages = match exceeds_error_threshold {
+        true => Vec::new(),
+        false => preview_messages(&file_destination, plan.batches)?,
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(DestinationPreview {
+            total_rows: plan.total_rows,
+            rejected_rows: plan.rejected_row_count,
+            filtered_rows: plan.filtered_row_count,
+            duplicate_rows: plan.duplicate_row_count,
+            exceeds_error_threshold,
+            rejections: plan.rejected_rows,
+            messages,
+        }),
+    ))
+}
+
+// The saved destination keeps its id, so deduplication still skips the
+// rows it already dispatched
+fn unsaved_file_destination(
+    file_source_id: &i32,
+    saved_file_destination: Option<FileDestination>,
+    configuration: FileDestinationCreation,
+) -> FileDestination {
+    let (id, version, enabled, paused) = match &saved_file_destination {
+        Some(saved) => (saved.id, saved.version + 1, saved.enabled, saved.paused),
+        None => (0, 1, true, false),
+    };
+    FileDestination {
+        id,
+        file_source_id: *file_source_id,
+        identifier: configuration.identifier,
+        destination: configuration.destination,
+        include_headers: configuration.include_headers,
+        grouping: configuration.grouping,
+        batching: configuration.batching,
+        transformations: configuration.transformations,
+        filter: configuration.filter,
+        masking: configuration.masking,
+        deduplication: configuration.deduplication,
+        mode: configuration.mode,
+        sort_by: configuration.sort_by,
+        version,
+        enabled,
+        paused,
+        created_at: Utc::now(),
+        updated_at: None,
+    }
+}
+
+// Time windows are flushed in chunks of `max_rows`, which is what the
+// sample alone would produce. Rows of other uploads may share the window
+fn preview_messages(
+    file_destination: &FileDestination,
+    batches: Vec<DispatchBatch>,
+) -> anyhow::Result<Vec<PreviewMessage>, AppError> {
+    let batches: Vec<DispatchBatch> = match &file_destination.batching {
+        Some(BatchingConfiguration::TimeWindow { max_rows, .. }) => batches
+            .into_iter()
+            .flat_map(|batch| {
+                let max_rows = (*max_rows).max(1) as usize;
+                batch
+                    .rows
+                    .chunks(max_rows)
+                    .map(|rows| DispatchBatch {
+                        column_names: batch.column_names.clone(),
+                        column_types: batch.column_types.clone(),
+                        group: batch.group.clone(),
+                        headers: batch.headers.clone(),
+                        rows: rows.to_vec(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        _ => batches,
+    };
+
+    let file_name = format!("{}-preview", file_destination.identifier);
+    let mut messages = Vec::with_capacity(batches.len());
+    for (batch_index, batch) in batches.iter().enumerate() {
+        let payload_size = match &file_destination.destination {
+            DestinationConfiguration::SQS { .. } => serde_json::to_string(batch)?.len(),
+            DestinationConfiguration::S3 { format, .. }
+            | DestinationConfiguration::LocalFileSystem { format, .. } => {
+                encode_batch(batch, format)?.len()
+            }
+        };
+        messages.push(PreviewMessage {
+            batch_index,
+            target: batch_target(&file_destination.destination, &file_name, batch_index),
+            group: batch.group.clone(),
+            row_count: batch.rows.len(),
+            payload: serde_json::to_value(batch)?,
+            payload_size,
+        });
+    }
+
+    Ok(messages)
+}
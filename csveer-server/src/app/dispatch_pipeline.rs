@@ -0,0 +1,527 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use sqlx::types::JsonValue;
+
+use crate::{
+    app::{
+        aggregation::{Accumulator, CompiledAggregation},
+        deduplication::CompiledDeduplication,
+        expressions::{BoundExpression, Expression},
+        file_reader::FileRecord,
+        masking::CompiledMasking,
+        reference_dataset::ReferenceDatasets,
+        rejected_rows::RejectedRow,
+        schema_validation::CompiledSchema,
+        sorting::{CompiledSort, SortRuns, SORT_RUN_ROWS},
+        transformations::{CompiledTransformations, TransformationError},
+    },
+    config::{masking::MaskingConfig, server::AppError},
+    data::{
+        data_dispatch::MaskedColumn,
+        file_destination::{
+            BatchingConfiguration, DispatchMode, FileDestination, GroupingConfiguration,
+        },
+        file_source::{ColumnReference, ColumnType, FileSource},
+    },
+};
+
+#[derive(Debug, Serialize)]
+pub struct DispatchBatch {
+    #[serde(skip)]
+    pub column_names: Vec<String>,
+    #[serde(skip)]
+    pub column_types: Vec<Option<ColumnType>>,
+    pub group: Option<Vec<JsonValue>>,
+    pub headers: Option<Vec<String>>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+pub struct DispatchPlan {
+    pub batches: Vec<DispatchBatch>,
+    pub total_rows: usize,
+    pub rejected_row_count: usize,
+    pub filtered_row_count: usize,
+    pub duplicate_row_count: usize,
+    pub key_hashes: Vec<String>,
+    pub masked_columns: Vec<MaskedColumn>,
+    pub rejected_rows: Vec<RejectedRow>,
+}
+
+struct RowGroup {
+    key: Option<Vec<JsonValue>>,
+    rows: Vec<Vec<JsonValue>>,
+    accumulators: Vec<Accumulator>,
+}
+
+#[derive(Default)]
+struct RowGroups {
+    groups: Vec<RowGroup>,
+    positions: HashMap<String, usize>,
+    row_count: usize,
+}
+
+// Every stage of a destination compiled against the columns of one file.
+// Rows are prepared first, so the keys dispatched before only have to be
+// looked up for the keys found in the file, and then cut into batches
+pub struct DispatchPipeline<'a> {
+    file_source: &'a FileSource,
+    file_destination: &'a FileDestination,
+    hidden_columns: HashSet<usize>,
+    transformations: CompiledTransformations<'a>,
+    filter: Option<BoundExpression>,
+    deduplication: CompiledDeduplication,
+    sort: CompiledSort,
+    masking: CompiledMasking<'a>,
+    grouping_columns: Option<Vec<usize>>,
+    aggregation: Option<CompiledAggregation>,
+    column_names: Vec<String>,
+    column_types: Vec<Option<ColumnType>>,
+    headers: Option<Vec<String>>,
+}
+
+enum StagedRows {
+    Collected(Vec<Vec<JsonValue>>),
+    Sorting(SortRuns),
+    Folded(RowGroups),
+}
+
+pub struct PreparedRows {
+    rows: StagedRows,
+    // One hash per row, empty when the destination does not deduplicate
+    row_key_hashes: Vec<String>,
+    total_rows: usize,
+    rejected_row_count: usize,
+    filtered_row_count: usize,
+    rejected_rows: Vec<RejectedRow>,
+}
+
+impl PreparedRows {
+    pub fn key_hashes(&self) -> Vec<String> {
+        let mut key_hashes = self.row_key_hashes.clone();
+        key_hashes.sort_unstable();
+        key_hashes.dedup();
+        key_hashes
+    }
+}
+
+impl<'a> DispatchPipeline<'a> {
+    pub fn new(
+        file_source: &'a FileSource,
+        file_destination: &'a FileDestination,
+        headers: Option<&[String]>,
+        widest_row: usize,
+        masking_config: &'a MaskingConfig,
+        reference_datasets: &'a ReferenceDatasets,
+    ) -> anyhow::Result<Self, AppError> {
+        let file_column_names: Option<Vec<String>> = match (headers, &file_source.schema) {
+            (Some(headers), _) => Some(headers.to_vec()),
+            (None, Some(schema)) => Some(schema.iter().map(|c| c.name.clone()).collect()),
+            (None, None) => None,
+        };
+
+        let hidden_columns: HashSet<usize> = file_source
+            .hide_columns
+            .iter()
+            .flatten()
+            .map(|col| resolve_column(col, file_column_names.as_deref()))
+            .collect::<Result<_, _>>()?;
+
+        let column_count = match &file_column_names {
+            Some(names) => names.len(),
+            None => widest_row,
+        };
+        let source_column_names: Vec<String> = (0..column_count)
+            .map(|idx| match &file_column_names {
+                Some(names) => names[idx].clone(),
+                None => format!("column_{}", idx),
+            })
+            .collect();
+        let source_column_types: Vec<Option<ColumnType>> = (0..column_count)
+            .map(|idx| {
+                file_source
+                    .schema
+                    .as_ref()
+                    .and_then(|schema| schema.get(idx))
+                    .map(|column| column.column_type.clone())
+            })
+            .collect();
+
+        let transformations = CompiledTransformations::new(
+            file_destination
+                .transformations
+                .as_deref()
+                .unwrap_or_default(),
+            source_column_names,
+            source_column_types,
+            reference_datasets,
+        )?;
+
+        let filter = match &file_destination.filter {
+            Some(filter) => Some(
+                Expression::parse(filter)
+                    .and_then(|expression| expression.bind(&transformations.column_names))
+                    .map_err(|err| {
+                        AppError::Validation(format!("Filter '{}' is invalid. {}", filter, err))
+                    })?,
+            ),
+            None => None,
+        };
+
+        let deduplication = CompiledDeduplication::new(
+            file_destination.deduplication.as_ref(),
+            &transformations.column_names,
+        )?;
+
+        let sort = CompiledSort::new(
+            file_destination.sort_by.as_deref().unwrap_or_default(),
+            &transformations.column_names,
+        )?;
+
+        let mut all_column_types = transformations.column_types.clone();
+        let masking = CompiledMasking::new(
+            file_destination.masking.as_deref().unwrap_or_default(),
+            &transformations.column_names,
+            &mut all_column_types,
+            masking_config,
+        )?;
+
+        let grouping_columns: Option<Vec<usize>> = match &file_destination.grouping {
+            Some(GroupingConfiguration::GroupedByColumns { columns }) => Some(
+                columns
+                    .iter()
+                    .map(|col| resolve_column(col, Some(&transformations.column_names)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => None,
+        };
+
+        // Aggregated groups are folded row by row, so only one set of
+        // accumulators per group is kept in memory instead of the group rows
+        let aggregation = match &file_destination.mode {
+            Some(DispatchMode::Aggregate { metrics }) => Some(CompiledAggregation::new(
+                metrics,
+                &transformations.column_names,
+                &all_column_types,
+                grouping_columns.as_deref().unwrap_or_default(),
+            )?),
+            Some(DispatchMode::Rows) | None => None,
+        };
+
+        let is_visible = |idx: &usize| !hidden_columns.contains(idx);
+        let (column_names, column_types, headers) = match &aggregation {
+            Some(aggregation) => (
+                aggregation.column_names.clone(),
+                aggregation.column_types.clone(),
+                file_destination
+                    .include_headers
+                    .then(|| aggregation.column_names.clone()),
+            ),
+            None => {
+                let column_names: Vec<String> = transformations
+                    .column_names
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| is_visible(idx))
+                    .map(|(_, name)| name.clone())
+                    .collect();
+                let column_types: Vec<Option<ColumnType>> = all_column_types
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| is_visible(idx))
+                    .map(|(_, column_type)| column_type.clone())
+                    .collect();
+                let headers = match (file_destination.include_headers, headers) {
+                    (true, Some(_)) => Some(column_names.clone()),
+                    _ => None,
+                };
+                (column_names, column_types, headers)
+            }
+        };
+
+        Ok(Self {
+            file_source,
+            file_destination,
+            hidden_columns,
+            transformations,
+            filter,
+            deduplication,
+            sort,
+            masking,
+            grouping_columns,
+            aggregation,
+            column_names,
+            column_types,
+            headers,
+        })
+    }
+
+    pub fn prepare(
+        &self,
+        records: impl Iterator<Item = anyhow::Result<FileRecord, AppError>>,
+    ) -> anyhow::Result<PreparedRows, AppError> {
+        let schema = self
+            .file_source
+            .schema
+            .as_deref()
+            .map(CompiledSchema::new)
+            .transpose()?;
+
+        // Without deduplication no stage has to see every row first, so rows
+        // are handed to the sort, or folded into their group when nothing is
+        // sorted, as soon as they are read instead of being collected
+        let mut staged = match (
+            self.deduplication.is_empty(),
+            self.sort.is_empty(),
+            self.aggregation.is_some(),
+        ) {
+            (true, false, _) => StagedRows::Sorting(SortRuns::new(SORT_RUN_ROWS)),
+            (true, true, true) => StagedRows::Folded(RowGroups::default()),
+            _ => StagedRows::Collected(Vec::new()),
+        };
+
+        let mut total_rows = 0;
+        let mut rejected_rows = Vec::new();
+        let mut valid_row_count = 0;
+        let mut kept_row_count = 0;
+        for record in records {
+            total_rows += 1;
+            let row = match record? {
+                FileRecord::Row(row) => row,
+                FileRecord::Rejected(rejected_row) => {
+                    rejected_rows.push(rejected_row);
+                    continue;
+                }
+            };
+            let line = row.line;
+            let typed_row = match &schema {
+                Some(schema) => match schema.apply(row) {
+                    Ok(typed_row) => typed_row,
+                    Err(errors) => {
+                        rejected_rows.extend(errors);
+                        continue;
+                    }
+                },
+                None => row.values.into_iter().map(JsonValue::String).collect(),
+            };
+            let mut row = match self.transformations.apply(line, typed_row) {
+                Ok(row) => row,
+                Err(TransformationError::Rejected(rejected_row)) => {
+                    rejected_rows.push(rejected_row);
+                    continue;
+                }
+                Err(TransformationError::Failed(reason)) => {
+                    return Err(AppError::Validation(reason))
+                }
+            };
+            valid_row_count += 1;
+
+            if let Some(filter) = &self.filter {
+                if !filter.matches(&row) {
+                    continue;
+                }
+            }
+            kept_row_count += 1;
+
+            match &mut staged {
+                StagedRows::Collected(rows) => rows.push(row),
+                StagedRows::Sorting(runs) => runs.push(&self.sort, row)?,
+                StagedRows::Folded(groups) => {
+                    self.masking.apply(&mut row);
+                    self.add_to_group(groups, row)?;
+                }
+            }
+        }
+
+        let row_key_hashes = match &staged {
+            StagedRows::Collected(rows) => self.deduplication.key_hashes(rows),
+            _ => Vec::new(),
+        };
+
+        Ok(PreparedRows {
+            rows: staged,
+            row_key_hashes,
+            total_rows,
+            rejected_row_count: total_rows - valid_row_count,
+            filtered_row_count: valid_row_count - kept_row_count,
+            rejected_rows,
+        })
+    }
+
+    pub fn build_batches(
+        self,
+        prepared: PreparedRows,
+        dispatched_key_hashes: &HashSet<String>,
+    ) -> anyhow::Result<DispatchPlan, AppError> {
+        let (groups, duplicate_row_count, key_hashes) = match prepared.rows {
+            StagedRows::Folded(groups) => (groups, 0, Vec::new()),
+            StagedRows::Sorting(runs) => (
+                self.group_rows(runs.into_sorted(&self.sort)?)?,
+                0,
+                Vec::new(),
+            ),
+            StagedRows::Collected(rows) => {
+                let deduplicated =
+                    self.deduplication
+                        .apply(rows, prepared.row_key_hashes, dispatched_key_hashes);
+                let groups = match self.sort.is_empty() {
+                    true => self.group_rows(deduplicated.rows.into_iter().map(Ok))?,
+                    false => {
+                        let mut runs = SortRuns::new(SORT_RUN_ROWS);
+                        for row in deduplicated.rows {
+                            runs.push(&self.sort, row)?;
+                        }
+                        self.group_rows(runs.into_sorted(&self.sort)?)?
+                    }
+                };
+                (
+                    groups,
+                    deduplicated.duplicate_row_count,
+                    deduplicated.key_hashes,
+                )
+            }
+        };
+
+        let mut batches = Vec::new();
+        for group in groups.groups {
+            if let Some(aggregation) = &self.aggregation {
+                batches.push(DispatchBatch {
+                    column_names: self.column_names.clone(),
+                    column_types: self.column_types.clone(),
+                    rows: vec![aggregation.finish(group.key.as_deref(), &group.accumulators)],
+                    group: group.key,
+                    headers: self.headers.clone(),
+                });
+                continue;
+            }
+
+            let batch_size = match &self.file_destination.batching {
+                Some(BatchingConfiguration::Fixed { batch_size }) => *batch_size as usize,
+                // Time windows are cut into batches when they are flushed
+                Some(BatchingConfiguration::TimeWindow { .. }) | None => group.rows.len().max(1),
+            };
+            for rows in group.rows.chunks(batch_size) {
+                batches.push(DispatchBatch {
+                    column_names: self.column_names.clone(),
+                    column_types: self.column_types.clone(),
+                    group: group.key.clone(),
+                    headers: self.headers.clone(),
+                    rows: rows.to_vec(),
+                });
+            }
+        }
+
+        Ok(DispatchPlan {
+            batches,
+            total_rows: prepared.total_rows,
+            rejected_row_count: prepared.rejected_row_count,
+            filtered_row_count: prepared.filtered_row_count,
+            duplicate_row_count,
+            key_hashes,
+            masked_columns: self.masking.masked_columns,
+            rejected_rows: prepared.rejected_rows,
+        })
+    }
+
+    // Grouping keeps the order of the rows, so sorted rows give sorted
+    // groups. Rows are sorted before masking so masked columns still sort by
+    // their original values
+    fn group_rows(
+        &self,
+        rows: impl Iterator<Item = anyhow::Result<Vec<JsonValue>, AppError>>,
+    ) -> anyhow::Result<RowGroups, AppError> {
+        let mut groups = RowGroups::default();
+        for row in rows {
+            let mut row = row?;
+            self.masking.apply(&mut row);
+            self.add_to_group(&mut groups, row)?;
+        }
+        Ok(groups)
+    }
+
+    fn add_to_group(
+        &self,
+        groups: &mut RowGroups,
+        row: Vec<JsonValue>,
+    ) -> anyhow::Result<(), AppError> {
+        let row_idx = groups.row_count;
+        groups.row_count += 1;
+
+        let key = match &self.grouping_columns {
+            Some(columns) => {
+                let mut key = Vec::with_capacity(columns.len());
+                for col in columns {
+                    match row.get(*col) {
+                        Some(value) => key.push(value.clone()),
+                        None => {
+                            return Err(AppError::Validation(format!(
+                                "Cannot group row {} by column {}, row only has {} columns",
+                                row_idx,
+                                col,
+                                row.len()
+                            )))
+                        }
+                    }
+                }
+                Some(key)
+            }
+            None => None,
+        };
+
+        let group_id = serde_json::to_string(&key)?;
+        let position = match groups.positions.get(&group_id) {
+            Some(position) => *position,
+            None => {
+                groups.positions.insert(group_id, groups.groups.len());
+                groups.groups.push(RowGroup {
+                    key,
+                    rows: Vec::new(),
+                    accumulators: self
+                        .aggregation
+                        .as_ref()
+                        .map(|aggregation| aggregation.accumulators())
+                        .unwrap_or_default(),
+                });
+                groups.groups.len() - 1
+            }
+        };
+
+        match &self.aggregation {
+            Some(aggregation) => {
+                aggregation.update(&mut groups.groups[position].accumulators, &row)?;
+            }
+            None => groups.groups[position].rows.push(
+                row.into_iter()
+                    .enumerate()
+                    .filter(|(idx, _)| !self.hidden_columns.contains(idx))
+                    .map(|(_, value)| value)
+                    .collect(),
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+pub fn resolve_column(
+    reference: &ColumnReference,
+    column_names: Option<&[String]>,
+) -> anyhow::Result<usize, AppError> {
+    match (reference, column_names) {
+        (ColumnReference::Index(idx), _) => Ok(*idx as usize),
+        (ColumnReference::Name(name), Some(column_names)) => column_names
+            .iter()
+            .position(|column_name| column_name == name)
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Column '{}' is not present in the header row of the file. Found: {}",
+                    name,
+                    column_names.join(", ")
+                ))
+            }),
+        (ColumnReference::Name(name), None) => Err(AppError::Validation(format!(
+            "Column '{}' cannot be resolved, the file has no header row",
+            name
+        ))),
+    }
+}
@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+
+use sqlx::types::JsonValue;
+
+use super::{ArithmeticOperator, CompareOperator, Expr, Function};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl From<&JsonValue> for Value {
+    fn from(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::Null => Value::Null,
+            JsonValue::Bool(value) => Value::Boolean(*value),
+            JsonValue::Number(number) => number.as_f64().map_or(Value::Null, Value::Number),
+            JsonValue::String(text) => Value::Text(text.clone()),
+            other => Value::Text(other.to_string()),
+        }
+    }
+}
+
+// Comparisons and logic follow SQL three-valued semantics: anything compared
+// with NULL, or with a value that cannot be coerced, is unknown (Null)
+pub fn evaluate(expr: &Expr<usize>, row: &[JsonValue]) -> Value {
+    match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Column(idx) => row.get(*idx).map_or(Value::Null, Value::from),
+        Expr::Negate(expr) => match as_number(&evaluate(expr, row)) {
+            Some(number) => Value::Number(-number),
+            None => Value::Null,
+        },
+        Expr::Arithmetic(operator, left, right) => {
+            let (left, right) = match (
+                as_number(&evaluate(left, row)),
+                as_number(&evaluate(right, row)),
+            ) {
+                (Some(left), Some(right)) => (left, right),
+                _ => return Value::Null,
+            };
+            let result = match operator {
+                ArithmeticOperator::Add => left + right,
+                ArithmeticOperator::Subtract => left - right,
+                ArithmeticOperator::Multiply => left * right,
+                ArithmeticOperator::Divide => left / right,
+                ArithmeticOperator::Remainder => left % right,
+            };
+            match result.is_finite() {
+                true => Value::Number(result),
+                false => Value::Null,
+            }
+        }
+        Expr::Concat(left, right) => match (evaluate(left, row), evaluate(right, row)) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (left, right) => Value::Text(format!("{}{}", as_text(&left), as_text(&right))),
+        },
+        Expr::Function(function, args) => call(*function, args, row),
+        Expr::Not(expr) => match evaluate(expr, row) {
+            Value::Boolean(value) => Value::Boolean(!value),
+            _ => Value::Null,
+        },
+        Expr::And(left, right) => match (evaluate(left, row), evaluate(right, row)) {
+            (Value::Boolean(false), _) | (_, Value::Boolean(false)) => Value::Boolean(false),
+            (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+            _ => Value::Null,
+        },
+        Expr::Or(left, right) => match (evaluate(left, row), evaluate(right, row)) {
+            (Value::Boolean(true), _) | (_, Value::Boolean(true)) => Value::Boolean(true),
+            (Value::Boolean(false), Value::Boolean(false)) => Value::Boolean(false),
+            _ => Value::Null,
+        },
+        Expr::Compare(operator, left, right) => {
+            match compare(&evaluate(left, row), &evaluate(right, row)) {
+                Some(ordering) => Value::Boolean(match operator {
+                    CompareOperator::Equal => ordering == Ordering::Equal,
+                    CompareOperator::NotEqual => ordering != Ordering::Equal,
+                    CompareOperator::Less => ordering == Ordering::Less,
+                    CompareOperator::LessOrEqual => ordering != Ordering::Greater,
+                    CompareOperator::Greater => ordering == Ordering::Greater,
+                    CompareOperator::GreaterOrEqual => ordering != Ordering::Less,
+                }),
+                None => Value::Null,
+            }
+        }
+        Expr::IsNull { expr, negated } => {
+            let is_null = evaluate(expr, row) == Value::Null;
+            Value::Boolean(is_null != *negated)
+        }
+        Expr::In {
+            expr,
+            values,
+            negated,
+        } => {
+            let value = evaluate(expr, row);
+            if value == Value::Null {
+                return Value::Null;
+            }
+            let found = values.iter().any(|candidate| {
+                compare(&value, &evaluate(candidate, row)) == Some(Ordering::Equal)
+            });
+            Value::Boolean(found != *negated)
+        }
+    }
+}
+
+fn call(function: Function, args: &[Expr<usize>], row: &[JsonValue]) -> Value {
+    if function == Function::Coalesce {
+        return args
+            .iter()
+            .map(|arg| evaluate(arg, row))
+            .find(|value| *value != Value::Null)
+            .unwrap_or(Value::Null);
+    }
+
+    let value = evaluate(&args[0], row);
+    if value == Value::Null {
+        return Value::Null;
+    }
+    match function {
+        Function::Round => {
+            let digits = match args.get(1).map(|arg| as_number(&evaluate(arg, row))) {
+                Some(Some(digits)) => digits as i32,
+                Some(None) => return Value::Null,
+                None => 0,
+            };
+            let factor = 10f64.powi(digits);
+            as_number(&value).map_or(Value::Null, |number| {
+                Value::Number((number * factor).round() / factor)
+            })
+        }
+        Function::Floor => as_number(&value).map_or(Value::Null, |n| Value::Number(n.floor())),
+        Function::Ceil => as_number(&value).map_or(Value::Null, |n| Value::Number(n.ceil())),
+        Function::Abs => as_number(&value).map_or(Value::Null, |n| Value::Number(n.abs())),
+        Function::Upper => Value::Text(as_text(&value).to_uppercase()),
+        Function::Lower => Value::Text(as_text(&value).to_lowercase()),
+        Function::Trim => Value::Text(as_text(&value).trim().to_string()),
+        Function::Length => Value::Number(as_text(&value).chars().count() as f64),
+        Function::Coalesce => unreachable!(),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => Some(*number),
+        Value::Text(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::Text(text) => text.clone(),
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.partial_cmp(right),
+        (Value::Number(left), Value::Text(right)) => left.partial_cmp(&right.trim().parse().ok()?),
+        (Value::Text(left), Value::Number(right)) => {
+            left.trim().parse::<f64>().ok()?.partial_cmp(right)
+        }
+        (Value::Text(left), Value::Text(right)) => Some(left.cmp(right)),
+        (Value::Boolean(left), Value::Boolean(right)) => Some(left.cmp(right)),
+        (Value::Boolean(left), Value::Text(right)) => Some(left.cmp(&parse_boolean(right)?)),
+        (Value::Text(left), Value::Boolean(right)) => Some(parse_boolean(left)?.cmp(right)),
+        _ => None,
+    }
+}
+
+fn parse_boolean(text: &str) -> Option<bool> {
+    match text.trim().to_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+impl From<Value> for JsonValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => JsonValue::Null,
+            Value::Boolean(value) => JsonValue::Bool(value),
+            // Whole numbers are written as integers, e.g. round(amount * 100)
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < 9e15 => {
+                JsonValue::from(number as i64)
+            }
+            Value::Number(number) => JsonValue::from(number),
+            Value::Text(text) => JsonValue::String(text),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Text(String),
+    Identifier(String),
+    QuotedIdentifier(String),
+    Index(i32),
+    And,
+    Or,
+    Not,
+    Is,
+    In,
+    Null,
+    True,
+    False,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Concat,
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let start = pos;
+        let c = chars[pos];
+        let token = match c {
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            '(' => {
+                pos += 1;
+                Token::LeftParen
+            }
+            ')' => {
+                pos += 1;
+                Token::RightParen
+            }
+            ',' => {
+                pos += 1;
+                Token::Comma
+            }
+            '+' => {
+                pos += 1;
+                Token::Plus
+            }
+            '-' => {
+                pos += 1;
+                Token::Minus
+            }
+            '*' => {
+                pos += 1;
+                Token::Star
+            }
+            '/' => {
+                pos += 1;
+                Token::Slash
+            }
+            '%' => {
+                pos += 1;
+                Token::Percent
+            }
+            '|' if chars.get(pos + 1) == Some(&'|') => {
+                pos += 2;
+                Token::Concat
+            }
+            '=' => {
+                pos += if chars.get(pos + 1) == Some(&'=') {
+                    2
+                } else {
+                    1
+                };
+                Token::Equal
+            }
+            '!' if chars.get(pos + 1) == Some(&'=') => {
+                pos += 2;
+                Token::NotEqual
+            }
+            '<' => match chars.get(pos + 1) {
+                Some('=') => {
+                    pos += 2;
+                    Token::LessOrEqual
+                }
+                Some('>') => {
+                    pos += 2;
+                    Token::NotEqual
+                }
+                _ => {
+                    pos += 1;
+                    Token::Less
+                }
+            },
+            '>' => match chars.get(pos + 1) {
+                Some('=') => {
+                    pos += 2;
+                    Token::GreaterOrEqual
+                }
+                _ => {
+                    pos += 1;
+                    Token::Greater
+                }
+            },
+            '\'' | '"' => {
+                let (value, end) = read_quoted(&chars, pos)?;
+                pos = end;
+                match c {
+                    '\'' => Token::Text(value),
+                    _ => Token::QuotedIdentifier(value),
+                }
+            }
+            '$' => {
+                pos += 1;
+                let digits = read_while(&chars, &mut pos, |c| c.is_ascii_digit());
+                match digits.parse() {
+                    Ok(idx) => Token::Index(idx),
+                    Err(_) => {
+                        return Err(format!("Expected a column index after '$' at {}", start))
+                    }
+                }
+            }
+            c if c.is_ascii_digit() => {
+                let number = read_while(&chars, &mut pos, |c| c.is_ascii_digit() || c == '.');
+                match number.parse() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) => return Err(format!("Invalid number '{}' at {}", number, start)),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let word = read_while(&chars, &mut pos, |c| c.is_alphanumeric() || c == '_');
+                match word.to_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "IS" => Token::Is,
+                    "IN" => Token::In,
+                    "NULL" => Token::Null,
+                    "TRUE" => Token::True,
+                    "FALSE" => Token::False,
+                    _ => Token::Identifier(word),
+                }
+            }
+            c => return Err(format!("Unexpected character '{}' at {}", c, start)),
+        };
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+fn read_while(chars: &[char], pos: &mut usize, predicate: impl Fn(char) -> bool) -> String {
+    let start = *pos;
+    while *pos < chars.len() && predicate(chars[*pos]) {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+// Quotes are escaped by doubling them, as in SQL
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut pos = start + 1;
+    loop {
+        match chars.get(pos) {
+            None => return Err(format!("Unterminated quote starting at {}", start)),
+            Some(c) if *c == quote => {
+                if chars.get(pos + 1) == Some(&quote) {
+                    value.push(quote);
+                    pos += 2;
+                } else {
+                    return Ok((value, pos + 1));
+                }
+            }
+            Some(c) => {
+                value.push(*c);
+                pos += 1;
+            }
+        }
+    }
+}
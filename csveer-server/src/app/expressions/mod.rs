@@ -0,0 +1,324 @@
+use sqlx::types::JsonValue;
+
+use crate::data::file_source::{ColumnReference, ColumnType};
+
+use self::{
+    eval::{evaluate, Value},
+    typecheck::{check, ExprType},
+};
+
+mod eval;
+mod lexer;
+mod parser;
+mod typecheck;
+
+#[derive(Debug, Clone, Copy)]
+pub enum CompareOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Round,
+    Floor,
+    Ceil,
+    Abs,
+    Upper,
+    Lower,
+    Trim,
+    Length,
+    Coalesce,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "round" => Some(Function::Round),
+            "floor" => Some(Function::Floor),
+            "ceil" => Some(Function::Ceil),
+            "abs" => Some(Function::Abs),
+            "upper" => Some(Function::Upper),
+            "lower" => Some(Function::Lower),
+            "trim" => Some(Function::Trim),
+            "length" => Some(Function::Length),
+            "coalesce" => Some(Function::Coalesce),
+            _ => None,
+        }
+    }
+
+    // Minimum and maximum number of arguments
+    pub fn arity(&self) -> (usize, usize) {
+        match self {
+            Function::Round => (1, 2),
+            Function::Coalesce => (1, usize::MAX),
+            _ => (1, 1),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Expr<C> {
+    Literal(Value),
+    Column(C),
+    Negate(Box<Expr<C>>),
+    Arithmetic(ArithmeticOperator, Box<Expr<C>>, Box<Expr<C>>),
+    Concat(Box<Expr<C>>, Box<Expr<C>>),
+    Function(Function, Vec<Expr<C>>),
+    Not(Box<Expr<C>>),
+    And(Box<Expr<C>>, Box<Expr<C>>),
+    Or(Box<Expr<C>>, Box<Expr<C>>),
+    Compare(CompareOperator, Box<Expr<C>>, Box<Expr<C>>),
+    IsNull {
+        expr: Box<Expr<C>>,
+        negated: bool,
+    },
+    In {
+        expr: Box<Expr<C>>,
+        values: Vec<Expr<C>>,
+        negated: bool,
+    },
+}
+
+impl<C> Expr<C> {
+    fn map_columns<D>(
+        &self,
+        resolve: &impl Fn(&C) -> Result<D, String>,
+    ) -> Result<Expr<D>, String> {
+        let map = |expr: &Expr<C>| expr.map_columns(resolve).map(Box::new);
+        Ok(match self {
+            Expr::Literal(value) => Expr::Literal(value.clone()),
+            Expr::Column(column) => Expr::Column(resolve(column)?),
+            Expr::Negate(expr) => Expr::Negate(map(expr)?),
+            Expr::Arithmetic(operator, left, right) => {
+                Expr::Arithmetic(*operator, map(left)?, map(right)?)
+            }
+            Expr::Concat(left, right) => Expr::Concat(map(left)?, map(right)?),
+            Expr::Function(function, args) => Expr::Function(
+                *function,
+                args.iter()
+                    .map(|arg| arg.map_columns(resolve))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::Not(expr) => Expr::Not(map(expr)?),
+            Expr::And(left, right) => Expr::And(map(left)?, map(right)?),
+            Expr::Or(left, right) => Expr::Or(map(left)?, map(right)?),
+            Expr::Compare(operator, left, right) => {
+                Expr::Compare(*operator, map(left)?, map(right)?)
+            }
+            Expr::IsNull { expr, negated } => Expr::IsNull {
+                expr: map(expr)?,
+                negated: *negated,
+            },
+            Expr::In {
+                expr,
+                values,
+                negated,
+            } => Expr::In {
+                expr: map(expr)?,
+                values: values
+                    .iter()
+                    .map(|value| value.map_columns(resolve))
+                    .collect::<Result<_, _>>()?,
+                negated: *negated,
+            },
+        })
+    }
+}
+
+pub struct Expression {
+    root: Expr<ColumnReference>,
+}
+
+pub struct BoundExpression {
+    root: Expr<usize>,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        Ok(Self {
+            root: parser::parse(source)?,
+        })
+    }
+
+    // Without column names every column is typed as unknown, only literals
+    // and operators can then be checked
+    fn check_with_columns(
+        &self,
+        column_names: Option<&[String]>,
+        column_types: &[Option<ColumnType>],
+    ) -> Result<ExprType, String> {
+        check(&self.root, &|column: &ColumnReference| match column_names {
+            Some(column_names) => {
+                let idx = resolve_column(column, column_names)?;
+                Ok(ExprType::from(
+                    column_types.get(idx).and_then(|t| t.as_ref()),
+                ))
+            }
+            None => Ok(ExprType::Unknown),
+        })
+    }
+
+    pub fn check_filter(
+        &self,
+        column_names: Option<&[String]>,
+        column_types: &[Option<ColumnType>],
+    ) -> Result<(), String> {
+        match self.check_with_columns(column_names, column_types)? {
+            ExprType::Boolean => Ok(()),
+            other => Err(format!(
+                "Filter should evaluate to a boolean, found {:?}",
+                other
+            )),
+        }
+    }
+
+    // Type of the column computed by this expression, None when it can only
+    // be known while dispatching
+    pub fn computed_column_type(
+        &self,
+        column_names: Option<&[String]>,
+        column_types: &[Option<ColumnType>],
+    ) -> Result<Option<ColumnType>, String> {
+        Ok(match self.check_with_columns(column_names, column_types)? {
+            ExprType::Boolean => Some(ColumnType::Boolean),
+            ExprType::Number => Some(ColumnType::Decimal {
+                min: None,
+                max: None,
+            }),
+            ExprType::Text | ExprType::Null | ExprType::Unknown => None,
+        })
+    }
+
+    pub fn bind(&self, column_names: &[String]) -> Result<BoundExpression, String> {
+        Ok(BoundExpression {
+            root: self
+                .root
+                .map_columns(&|column| resolve_column(column, column_names))?,
+        })
+    }
+}
+
+impl BoundExpression {
+    pub fn matches(&self, row: &[JsonValue]) -> bool {
+        evaluate(&self.root, row) == Value::Boolean(true)
+    }
+
+    pub fn evaluate(&self, row: &[JsonValue]) -> JsonValue {
+        evaluate(&self.root, row).into()
+    }
+}
+
+fn resolve_column(reference: &ColumnReference, column_names: &[String]) -> Result<usize, String> {
+    let column = match reference {
+        ColumnReference::Index(idx) => Some(*idx as usize).filter(|idx| *idx < column_names.len()),
+        ColumnReference::Name(name) => column_names.iter().position(|c| c == name),
+    };
+    column.ok_or_else(|| {
+        format!(
+            "Column {} does not exist. Found: {}",
+            reference,
+            column_names.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::types::JsonValue;
+
+    use super::{BoundExpression, Expression};
+
+    fn bind(source: &str) -> BoundExpression {
+        let column_names = vec![
+            String::from("amount"),
+            String::from("status"),
+            String::from("flag"),
+        ];
+        Expression::parse(source)
+            .unwrap()
+            .bind(&column_names)
+            .unwrap()
+    }
+
+    fn evaluate(source: &str, row: &[JsonValue]) -> JsonValue {
+        bind(source).evaluate(row)
+    }
+
+    fn matches(source: &str, row: &[JsonValue]) -> bool {
+        bind(source).matches(row)
+    }
+
+    #[test]
+    fn should_compare_null_as_unknown() {
+        let row = [JsonValue::Null, json!("open"), json!(true)];
+
+        assert_eq!(evaluate("amount > 10", &row), JsonValue::Null);
+        assert_eq!(evaluate("amount = NULL", &row), JsonValue::Null);
+        assert_eq!(evaluate("NOT (amount > 10)", &row), JsonValue::Null);
+        assert!(!matches("amount > 10", &row));
+        assert!(!matches("NOT (amount > 10)", &row));
+    }
+
+    #[test]
+    fn should_follow_three_valued_and_or() {
+        let row = [JsonValue::Null, json!("open"), json!(true)];
+
+        assert_eq!(evaluate("amount > 10 AND FALSE", &row), json!(false));
+        assert_eq!(evaluate("amount > 10 AND TRUE", &row), JsonValue::Null);
+        assert_eq!(evaluate("amount > 10 OR TRUE", &row), json!(true));
+        assert_eq!(evaluate("amount > 10 OR FALSE", &row), JsonValue::Null);
+        assert!(matches("amount > 10 OR flag", &row));
+    }
+
+    #[test]
+    fn should_treat_uncoercible_values_as_unknown() {
+        let row = [json!("abc"), json!("open"), json!("yes")];
+
+        assert_eq!(evaluate("amount > 10", &row), JsonValue::Null);
+        assert_eq!(evaluate("amount + 1", &row), JsonValue::Null);
+        assert_eq!(evaluate("flag = TRUE", &row), JsonValue::Null);
+    }
+
+    #[test]
+    fn should_coerce_text_to_numbers_and_booleans() {
+        let row = [json!(" 12.5 "), json!("open"), json!("TRUE")];
+
+        assert_eq!(evaluate("amount > 10", &row), json!(true));
+        assert_eq!(evaluate("amount * 2", &row), json!(25));
+        assert_eq!(evaluate("flag = TRUE", &row), json!(true));
+    }
+
+    #[test]
+    fn should_only_test_null_with_is_null() {
+        let row = [JsonValue::Null, json!("open"), json!(true)];
+
+        assert_eq!(evaluate("amount IS NULL", &row), json!(true));
+        assert_eq!(evaluate("status IS NOT NULL", &row), json!(true));
+        assert_eq!(evaluate("amount IN (1, 2)", &row), JsonValue::Null);
+        assert_eq!(evaluate("status NOT IN ('closed')", &row), json!(true));
+        assert_eq!(evaluate("coalesce(amount, 0) + 1", &row), json!(1));
+    }
+
+    #[test]
+    fn should_leave_division_by_zero_null() {
+        let row = [json!(5), json!("open"), json!(true)];
+
+        assert_eq!(evaluate("amount / 0", &row), JsonValue::Null);
+        assert_eq!(evaluate("amount || status", &row), json!("5open"));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::data::file_source::ColumnReference;
+
+use super::{
+    eval::Value,
+    lexer::{tokenize, Token},
+    ArithmeticOperator, CompareOperator, Expr, Function,
+};
+
+pub fn parse(source: &str) -> Result<Expr<ColumnReference>, String> {
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err(String::from("Expression should not be blank"));
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        source_length: source.chars().count(),
+    };
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("Unexpected {:?} at {}", token, parser.position())),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    source_length: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.source_length, |(pos, _)| *pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn accept(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), String> {
+        let position = self.position();
+        match self.next() {
+            Some(token) if token == *expected => Ok(()),
+            Some(token) => Err(format!(
+                "Expected {:?} but found {:?} at {}",
+                expected, token, position
+            )),
+            None => Err(format!("Expected {:?} but the expression ended", expected)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr<ColumnReference>, String> {
+        let mut expr = self.parse_and()?;
+        while self.accept(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr<ColumnReference>, String> {
+        let mut expr = self.parse_not()?;
+        while self.accept(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr<ColumnReference>, String> {
+        if self.accept(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr<ColumnReference>, String> {
+        let left = self.parse_operand()?;
+
+        let operator = match self.peek() {
+            Some(Token::Equal) => Some(CompareOperator::Equal),
+            Some(Token::NotEqual) => Some(CompareOperator::NotEqual),
+            Some(Token::Less) => Some(CompareOperator::Less),
+            Some(Token::LessOrEqual) => Some(CompareOperator::LessOrEqual),
+            Some(Token::Greater) => Some(CompareOperator::Greater),
+            Some(Token::GreaterOrEqual) => Some(CompareOperator::GreaterOrEqual),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            self.pos += 1;
+            let right = self.parse_operand()?;
+            return Ok(Expr::Compare(operator, Box::new(left), Box::new(right)));
+        }
+
+        if self.accept(&Token::Is) {
+            let negated = self.accept(&Token::Not);
+            self.expect(&Token::Null)?;
+            return Ok(Expr::IsNull {
+                expr: Box::new(left),
+                negated,
+            });
+        }
+
+        let negated = self.accept(&Token::Not);
+        if self.accept(&Token::In) {
+            self.expect(&Token::LeftParen)?;
+            let mut values = vec![self.parse_operand()?];
+            while self.accept(&Token::Comma) {
+                values.push(self.parse_operand()?);
+            }
+            self.expect(&Token::RightParen)?;
+            return Ok(Expr::In {
+                expr: Box::new(left),
+                values,
+                negated,
+            });
+        }
+        if negated {
+            return Err(format!("Expected IN at {}", self.position()));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr<ColumnReference>, String> {
+        let mut expr = self.parse_term()?;
+        loop {
+            if self.accept(&Token::Concat) {
+                expr = Expr::Concat(Box::new(expr), Box::new(self.parse_term()?));
+                continue;
+            }
+            let operator = match self.peek() {
+                Some(Token::Plus) => ArithmeticOperator::Add,
+                Some(Token::Minus) => ArithmeticOperator::Subtract,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            expr = Expr::Arithmetic(operator, Box::new(expr), Box::new(self.parse_term()?));
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Expr<ColumnReference>, String> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Star) => ArithmeticOperator::Multiply,
+                Some(Token::Slash) => ArithmeticOperator::Divide,
+                Some(Token::Percent) => ArithmeticOperator::Remainder,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            expr = Expr::Arithmetic(operator, Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr<ColumnReference>, String> {
+        if self.accept(&Token::Minus) {
+            return Ok(match self.parse_unary()? {
+                Expr::Literal(Value::Number(number)) => Expr::Literal(Value::Number(-number)),
+                expr => Expr::Negate(Box::new(expr)),
+            });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_function(
+        &mut self,
+        name: &str,
+        position: usize,
+    ) -> Result<Expr<ColumnReference>, String> {
+        let function = Function::from_name(name)
+            .ok_or_else(|| format!("Unknown function '{}' at {}", name, position))?;
+        let mut args = Vec::new();
+        if !self.accept(&Token::RightParen) {
+            args.push(self.parse_or()?);
+            while self.accept(&Token::Comma) {
+                args.push(self.parse_or()?);
+            }
+            self.expect(&Token::RightParen)?;
+        }
+        let (min, max) = function.arity();
+        if args.len() < min || args.len() > max {
+            return Err(format!(
+                "Function '{}' at {} does not accept {} arguments",
+                name,
+                position,
+                args.len()
+            ));
+        }
+        Ok(Expr::Function(function, args))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr<ColumnReference>, String> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Literal(Value::Number(number))),
+            Some(Token::Text(text)) => Ok(Expr::Literal(Value::Text(text))),
+            Some(Token::True) => Ok(Expr::Literal(Value::Boolean(true))),
+            Some(Token::False) => Ok(Expr::Literal(Value::Boolean(false))),
+            Some(Token::Null) => Ok(Expr::Literal(Value::Null)),
+            Some(Token::Identifier(name)) if self.accept(&Token::LeftParen) => {
+                self.parse_function(&name, position)
+            }
+            Some(Token::Identifier(name)) | Some(Token::QuotedIdentifier(name)) => {
+                Ok(Expr::Column(ColumnReference::Name(name)))
+            }
+            Some(Token::Index(idx)) => Ok(Expr::Column(ColumnReference::Index(idx))),
+            Some(Token::LeftParen) => {
+                let expr = self.parse_or()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expr)
+            }
+            Some(token) => Err(format!("Unexpected {:?} at {}", token, position)),
+            None => Err(String::from("Unexpected end of expression")),
+        }
+    }
+}
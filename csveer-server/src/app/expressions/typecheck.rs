@@ -0,0 +1,137 @@
+use crate::data::file_source::ColumnType;
+
+use super::{eval::Value, Expr, Function};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExprType {
+    Boolean,
+    Number,
+    Text,
+    Null,
+    // Columns without a declared type are only known at dispatch time
+    Unknown,
+}
+
+impl From<Option<&ColumnType>> for ExprType {
+    fn from(column_type: Option<&ColumnType>) -> Self {
+        match column_type {
+            Some(ColumnType::Integer { .. }) | Some(ColumnType::Decimal { .. }) => ExprType::Number,
+            Some(ColumnType::Boolean) => ExprType::Boolean,
+            Some(_) => ExprType::Text,
+            None => ExprType::Unknown,
+        }
+    }
+}
+
+pub fn check<C>(
+    expr: &Expr<C>,
+    column_type: &impl Fn(&C) -> Result<ExprType, String>,
+) -> Result<ExprType, String> {
+    match expr {
+        Expr::Literal(value) => Ok(match value {
+            Value::Null => ExprType::Null,
+            Value::Boolean(_) => ExprType::Boolean,
+            Value::Number(_) => ExprType::Number,
+            Value::Text(_) => ExprType::Text,
+        }),
+        Expr::Column(column) => column_type(column),
+        Expr::Negate(expr) => {
+            expect_number(check(expr, column_type)?, "-")?;
+            Ok(ExprType::Number)
+        }
+        Expr::Arithmetic(_, left, right) => {
+            expect_number(check(left, column_type)?, "Arithmetic")?;
+            expect_number(check(right, column_type)?, "Arithmetic")?;
+            Ok(ExprType::Number)
+        }
+        Expr::Concat(left, right) => {
+            check(left, column_type)?;
+            check(right, column_type)?;
+            Ok(ExprType::Text)
+        }
+        Expr::Function(function, args) => {
+            let arg_types = args
+                .iter()
+                .map(|arg| check(arg, column_type))
+                .collect::<Result<Vec<_>, _>>()?;
+            match function {
+                Function::Round | Function::Floor | Function::Ceil | Function::Abs => {
+                    for arg_type in arg_types {
+                        expect_number(arg_type, &format!("{:?}", function))?;
+                    }
+                    Ok(ExprType::Number)
+                }
+                Function::Upper | Function::Lower | Function::Trim => Ok(ExprType::Text),
+                Function::Length => Ok(ExprType::Number),
+                Function::Coalesce => {
+                    let mut result = ExprType::Null;
+                    for arg_type in arg_types {
+                        expect_comparable(result, arg_type)?;
+                        if result == ExprType::Null {
+                            result = arg_type;
+                        }
+                    }
+                    Ok(result)
+                }
+            }
+        }
+        Expr::Not(expr) => {
+            expect_boolean(check(expr, column_type)?, "NOT")?;
+            Ok(ExprType::Boolean)
+        }
+        Expr::And(left, right) => {
+            expect_boolean(check(left, column_type)?, "AND")?;
+            expect_boolean(check(right, column_type)?, "AND")?;
+            Ok(ExprType::Boolean)
+        }
+        Expr::Or(left, right) => {
+            expect_boolean(check(left, column_type)?, "OR")?;
+            expect_boolean(check(right, column_type)?, "OR")?;
+            Ok(ExprType::Boolean)
+        }
+        Expr::Compare(_, left, right) => {
+            expect_comparable(check(left, column_type)?, check(right, column_type)?)?;
+            Ok(ExprType::Boolean)
+        }
+        Expr::IsNull { expr, .. } => {
+            check(expr, column_type)?;
+            Ok(ExprType::Boolean)
+        }
+        Expr::In { expr, values, .. } => {
+            let expr_type = check(expr, column_type)?;
+            for value in values {
+                expect_comparable(expr_type, check(value, column_type)?)?;
+            }
+            Ok(ExprType::Boolean)
+        }
+    }
+}
+
+fn expect_boolean(expr_type: ExprType, operator: &str) -> Result<(), String> {
+    match expr_type {
+        ExprType::Boolean | ExprType::Null | ExprType::Unknown => Ok(()),
+        other => Err(format!(
+            "{} expects boolean operands, found {:?}",
+            operator, other
+        )),
+    }
+}
+
+fn expect_number(expr_type: ExprType, operator: &str) -> Result<(), String> {
+    match expr_type {
+        ExprType::Number | ExprType::Null | ExprType::Unknown => Ok(()),
+        other => Err(format!(
+            "{} expects numeric operands, found {:?}",
+            operator, other
+        )),
+    }
+}
+
+fn expect_comparable(left: ExprType, right: ExprType) -> Result<(), String> {
+    match (left, right) {
+        (ExprType::Unknown, _) | (_, ExprType::Unknown) => Ok(()),
+        (ExprType::Null, _) | (_, ExprType::Null) => Ok(()),
+        (left, right) if left == right => Ok(()),
+        (left, right) => Err(format!("Cannot compare {:?} with {:?}", left, right)),
+    }
+}
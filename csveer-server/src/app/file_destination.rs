@@ -1,18 +1,40 @@
+use aws_sdk_sqs::Client as SQSClient;
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
 };
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use tracing::{info, instrument};
 
 use crate::{
-    config::server::AppError,
+    app::{
+        aggregation::CompiledAggregation,
+        configuration_version::{
+            diff_configurations, version_not_found, ConfigurationDiff, VersionDiffParams,
+        },
+        data_dispatch_queue::release_held_dispatch_messages,
+        deduplication::CompiledDeduplication,
+        dispatch_pipeline::resolve_column,
+        file_source::{find_file_source, StateSwitch},
+        masking::CompiledMasking,
+        reference_dataset::{load_reference_datasets, ReferenceDatasets},
+        sorting::CompiledSort,
+        transformations::CompiledTransformations,
+    },
+    config::{masking::MaskingConfig, server::AppError},
     data::{
         file_destination::{
-            insert_file_destination, BatchingConfiguration, DestinationConfiguration,
-            FileDestination, FileDestinationCreation, GroupingConfiguration,
+            delete_file_destination as delete_file_destination_by_id,
+            find_by_file_source_id_and_identifier, insert_file_destination, list_by_file_source_id,
+            update_file_destination as update_file_destination_by_id,
+            update_file_destination_state, BatchingConfiguration, DestinationConfiguration,
+            DispatchMode, FileDestination, FileDestinationCreation, GroupingConfiguration,
+        },
+        file_destination_version::{
+            find_by_file_destination_id_and_version, insert_file_destination_version,
+            list_by_file_destination_id, FileDestinationVersion,
         },
-        file_source::find_by_context_and_identifier,
+        file_source::{find_by_context_and_identifier, ColumnType, FileFormat, FileSource},
     },
 };
 
@@ -20,6 +42,7 @@ use super::validators;
 
 pub fn validate_file_destination(
     creatable_file_destination: &FileDestinationCreation,
+    masking_config: &MaskingConfig,
 ) -> anyhow::Result<(), AppError> {
     if creatable_file_destination.identifier.is_empty() {
         return Err(AppError::DetailedValidation(
@@ -46,6 +69,18 @@ pub fn validate_file_destination(
         DestinationConfiguration::SQS { queue_url } => {
             validators::sqs_destination::validate(queue_url)?
         }
+        DestinationConfiguration::S3 {
+            bucket,
+            prefix,
+            format,
+        } => {
+            validators::s3_destination::validate(bucket, prefix)?;
+            validators::file_output_format::validate(format)?
+        }
+        DestinationConfiguration::LocalFileSystem { directory, format } => {
+            validators::local_file_system_destination::validate(directory)?;
+            validators::file_output_format::validate(format)?
+        }
     }
 
     match &creatable_file_destination.grouping {
@@ -64,15 +99,242 @@ pub fn validate_file_destination(
             BatchingConfiguration::Fixed { batch_size } => {
                 validators::fixed_batching::validate(batch_size)?
             }
+            BatchingConfiguration::TimeWindow {
+                max_rows,
+                max_wait_seconds,
+            } => validators::time_window_batching::validate(max_rows, max_wait_seconds)?,
         },
         None => {
             info!("No batching configuration set. Skipping validation.")
         }
     }
 
+    if let Some(transformations) = &creatable_file_destination.transformations {
+        validators::transformations::validate(transformations)?
+    }
+
+    if let Some(filter) = &creatable_file_destination.filter {
+        validators::filter_expression::validate(filter, None, &[])?
+    }
+
+    if let Some(masking) = &creatable_file_destination.masking {
+        validators::column_masking::validate(masking, masking_config)?
+    }
+
+    if let Some(deduplication) = &creatable_file_destination.deduplication {
+        validators::deduplication::validate(deduplication)?
+    }
+
+    if let Some(sort_by) = &creatable_file_destination.sort_by {
+        validators::sort_by::validate(sort_by)?
+    }
+
+    if let Some(DispatchMode::Aggregate { metrics }) = &creatable_file_destination.mode {
+        validators::aggregation::validate(metrics)?;
+        if creatable_file_destination.batching.is_some() {
+            return Err(AppError::DetailedValidation(
+                String::from("File destination aggregation is invalid"),
+                vec![String::from(
+                    "Aggregated destinations send one summary per group and cannot be batched",
+                )],
+            ));
+        }
+        if creatable_file_destination.sort_by.is_some() {
+            return Err(AppError::DetailedValidation(
+                String::from("File destination aggregation is invalid"),
+                vec![String::from(
+                    "Aggregated destinations send one summary per group and cannot be sorted",
+                )],
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Checks the column references of the destination when the columns of the
+// file source are known before any upload
+pub fn validate_against_file_source(
+    creatable_file_destination: &FileDestinationCreation,
+    file_source: &FileSource,
+    masking_config: &MaskingConfig,
+    reference_datasets: &ReferenceDatasets,
+) -> anyhow::Result<(), AppError> {
+    let column_names: Option<Vec<String>> = match (
+        &file_source.header_signature,
+        &file_source.schema,
+        &file_source.format,
+    ) {
+        (Some(signature), _, _) => Some(signature.clone()),
+        (None, Some(schema), _) => Some(schema.iter().map(|c| c.name.clone()).collect()),
+        (None, None, Some(FileFormat::FixedWidth { columns })) => {
+            Some(columns.iter().map(|c| c.name.clone()).collect())
+        }
+        _ => None,
+    };
+    let column_names = match column_names {
+        Some(column_names) => column_names,
+        None => return Ok(()),
+    };
+    let column_types: Vec<Option<ColumnType>> = (0..column_names.len())
+        .map(|idx| {
+            file_source
+                .schema
+                .as_ref()
+                .and_then(|schema| schema.get(idx))
+                .map(|column| column.column_type.clone())
+        })
+        .collect();
+
+    let transformations = CompiledTransformations::new(
+        creatable_file_destination
+            .transformations
+            .as_deref()
+            .unwrap_or_default(),
+        column_names,
+        column_types,
+        reference_datasets,
+    )?;
+
+    if let Some(filter) = &creatable_file_destination.filter {
+        validators::filter_expression::validate(
+            filter,
+            Some(&transformations.column_names),
+            &transformations.column_types,
+        )?
+    }
+
+    let mut column_types = transformations.column_types.clone();
+    CompiledMasking::new(
+        creatable_file_destination
+            .masking
+            .as_deref()
+            .unwrap_or_default(),
+        &transformations.column_names,
+        &mut column_types,
+        masking_config,
+    )?;
+
+    CompiledDeduplication::new(
+        creatable_file_destination.deduplication.as_ref(),
+        &transformations.column_names,
+    )?;
+
+    CompiledSort::new(
+        creatable_file_destination
+            .sort_by
+            .as_deref()
+            .unwrap_or_default(),
+        &transformations.column_names,
+    )?;
+
+    if let Some(DispatchMode::Aggregate { metrics }) = &creatable_file_destination.mode {
+        let grouping_columns: Vec<usize> = match &creatable_file_destination.grouping {
+            Some(GroupingConfiguration::GroupedByColumns { columns }) => columns
+                .iter()
+                .map(|col| resolve_column(col, Some(&transformations.column_names)))
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        CompiledAggregation::new(
+            metrics,
+            &transformations.column_names,
+            &column_types,
+            &grouping_columns,
+        )?;
+    }
+
     Ok(())
 }
 
+// Checks every file destination of a file source against its columns once
+// the file source changed, so an update cannot leave a destination relying
+// on a column that is gone. Each destination is checked as its current
+// version was stored
+pub async fn validate_file_source_destinations(
+    file_source: &FileSource,
+    masking_config: &MaskingConfig,
+    executor: &mut PgConnection,
+) -> anyhow::Result<(), AppError> {
+    let mut issues = Vec::new();
+    for file_destination in list_by_file_source_id(&file_source.id, executor).await? {
+        let version =
+            find_file_destination_version(&file_destination, &file_destination.version, executor)
+                .await?;
+        let configuration: FileDestinationCreation = serde_json::from_value(version.configuration)?;
+        let validation = match load_reference_datasets(
+            &file_source.context,
+            configuration.transformations.as_deref().unwrap_or_default(),
+            executor,
+        )
+        .await
+        {
+            Ok(reference_datasets) => validate_against_file_source(
+                &configuration,
+                file_source,
+                masking_config,
+                &reference_datasets,
+            ),
+            Err(err) => Err(err),
+        };
+        match validation {
+            Ok(()) => {}
+            Err(AppError::Error(message)) => return Err(AppError::Error(message)),
+            Err(err) => issues.push(format!(
+                "File destination '{}': {}",
+                file_destination.identifier, err
+            )),
+        }
+    }
+
+    match issues.is_empty() {
+        true => Ok(()),
+        false => Err(AppError::DetailedValidation(
+            String::from("The file source would no longer match its file destinations"),
+            issues,
+        )),
+    }
+}
+
+// Checks the file destination against the columns of its file source and
+// stores it as its first version
+pub async fn insert_new_file_destination(
+    file_source: &FileSource,
+    creatable_file_destination: FileDestinationCreation,
+    masking_config: &MaskingConfig,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestination, AppError> {
+    let reference_datasets = load_reference_datasets(
+        &file_source.context,
+        creatable_file_destination
+            .transformations
+            .as_deref()
+            .unwrap_or_default(),
+        executor,
+    )
+    .await?;
+
+    validate_against_file_source(
+        &creatable_file_destination,
+        file_source,
+        masking_config,
+        &reference_datasets,
+    )?;
+
+    let configuration = serde_json::to_value(&creatable_file_destination)?;
+    let created_file_destination =
+        insert_file_destination(&file_source.id, creatable_file_destination, executor).await?;
+    insert_file_destination_version(
+        &created_file_destination.id,
+        &created_file_destination.version,
+        configuration,
+        executor,
+    )
+    .await?;
+
+    Ok(created_file_destination)
+}
+
 #[instrument(skip(db, creatable_file_destination))]
 pub async fn create_file_destination(
     Path((context, file_source)): Path<(String, String)>,
@@ -84,7 +346,8 @@ pub async fn create_file_destination(
         context, file_source, creatable_file_destination,
     );
 
-    validate_file_destination(&creatable_file_destination)?;
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    validate_file_destination(&creatable_file_destination, &masking_config)?;
 
     let mut tx = db.begin().await?;
 
@@ -101,10 +364,352 @@ pub async fn create_file_destination(
         Some(fs) => fs,
     };
 
-    let created_file_destination =
-        insert_file_destination(&file_source.id, creatable_file_destination, &mut *tx).await?;
+    if find_by_file_source_id_and_identifier(
+        &file_source.id,
+        &creatable_file_destination.identifier,
+        &mut tx,
+    )
+    .await?
+    .is_some()
+    {
+        return Err(AppError::Validation(format!(
+            "A file destination with identifier '{}' already exists for file source {}.",
+            creatable_file_destination.identifier, file_source.identifier
+        )));
+    }
+
+    let created_file_destination = insert_new_file_destination(
+        &file_source,
+        creatable_file_destination,
+        &masking_config,
+        &mut tx,
+    )
+    .await?;
 
     tx.commit().await?;
 
     Ok((StatusCode::CREATED, Json(created_file_destination)))
 }
+
+pub async fn find_file_destination(
+    file_source: &FileSource,
+    identifier: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestination, AppError> {
+    find_by_file_source_id_and_identifier(&file_source.id, identifier, executor)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "File destination with identifier {} does not exist for file source {}.",
+                identifier, file_source.identifier
+            ))
+        })
+}
+
+#[instrument(skip(db))]
+pub async fn list_file_destinations(
+    State(db): State<PgPool>,
+    Path((context, file_source)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<Vec<FileDestination>>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &file_source, &mut conn).await?;
+    let file_destinations = list_by_file_source_id(&file_source.id, &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(file_destinations)))
+}
+
+#[instrument(skip(db))]
+pub async fn get_file_destination(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &file_source, &mut conn).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(file_destination)))
+}
+
+// Every update is checked against the current columns of the file source
+// and stored as the next version of the file destination
+pub async fn apply_file_destination_update(
+    file_source: &FileSource,
+    file_destination: &FileDestination,
+    file_destination_update: FileDestinationCreation,
+    masking_config: &MaskingConfig,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestination, AppError> {
+    let reference_datasets = load_reference_datasets(
+        &file_source.context,
+        file_destination_update
+            .transformations
+            .as_deref()
+            .unwrap_or_default(),
+        executor,
+    )
+    .await?;
+
+    validate_against_file_source(
+        &file_destination_update,
+        file_source,
+        masking_config,
+        &reference_datasets,
+    )?;
+
+    let configuration = serde_json::to_value(&file_destination_update)?;
+    let updated_file_destination =
+        update_file_destination_by_id(&file_destination.id, file_destination_update, executor)
+            .await?;
+    insert_file_destination_version(
+        &updated_file_destination.id,
+        &updated_file_destination.version,
+        configuration,
+        executor,
+    )
+    .await?;
+
+    Ok(updated_file_destination)
+}
+
+#[instrument(skip(db, file_destination_update))]
+pub async fn update_file_destination(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+    Json(file_destination_update): Json<FileDestinationCreation>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    if file_destination_update.identifier != identifier {
+        return Err(AppError::Validation(String::from(
+            "The identifier of a file destination cannot be changed.",
+        )));
+    }
+
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    validate_file_destination(&file_destination_update, &masking_config)?;
+
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &file_source, &mut tx).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut tx).await?;
+    let updated_file_destination = apply_file_destination_update(
+        &file_source,
+        &file_destination,
+        file_destination_update,
+        &masking_config,
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(updated_file_destination)))
+}
+
+#[instrument(skip(db))]
+pub async fn list_file_destination_versions(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<(StatusCode, Json<Vec<FileDestinationVersion>>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &file_source, &mut conn).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut conn).await?;
+    let versions = list_by_file_destination_id(&file_destination.id, &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(versions)))
+}
+
+pub async fn find_file_destination_version(
+    file_destination: &FileDestination,
+    version: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestinationVersion, AppError> {
+    find_by_file_destination_id_and_version(&file_destination.id, version, executor)
+        .await?
+        .ok_or_else(|| {
+            version_not_found(
+                version,
+                &format!("file destination {}", file_destination.identifier),
+            )
+        })
+}
+
+#[instrument(skip(db))]
+pub async fn diff_file_destination_versions(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+    Query(params): Query<VersionDiffParams>,
+) -> anyhow::Result<(StatusCode, Json<ConfigurationDiff>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &file_source, &mut conn).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut conn).await?;
+    let from = find_file_destination_version(&file_destination, &params.from, &mut conn).await?;
+    let to = find_file_destination_version(&file_destination, &params.to, &mut conn).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ConfigurationDiff {
+            from_version: from.version,
+            to_version: to.version,
+            changes: diff_configurations(&from.configuration, &to.configuration),
+        }),
+    ))
+}
+
+// Rolling back applies the configuration of an older version as a new
+// version. It goes through the same validation as an update, since the file
+// source may have changed since that version was created
+#[instrument(skip(db))]
+pub async fn rollback_file_destination(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier, version)): Path<(String, String, String, i32)>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &file_source, &mut tx).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut tx).await?;
+    let file_destination_version =
+        find_file_destination_version(&file_destination, &version, &mut tx).await?;
+
+    let file_destination_update: FileDestinationCreation =
+        serde_json::from_value(file_destination_version.configuration).map_err(|err| {
+            AppError::Validation(format!(
+                "Version {} of file destination {} can no longer be applied. {}",
+                version, identifier, err
+            ))
+        })?;
+    validate_file_destination(&file_destination_update, &masking_config)?;
+
+    info!(
+        "Rolling back file destination {} from version {} to version {}",
+        file_destination.identifier, file_destination.version, version
+    );
+    let updated_file_destination = apply_file_destination_update(
+        &file_source,
+        &file_destination,
+        file_destination_update,
+        &masking_config,
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(updated_file_destination)))
+}
+
+// Held messages are only released once neither the destination nor its
+// file source is paused
+async fn switch_file_destination(
+    db: PgPool,
+    sqs_client: SQSClient,
+    context: String,
+    file_source: String,
+    identifier: String,
+    state_switch: StateSwitch,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &file_source, &mut tx).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut tx).await?;
+    let (enabled, paused) = state_switch.apply(file_destination.enabled, file_destination.paused);
+
+    info!(
+        "Switching file destination {} of file source {} with {:?}",
+        identifier, file_source.identifier, state_switch
+    );
+    let updated_file_destination =
+        update_file_destination_state(&file_destination.id, enabled, paused, &mut tx).await?;
+    tx.commit().await?;
+
+    // Resuming an already resumed destination retries the messages that
+    // could not be released before
+    if matches!(state_switch, StateSwitch::Resume) && !file_source.paused {
+        let mut conn = db.acquire().await?;
+        release_held_dispatch_messages(&sqs_client, &updated_file_destination, &mut conn).await?;
+    }
+
+    Ok((StatusCode::OK, Json(updated_file_destination)))
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn pause_file_destination(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    switch_file_destination(
+        db,
+        sqs_client,
+        context,
+        file_source,
+        identifier,
+        StateSwitch::Pause,
+    )
+    .await
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn resume_file_destination(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    switch_file_destination(
+        db,
+        sqs_client,
+        context,
+        file_source,
+        identifier,
+        StateSwitch::Resume,
+    )
+    .await
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn enable_file_destination(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    switch_file_destination(
+        db,
+        sqs_client,
+        context,
+        file_source,
+        identifier,
+        StateSwitch::Enable,
+    )
+    .await
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn disable_file_destination(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileDestination>), AppError> {
+    switch_file_destination(
+        db,
+        sqs_client,
+        context,
+        file_source,
+        identifier,
+        StateSwitch::Disable,
+    )
+    .await
+}
+
+#[instrument(skip(db))]
+pub async fn delete_file_destination(
+    State(db): State<PgPool>,
+    Path((context, file_source, identifier)): Path<(String, String, String)>,
+) -> anyhow::Result<StatusCode, AppError> {
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &file_source, &mut tx).await?;
+    let file_destination = find_file_destination(&file_source, &identifier, &mut tx).await?;
+
+    info!(
+        "Deleting file destination {} of file source {}",
+        identifier, file_source.identifier
+    );
+    delete_file_destination_by_id(&file_destination.id, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
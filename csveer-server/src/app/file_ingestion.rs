@@ -0,0 +1,442 @@
+use std::collections::{HashMap, HashSet};
+
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_sqs::Client as SQSClient;
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use tracing::{error, info, instrument};
+
+use crate::{
+    app::{
+        data_dispatch_queue::DataDispatchMessage,
+        dispatch_pipeline::DispatchPipeline,
+        file_reader::{open_file, widest_row},
+        reference_dataset::load_reference_datasets,
+    },
+    commons::{
+        file_storage::{retrieve_file_for_dispatch, PENDING_CSV_FILES_BUCKET},
+        queue_listener::post_message,
+    },
+    config::{listeners::DataDispatchListenerConfig, masking::MaskingConfig, server::AppError},
+    data::{
+        data_dispatch::{list_by_file_ingestion_id, DataDispatch, DataDispatchStatus},
+        file_destination::{list_by_file_source_id, FileDestination},
+        file_ingestion::{
+            find_by_id, lock_file_ingestion, queue_file_ingestion_replay,
+            update_file_ingestion_status, FileIngestion, FileIngestionStatus,
+        },
+        file_source::{self, FileSource},
+    },
+};
+
+#[derive(Serialize)]
+pub struct FileIngestionDispatch {
+    pub data_dispatch_id: i32,
+    pub file_destination_identifier: Option<String>,
+    pub status: DataDispatchStatus,
+    pub total_rows: Option<i32>,
+    pub rejected_rows: Option<i32>,
+    pub filtered_rows: Option<i32>,
+    pub duplicate_rows: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct FileIngestionDetails {
+    #[serde(flatten)]
+    pub file_ingestion: FileIngestion,
+    pub dispatches: Vec<FileIngestionDispatch>,
+}
+
+// The first destination to pick up the file moves the ingestion out of the
+// queue. Later ones find it already dispatching or finished
+pub async fn mark_file_ingestion_dispatching(
+    file_ingestion_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<(), AppError> {
+    if let Some(file_ingestion) = lock_file_ingestion(file_ingestion_id, executor).await? {
+        if matches!(
+            file_ingestion.status,
+            FileIngestionStatus::Received | FileIngestionStatus::Queued
+        ) {
+            update_file_ingestion_status(
+                file_ingestion_id,
+                FileIngestionStatus::Dispatching,
+                None,
+                executor,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Derives the status of the ingestion from its dispatches once every queued
+// destination has finished with the file
+pub async fn refresh_file_ingestion_status(
+    file_ingestion_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<(), AppError> {
+    let file_ingestion = match lock_file_ingestion(file_ingestion_id, executor).await? {
+        Some(file_ingestion) => file_ingestion,
+        None => return Ok(()),
+    };
+    let file_destination_count = match file_ingestion.file_destination_count {
+        Some(file_destination_count) => file_destination_count as usize,
+        None => return Ok(()),
+    };
+
+    let data_dispatches = list_by_file_ingestion_id(file_ingestion_id, executor).await?;
+    let status = ingestion_status(file_destination_count, &data_dispatches);
+    if status != file_ingestion.status {
+        update_file_ingestion_status(file_ingestion_id, status, None, executor).await?;
+    }
+
+    Ok(())
+}
+
+// Only the latest dispatch of each destination counts, a replay supersedes
+// the dispatch it was created from
+fn ingestion_status(
+    file_destination_count: usize,
+    data_dispatches: &[DataDispatch],
+) -> FileIngestionStatus {
+    let latest_dispatches = latest_dispatch_by_destination(data_dispatches);
+    let is_pending = latest_dispatches.len() < file_destination_count
+        || latest_dispatches
+            .values()
+            .any(|dispatch| matches!(dispatch.status, DataDispatchStatus::PendingExecution));
+    if is_pending {
+        return FileIngestionStatus::Dispatching;
+    }
+
+    let failed = latest_dispatches
+        .values()
+        .filter(|dispatch| matches!(dispatch.status, DataDispatchStatus::Failed))
+        .count();
+    let finished = latest_dispatches
+        .values()
+        .filter(|dispatch| matches!(dispatch.status, DataDispatchStatus::Finished))
+        .count();
+    match (failed, finished) {
+        (0, finished) if finished == latest_dispatches.len() => FileIngestionStatus::Completed,
+        (failed, _) if failed == latest_dispatches.len() => FileIngestionStatus::Failed,
+        _ => FileIngestionStatus::PartiallyFailed,
+    }
+}
+
+fn latest_dispatch_by_destination(data_dispatches: &[DataDispatch]) -> HashMap<i32, &DataDispatch> {
+    let mut latest_dispatches: HashMap<i32, &DataDispatch> = HashMap::new();
+    for dispatch in data_dispatches {
+        match latest_dispatches.get(&dispatch.file_destination_id) {
+            Some(latest) if latest.id > dispatch.id => {}
+            _ => {
+                latest_dispatches.insert(dispatch.file_destination_id, dispatch);
+            }
+        }
+    }
+    latest_dispatches
+}
+
+#[instrument(skip(db))]
+pub async fn get_file_ingestion(
+    State(db): State<PgPool>,
+    Path(file_ingestion_id): Path<i32>,
+) -> anyhow::Result<(StatusCode, Json<FileIngestionDetails>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_ingestion = find_by_id(&file_ingestion_id, &mut conn)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "File ingestion {} does not exist.",
+                file_ingestion_id
+            ))
+        })?;
+
+    let file_destinations =
+        list_by_file_source_id(&file_ingestion.file_source_id, &mut conn).await?;
+    let dispatches = list_by_file_ingestion_id(&file_ingestion.id, &mut conn)
+        .await?
+        .into_iter()
+        .map(|dispatch| FileIngestionDispatch {
+            data_dispatch_id: dispatch.id,
+            file_destination_identifier: file_destinations
+                .iter()
+                .find(|destination| destination.id == dispatch.file_destination_id)
+                .map(|destination| destination.identifier.clone()),
+            status: dispatch.status,
+            total_rows: dispatch.total_rows,
+            rejected_rows: dispatch.rejected_rows,
+            filtered_rows: dispatch.filtered_rows,
+            duplicate_rows: dispatch.duplicate_rows,
+            created_at: dispatch.created_at,
+            updated_at: dispatch.updated_at,
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(FileIngestionDetails {
+            file_ingestion,
+            dispatches,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileIngestionReplay {
+    pub file_destinations: Option<Vec<String>>,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct ReplayedDispatch {
+    pub file_destination_identifier: String,
+    pub replayed_data_dispatch_id: Option<i32>,
+    pub queued: bool,
+    // Only computed on a dry run
+    pub row_count: Option<usize>,
+    pub batch_count: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct FileIngestionReplayResult {
+    pub file_ingestion_id: i32,
+    pub s3_key: String,
+    pub dry_run: bool,
+    pub dispatches: Vec<ReplayedDispatch>,
+}
+
+// Sends the stored object of a finished ingestion again. The object is
+// checked against the recorded checksum first, since a later upload with the
+// same file name replaces it. A dry run stops after these checks and runs
+// the file through the pipeline of every destination it would be sent to,
+// reporting how many rows and batches each one would get
+#[instrument(skip(db, s3_client, sqs_client))]
+pub async fn replay_file_ingestion(
+    State(db): State<PgPool>,
+    State(s3_client): State<S3Client>,
+    State(sqs_client): State<SQSClient>,
+    Path(file_ingestion_id): Path<i32>,
+    Json(replay): Json<FileIngestionReplay>,
+) -> anyhow::Result<(StatusCode, Json<FileIngestionReplayResult>), AppError> {
+    let data_dispatch_config = envy::from_env::<DataDispatchListenerConfig>()?;
+    let dry_run = replay.dry_run.unwrap_or(false);
+
+    let mut tx = db.begin().await?;
+    let file_ingestion = lock_file_ingestion(&file_ingestion_id, &mut tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "File ingestion {} does not exist.",
+                file_ingestion_id
+            ))
+        })?;
+    if matches!(
+        file_ingestion.status,
+        FileIngestionStatus::Received
+            | FileIngestionStatus::Queued
+            | FileIngestionStatus::Dispatching
+    ) {
+        return Err(AppError::Validation(format!(
+            "File ingestion {} is still being processed.",
+            file_ingestion_id
+        )));
+    }
+
+    let file_source = file_source::find_by_id(&file_ingestion.file_source_id, &mut tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "File source of file ingestion {} does not exist.",
+                file_ingestion_id
+            ))
+        })?;
+    let s3_key = format!(
+        "{}/{}/{}",
+        file_source.context, file_source.identifier, file_ingestion.file_name
+    );
+    if s3_key != file_ingestion.s3_key {
+        return Err(AppError::Validation(format!(
+            "File source of file ingestion {} was moved to context {} after the upload.",
+            file_ingestion_id, file_source.context
+        )));
+    }
+
+    let file_destinations = list_by_file_source_id(&file_source.id, &mut tx).await?;
+    let file_destinations: Vec<_> = match &replay.file_destinations {
+        Some(identifiers) => {
+            let issues: Vec<String> = identifiers
+                .iter()
+                .filter(|identifier| {
+                    !file_destinations
+                        .iter()
+                        .any(|destination| &destination.identifier == *identifier)
+                })
+                .map(|identifier| format!("Unknown file destination '{}'", identifier))
+                .collect();
+            if identifiers.is_empty() || !issues.is_empty() {
+                return Err(AppError::DetailedValidation(
+                    String::from("Invalid file ingestion replay"),
+                    match issues.is_empty() {
+                        true => vec![String::from("File destinations should not be empty")],
+                        false => issues,
+                    },
+                ));
+            }
+            file_destinations
+                .into_iter()
+                .filter(|destination| identifiers.contains(&destination.identifier))
+                .collect()
+        }
+        None => file_destinations,
+    };
+
+    let contents =
+        retrieve_file_for_dispatch(&s3_client, PENDING_CSV_FILES_BUCKET, &file_ingestion.s3_key)
+            .await?;
+    if hex::encode(Sha256::digest(&contents)) != file_ingestion.checksum {
+        return Err(AppError::Validation(format!(
+            "The stored file of file ingestion {} was replaced by a later upload.",
+            file_ingestion_id
+        )));
+    }
+
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    let data_dispatches = list_by_file_ingestion_id(&file_ingestion.id, &mut tx).await?;
+    let latest_dispatches = latest_dispatch_by_destination(&data_dispatches);
+
+    let mut dispatches = Vec::with_capacity(file_destinations.len());
+    let mut new_destinations = 0;
+    for destination in &file_destinations {
+        let replayed_data_dispatch_id = latest_dispatches
+            .get(&destination.id)
+            .map(|dispatch| dispatch.id);
+        let mut queued = false;
+        let mut counts = None;
+        if dry_run {
+            counts = Some(
+                dry_run_counts(
+                    &file_source,
+                    destination,
+                    &contents,
+                    &masking_config,
+                    &mut tx,
+                )
+                .await?,
+            );
+        } else {
+            let data_dispatch_message = DataDispatchMessage {
+                context: file_source.context.clone(),
+                file_source_identifier: file_source.identifier.clone(),
+                file_destination_identifier: destination.identifier.clone(),
+                file_name: file_ingestion.file_name.clone(),
+                file_ingestion_id: Some(file_ingestion.id),
+                replayed_data_dispatch_id,
+            };
+            match post_message(
+                &sqs_client,
+                &data_dispatch_config.queue_url,
+                serde_json::to_string(&data_dispatch_message)?,
+            )
+            .await
+            {
+                Ok(_) => {
+                    queued = true;
+                    if replayed_data_dispatch_id.is_none() {
+                        new_destinations += 1;
+                    }
+                }
+                Err(err) => error!(
+                    "Failed to post replay message {:?} for file destination '{}'. Error: {}",
+                    data_dispatch_message, destination.identifier, err
+                ),
+            }
+        }
+        dispatches.push(ReplayedDispatch {
+            file_destination_identifier: destination.identifier.clone(),
+            replayed_data_dispatch_id,
+            queued,
+            row_count: counts.map(|(row_count, _)| row_count),
+            batch_count: counts.map(|(_, batch_count)| batch_count),
+        });
+    }
+
+    if dry_run {
+        return Ok((
+            StatusCode::OK,
+            Json(FileIngestionReplayResult {
+                file_ingestion_id: file_ingestion.id,
+                s3_key: file_ingestion.s3_key,
+                dry_run,
+                dispatches,
+            }),
+        ));
+    }
+
+    if dispatches.iter().any(|dispatch| dispatch.queued) {
+        queue_file_ingestion_replay(&file_ingestion.id, new_destinations, &mut tx).await?;
+    }
+    tx.commit().await?;
+
+    info!(
+        "Replayed file ingestion {} to {} file destinations",
+        file_ingestion.id,
+        dispatches.iter().filter(|dispatch| dispatch.queued).count()
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(FileIngestionReplayResult {
+            file_ingestion_id: file_ingestion.id,
+            s3_key: file_ingestion.s3_key,
+            dry_run,
+            dispatches,
+        }),
+    ))
+}
+
+// Replays skip deduplication, so the pipeline runs without looking up the
+// keys dispatched before
+async fn dry_run_counts(
+    file_source: &FileSource,
+    file_destination: &FileDestination,
+    contents: &[u8],
+    masking_config: &MaskingConfig,
+    executor: &mut PgConnection,
+) -> anyhow::Result<(usize, usize), AppError> {
+    let reference_datasets = load_reference_datasets(
+        &file_source.context,
+        file_destination
+            .transformations
+            .as_deref()
+            .unwrap_or_default(),
+        executor,
+    )
+    .await?;
+    let widest_row = widest_row(file_source, contents)?;
+    let records = open_file(file_source, contents)?;
+    let pipeline = DispatchPipeline::new(
+        file_source,
+        file_destination,
+        records.headers.as_deref(),
+        widest_row,
+        masking_config,
+        &reference_datasets,
+    )?;
+    let prepared = pipeline.prepare(records)?;
+    let plan = pipeline.build_batches(prepared, &HashSet::new())?;
+
+    Ok((
+        plan.batches.iter().map(|batch| batch.rows.len()).sum(),
+        plan.batches.len(),
+    ))
+}
@@ -1,19 +1,29 @@
+use anyhow::Context;
 use aws_lambda_events::s3::S3Event;
+use aws_sdk_s3::Client as S3Client;
 use aws_sdk_sqs::types::Message;
 use aws_sdk_sqs::Client as SQSClient;
+use percent_encoding::percent_decode_str;
 use sqlx::{Pool, Postgres};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, info_span, instrument};
 use ulid::Ulid;
 
 use crate::{
-    app::data_dispatch_queue::DataDispatchMessage,
+    app::{data_dispatch_queue::DataDispatchMessage, header_drift::check_header_drift},
     commons::queue_listener::{ack_message, poll_message, post_message},
     config::{
         listeners::{DataDispatchListenerConfig, FileIngestionListenerConfig},
         server::{AppError, AppState},
     },
-    data::{file_destination, file_source},
+    data::{
+        file_destination,
+        file_ingestion::{
+            find_received_by_s3_key, lock_file_ingestion, update_file_destination_count,
+            update_file_ingestion_status, FileIngestionStatus,
+        },
+        file_source,
+    },
 };
 
 pub async fn listen_file_ingestion(
@@ -21,6 +31,7 @@ pub async fn listen_file_ingestion(
     app_state: AppState,
 ) -> anyhow::Result<(), AppError> {
     let sqs_client = app_state.sqs_client;
+    let s3_client = app_state.s3_client;
     let file_ingestion_config = envy::from_env::<FileIngestionListenerConfig>()?;
     let data_dispatch_config = envy::from_env::<DataDispatchListenerConfig>()?;
     let mut db_pool = app_state.db_pool;
@@ -43,7 +54,15 @@ pub async fn listen_file_ingestion(
             let message = messages.first().unwrap();
             let message_body = message.body().unwrap();
             info!("About to process message: {}", message_body);
-            match process_message(&mut db_pool, &sqs_client, &data_dispatch_config, message).await {
+            match process_message(
+                &mut db_pool,
+                &s3_client,
+                &sqs_client,
+                &data_dispatch_config,
+                message,
+            )
+            .await
+            {
                 Ok(_) => {
                     ack_message(
                         &sqs_client,
@@ -66,9 +85,10 @@ pub async fn listen_file_ingestion(
     Ok(())
 }
 
-#[instrument(skip(db_pool, sqs_client, message))]
+#[instrument(skip(db_pool, s3_client, sqs_client, message))]
 async fn process_message(
     db_pool: &mut Pool<Postgres>,
+    s3_client: &S3Client,
     sqs_client: &SQSClient,
     data_dispatch_config: &DataDispatchListenerConfig,
     message: &Message,
@@ -87,13 +107,21 @@ async fn process_message(
             return Ok(());
         }
     };
-    process_s3_event(db_pool, sqs_client, data_dispatch_config, s3_event).await?;
+    process_s3_event(
+        db_pool,
+        s3_client,
+        sqs_client,
+        data_dispatch_config,
+        s3_event,
+    )
+    .await?;
     Ok(())
 }
 
-#[instrument(skip(db_pool, sqs_client, s3_event))]
+#[instrument(skip(db_pool, s3_client, sqs_client, s3_event))]
 async fn process_s3_event(
     db_pool: &mut Pool<Postgres>,
+    s3_client: &S3Client,
     sqs_client: &SQSClient,
     data_dispatch_config: &DataDispatchListenerConfig,
     s3_event: S3Event,
@@ -104,7 +132,7 @@ async fn process_s3_event(
             info!("Skipped record with event {}", event_name);
             continue;
         }
-        let object_key = record.s3.object.key.unwrap();
+        let object_key = decode_object_key(&record.s3.object.key.unwrap())?;
         let (context, remainder) = object_key.split_once('/').unwrap();
         let (file_source_identifier, file_name) = remainder.split_once('/').unwrap();
 
@@ -127,15 +155,69 @@ async fn process_s3_event(
             }
         };
 
-        let file_destinations =
-            file_destination::list_by_file_source_id(&file_source.id, &mut *tx).await?;
+        // Locking the ingestion keeps the dispatches from updating it before
+        // the queued destinations are recorded
+        let file_ingestion = match find_received_by_s3_key(&object_key, &mut tx).await? {
+            Some(file_ingestion) => lock_file_ingestion(&file_ingestion.id, &mut tx).await?,
+            None => {
+                info!(
+                    "No upload was recorded for {}, its dispatches will not be tracked",
+                    object_key
+                );
+                None
+            }
+        };
+
+        if !file_source.enabled {
+            info!(
+                "Skipped {} since file source {} is disabled",
+                object_key, file_source.identifier
+            );
+            if let Some(file_ingestion) = &file_ingestion {
+                update_file_ingestion_status(
+                    &file_ingestion.id,
+                    FileIngestionStatus::Failed,
+                    Some(String::from("File source is disabled")),
+                    &mut tx,
+                )
+                .await?;
+            }
+            tx.commit().await?;
+            continue;
+        }
+
+        if !check_header_drift(s3_client, &file_source, &object_key, file_name, &mut *tx).await? {
+            if let Some(file_ingestion) = &file_ingestion {
+                update_file_ingestion_status(
+                    &file_ingestion.id,
+                    FileIngestionStatus::Failed,
+                    Some(String::from("Rejected by the header drift policy")),
+                    &mut tx,
+                )
+                .await?;
+            }
+            tx.commit().await?;
+            continue;
+        }
+
+        // Disabled destinations do not get the file at all, while paused ones
+        // hold its dispatch until they are resumed
+        let file_destinations: Vec<_> =
+            file_destination::list_by_file_source_id(&file_source.id, &mut *tx)
+                .await?
+                .into_iter()
+                .filter(|file_destination| file_destination.enabled)
+                .collect();
 
-        for destination in file_destinations {
+        let mut queued_destinations = 0;
+        for destination in &file_destinations {
             let data_dispatch_message = DataDispatchMessage {
                 context: context.to_string(),
                 file_source_identifier: file_source.identifier.clone(),
                 file_destination_identifier: destination.identifier.clone(),
                 file_name: file_name.to_string(),
+                file_ingestion_id: file_ingestion.as_ref().map(|i| i.id),
+                replayed_data_dispatch_id: None,
             };
             info!(
                 "Sending message {:?} to data dispatch for file destination '{}'",
@@ -148,10 +230,13 @@ async fn process_s3_event(
             )
             .await
             {
-                Ok(_) => info!(
-                    "Successfully posted message {:?} to data dispatch for file destination '{}'",
-                    data_dispatch_message, destination.identifier
-                ),
+                Ok(_) => {
+                    queued_destinations += 1;
+                    info!(
+                        "Successfully posted message {:?} to data dispatch for file destination '{}'",
+                        data_dispatch_message, destination.identifier
+                    )
+                }
                 Err(err) => error!(
                     "Failed to post message {:?} to data dispatch for file destination '{}'. Error: {}",
                     data_dispatch_message, destination.identifier, err.to_string()
@@ -159,8 +244,50 @@ async fn process_s3_event(
             }
         }
 
+        if let Some(file_ingestion) = &file_ingestion {
+            let (status, message) = match queued_destinations {
+                0 if file_destinations.is_empty() => (
+                    FileIngestionStatus::Completed,
+                    Some(String::from("File source has no enabled file destinations")),
+                ),
+                0 => (
+                    FileIngestionStatus::Failed,
+                    Some(String::from("Could not queue the file for any destination")),
+                ),
+                _ => (FileIngestionStatus::Queued, None),
+            };
+            update_file_destination_count(&file_ingestion.id, queued_destinations, &mut tx).await?;
+            update_file_ingestion_status(&file_ingestion.id, status, message, &mut tx).await?;
+        }
+
         tx.commit().await?;
     }
 
     Ok(())
 }
+
+// Keys in S3 event notifications are URL encoded, with spaces sent as '+'
+// and a literal '+' sent as "%2B"
+fn decode_object_key(key: &str) -> anyhow::Result<String, AppError> {
+    let object_key = percent_decode_str(&key.replace('+', " "))
+        .decode_utf8()
+        .with_context(|| format!("Decoding object key {}", key))?;
+    Ok(object_key.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_object_key;
+
+    #[test]
+    fn should_decode_object_keys_of_s3_events() {
+        assert_eq!(
+            decode_object_key("banking/daily-transfer-csv/daily+transfer%2B2026.csv").unwrap(),
+            "banking/daily-transfer-csv/daily transfer+2026.csv"
+        );
+        assert_eq!(
+            decode_object_key("banking/daily-transfer-csv/transfer%C3%AAs.csv").unwrap(),
+            "banking/daily-transfer-csv/transferês.csv"
+        );
+    }
+}
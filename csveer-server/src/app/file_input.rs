@@ -1,7 +1,14 @@
 use crate::{
-    commons::file_storage::store_file_for_ingestion,
+    commons::file_storage::{store_file_for_ingestion, PENDING_CSV_FILES_BUCKET},
     config::server::AppError,
-    data::{file_destination::list_by_file_source_id, file_source::find_by_context_and_identifier},
+    data::{
+        file_destination::list_by_file_source_id,
+        file_ingestion::{
+            insert_file_ingestion, update_file_ingestion_status, FileIngestion,
+            FileIngestionCreation, FileIngestionStatus,
+        },
+        file_source::{find_by_context_and_identifier, FileSource},
+    },
 };
 use aws_sdk_s3::Client as S3Client;
 use axum::{
@@ -10,17 +17,20 @@ use axum::{
     Json,
 };
 use serde::Serialize;
-use sqlx::PgPool;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
 use tracing::{error, info, instrument};
 
 #[derive(Serialize)]
 pub struct FileUploadResult {
+    file_ingestions: Vec<FileIngestion>,
     file_destinations_affected: Vec<String>,
 }
 
 impl FileUploadResult {
-    fn new(file_destinations_affected: Vec<String>) -> Self {
+    fn new(file_ingestions: Vec<FileIngestion>, file_destinations_affected: Vec<String>) -> Self {
         Self {
+            file_ingestions,
             file_destinations_affected,
         }
     }
@@ -46,40 +56,119 @@ pub async fn file_upload(
             return Err(AppError::Validation(message));
         }
     };
+    if !file_source.enabled {
+        return Err(AppError::Validation(format!(
+            "File source {} of context {} is disabled and does not accept uploads",
+            identifier, context
+        )));
+    }
 
+    let mut file_ingestions = Vec::new();
     while let Ok(Some(field)) = multipart.next_field().await {
-        process_file(&s3_client, &context, &identifier, field).await?;
+        if let Some(file_ingestion) =
+            process_file(&s3_client, &file_source, field, &mut conn).await?
+        {
+            file_ingestions.push(file_ingestion);
+        }
     }
 
     let file_destination_names: Vec<String> = list_by_file_source_id(&file_source.id, &mut conn)
         .await?
         .into_iter()
+        .filter(|i| i.enabled)
         .map(|i| i.identifier)
         .collect();
 
     Ok((
         StatusCode::ACCEPTED,
-        Json(FileUploadResult::new(file_destination_names)),
+        Json(FileUploadResult::new(
+            file_ingestions,
+            file_destination_names,
+        )),
     ))
 }
 
-#[instrument(skip(s3_client, context, identifier, field))]
+// Only the last component of the name is kept, so an upload cannot address
+// objects or files outside of its file source. Names that are absolute or
+// climb up with `..` are rejected instead of being guessed at
+fn upload_file_name(file_name: &str) -> anyhow::Result<String, AppError> {
+    let is_separator = |c: char| c == '/' || c == '\\';
+    let is_absolute = file_name.starts_with(is_separator) || file_name.chars().nth(1) == Some(':');
+    if is_absolute
+        || file_name
+            .split(is_separator)
+            .any(|component| component == "..")
+    {
+        return Err(AppError::Validation(format!(
+            "File name '{}' should not be absolute or contain '..'",
+            file_name
+        )));
+    }
+
+    match file_name
+        .rsplit(is_separator)
+        .next()
+        .filter(|name| !name.is_empty() && *name != ".")
+    {
+        Some(name) => Ok(name.to_string()),
+        None => Err(AppError::Validation(format!(
+            "File name '{}' does not name a file",
+            file_name
+        ))),
+    }
+}
+
+// The ingestion is recorded before the file is stored, so it already exists
+// when the storage event reaches the ingestion listener
+#[instrument(skip(s3_client, file_source, field, executor))]
 async fn process_file(
     s3_client: &S3Client,
-    context: &str,
-    identifier: &str,
+    file_source: &FileSource,
     field: Field<'_>,
-) -> anyhow::Result<(), AppError> {
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileIngestion>, AppError> {
     let file_name = match field.file_name() {
-        Some(file_name) => file_name,
+        Some(file_name) => upload_file_name(file_name)?,
         None => {
             info!("File had no file name.");
-            return Ok(());
+            return Ok(None);
         }
     };
-    let bucket_name = "pending-csv-files";
-    let file_name = format!("{}/{}/{}", context, identifier, file_name);
+    let s3_key = format!(
+        "{}/{}/{}",
+        file_source.context, file_source.identifier, file_name
+    );
     let file_bytes = field.bytes().await?;
-    store_file_for_ingestion(&s3_client, &bucket_name, file_bytes.into(), &file_name).await?;
-    Ok(())
+
+    let file_ingestion = insert_file_ingestion(
+        FileIngestionCreation {
+            file_source_id: file_source.id,
+            file_name,
+            s3_key: s3_key.clone(),
+            checksum: hex::encode(Sha256::digest(&file_bytes)),
+            size: file_bytes.len() as i64,
+        },
+        executor,
+    )
+    .await?;
+
+    if let Err(err) = store_file_for_ingestion(
+        s3_client,
+        PENDING_CSV_FILES_BUCKET,
+        file_bytes.into(),
+        &s3_key,
+    )
+    .await
+    {
+        update_file_ingestion_status(
+            &file_ingestion.id,
+            FileIngestionStatus::Failed,
+            Some(format!("Failed to store file. {}", err)),
+            executor,
+        )
+        .await?;
+        return Err(err.into());
+    }
+
+    Ok(Some(file_ingestion))
 }
@@ -0,0 +1,318 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use arrow::{
+    array::{ArrayRef, BooleanArray, Date32Array, Decimal128Array, Int64Array, StringArray},
+    datatypes::{DataType, Field, Schema, DECIMAL128_MAX_PRECISION, DECIMAL128_MAX_SCALE},
+    ipc::writer::FileWriter,
+    record_batch::RecordBatch,
+};
+use chrono::NaiveDate;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use sqlx::types::JsonValue;
+
+use crate::{
+    app::dispatch_pipeline::DispatchBatch,
+    config::server::AppError,
+    data::{
+        file_destination::{FileOutputFormat, ParquetCompression},
+        file_source::ColumnType,
+    },
+};
+
+// Only the last component of the uploaded file name is kept, so outputs
+// always land directly under the destination directory or prefix
+pub fn output_file_name(file_name: &str, batch_index: usize, format: &FileOutputFormat) -> String {
+    let base_name = file_name
+        .rsplit(|c| c == '/' || c == '\\')
+        .next()
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .unwrap_or("file");
+    let extension = match format {
+        FileOutputFormat::Json => "json",
+        FileOutputFormat::Parquet { .. } => "parquet",
+        FileOutputFormat::ArrowIpc => "arrow",
+    };
+    format!("{}-{:05}.{}", base_name, batch_index, extension)
+}
+
+pub fn encode_batch(
+    batch: &DispatchBatch,
+    format: &FileOutputFormat,
+) -> anyhow::Result<Vec<u8>, AppError> {
+    let contents = match format {
+        FileOutputFormat::Json => serde_json::to_vec(batch)?,
+        FileOutputFormat::Parquet {
+            row_group_size,
+            compression,
+        } => {
+            let record_batch = to_record_batch(batch)?;
+            let mut properties = WriterProperties::builder().set_compression(match compression {
+                None | Some(ParquetCompression::Snappy) => Compression::SNAPPY,
+                Some(ParquetCompression::Uncompressed) => Compression::UNCOMPRESSED,
+                Some(ParquetCompression::Gzip) => Compression::GZIP(GzipLevel::default()),
+                Some(ParquetCompression::Zstd) => Compression::ZSTD(ZstdLevel::default()),
+            });
+            if let Some(row_group_size) = row_group_size {
+                properties = properties.set_max_row_group_size(*row_group_size as usize);
+            }
+
+            let mut contents = Vec::new();
+            let mut writer = ArrowWriter::try_new(
+                &mut contents,
+                record_batch.schema(),
+                Some(properties.build()),
+            )
+            .context("Creating parquet writer")?;
+            writer
+                .write(&record_batch)
+                .context("Writing record batch to parquet")?;
+            writer.close().context("Finishing parquet file")?;
+            contents
+        }
+        FileOutputFormat::ArrowIpc => {
+            let record_batch = to_record_batch(batch)?;
+            let mut contents = Vec::new();
+            let mut writer = FileWriter::try_new(&mut contents, &record_batch.schema())
+                .context("Creating arrow IPC writer")?;
+            writer
+                .write(&record_batch)
+                .context("Writing record batch to arrow IPC")?;
+            writer.finish().context("Finishing arrow IPC file")?;
+            drop(writer);
+            contents
+        }
+    };
+
+    Ok(contents)
+}
+
+fn to_record_batch(batch: &DispatchBatch) -> anyhow::Result<RecordBatch, AppError> {
+    let mut fields = Vec::with_capacity(batch.column_names.len());
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.column_names.len());
+
+    for (idx, name) in batch.column_names.iter().enumerate() {
+        let values: Vec<Option<&JsonValue>> = batch.rows.iter().map(|row| row.get(idx)).collect();
+        // Columns without a declared type are written as text, guessing
+        // from the values would give every batch its own schema
+        let data_type = match batch.column_types.get(idx) {
+            Some(Some(column_type)) => declared_data_type(column_type),
+            _ => DataType::Utf8,
+        };
+
+        let column: ArrayRef = match data_type {
+            DataType::Int64 => Arc::new(Int64Array::from(
+                values
+                    .iter()
+                    .map(|v| v.and_then(as_i64))
+                    .collect::<Vec<_>>(),
+            )),
+            DataType::Decimal128(..) => decimal_column(&values),
+            DataType::Boolean => Arc::new(BooleanArray::from(
+                values
+                    .iter()
+                    .map(|v| v.and_then(as_bool))
+                    .collect::<Vec<_>>(),
+            )),
+            DataType::Date32 => Arc::new(Date32Array::from(
+                values
+                    .iter()
+                    .map(|v| v.and_then(as_date32))
+                    .collect::<Vec<_>>(),
+            )),
+            _ => string_column(&values),
+        };
+
+        fields.push(Field::new(name, column.data_type().clone(), true));
+        columns.push(column);
+    }
+
+    let record_batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .context("Building record batch from dispatch batch")?;
+
+    Ok(record_batch)
+}
+
+fn declared_data_type(column_type: &ColumnType) -> DataType {
+    match column_type {
+        ColumnType::Integer { .. } => DataType::Int64,
+        // The scale is only known once the values are seen, see decimal_column
+        ColumnType::Decimal { .. } => DataType::Decimal128(DECIMAL128_MAX_PRECISION, 0),
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Date { .. } => DataType::Date32,
+        ColumnType::String { .. } | ColumnType::Enum { .. } => DataType::Utf8,
+    }
+}
+
+// The column scale is the largest number of fractional digits among its
+// values. Columns that do not fit a 128 bit decimal are written as text
+// rather than rounded
+fn decimal_column(values: &[Option<&JsonValue>]) -> ArrayRef {
+    let mut parts = Vec::with_capacity(values.len());
+    for value in values {
+        match value {
+            None | Some(JsonValue::Null) => parts.push(None),
+            Some(JsonValue::String(s)) if s.is_empty() => parts.push(None),
+            Some(value) => match as_decimal(value) {
+                Some(part) => parts.push(Some(part)),
+                None => return string_column(values),
+            },
+        }
+    }
+
+    let scale = parts
+        .iter()
+        .flatten()
+        .map(|(_, scale)| *scale)
+        .max()
+        .unwrap_or(0);
+    if scale > DECIMAL128_MAX_SCALE as u32 {
+        return string_column(values);
+    }
+    let max_value = 10_i128.pow(DECIMAL128_MAX_PRECISION as u32);
+    let mut decimals = Vec::with_capacity(parts.len());
+    for part in parts {
+        let decimal = match part {
+            None => None,
+            Some((mantissa, own_scale)) => match 10_i128
+                .checked_pow(scale - own_scale)
+                .and_then(|factor| mantissa.checked_mul(factor))
+                .filter(|decimal| decimal.abs() < max_value)
+            {
+                Some(decimal) => Some(decimal),
+                None => return string_column(values),
+            },
+        };
+        decimals.push(decimal);
+    }
+
+    match Decimal128Array::from(decimals)
+        .with_precision_and_scale(DECIMAL128_MAX_PRECISION, scale as i8)
+    {
+        Ok(array) => Arc::new(array),
+        Err(_) => string_column(values),
+    }
+}
+
+fn string_column(values: &[Option<&JsonValue>]) -> ArrayRef {
+    Arc::new(StringArray::from(
+        values
+            .iter()
+            .map(|v| v.and_then(as_string))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+// Splits decimal text such as "-12.50" or "1.5e3" into its unscaled digits
+// and scale, without going through a float
+pub(crate) fn as_decimal(value: &JsonValue) -> Option<(i128, u32)> {
+    let text = match value {
+        JsonValue::Number(n) => n.to_string(),
+        JsonValue::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    let (number, exponent) = match text.split_once(|c| c == 'e' || c == 'E') {
+        Some((number, exponent)) => (number, exponent.parse::<i32>().ok()?),
+        None => (text.as_str(), 0),
+    };
+    let (negative, number) = match number.strip_prefix('-') {
+        Some(number) => (true, number),
+        None => (false, number.strip_prefix('+').unwrap_or(number)),
+    };
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let digits = format!("{}{}", integer, fraction);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut mantissa: i128 = digits.parse().ok()?;
+    let mut scale = fraction.len() as i64 - exponent as i64;
+    if scale < 0 {
+        mantissa = mantissa.checked_mul(10_i128.checked_pow(u32::try_from(-scale).ok()?)?)?;
+        scale = 0;
+    }
+    let mantissa = if negative { -mantissa } else { mantissa };
+
+    Some((mantissa, u32::try_from(scale).ok()?))
+}
+
+fn as_i64(value: &JsonValue) -> Option<i64> {
+    match value {
+        JsonValue::Number(n) => n.as_i64(),
+        JsonValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_bool(value: &JsonValue) -> Option<bool> {
+    match value {
+        JsonValue::Bool(b) => Some(*b),
+        JsonValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_date32(value: &JsonValue) -> Option<i32> {
+    // Typed date values are always normalized to ISO dates by the schema validation
+    let date = NaiveDate::parse_from_str(value.as_str()?, "%Y-%m-%d").ok()?;
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    Some((date - epoch).num_days() as i32)
+}
+
+fn as_string(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::datatypes::DataType;
+    use serde_json::json;
+
+    use super::{as_decimal, to_record_batch};
+    use crate::app::dispatch_pipeline::DispatchBatch;
+
+    fn undeclared_batch(rows: Vec<Vec<serde_json::Value>>) -> DispatchBatch {
+        DispatchBatch {
+            column_names: vec![String::from("amount")],
+            column_types: vec![None],
+            group: None,
+            headers: None,
+            rows,
+        }
+    }
+
+    #[test]
+    fn should_write_undeclared_columns_as_text_in_every_batch() {
+        for rows in [
+            vec![vec![json!("12")], vec![json!("7")]],
+            vec![vec![json!("1.5")], vec![json!(null)]],
+            vec![vec![json!(true)]],
+        ] {
+            let record_batch = to_record_batch(&undeclared_batch(rows)).unwrap();
+            assert_eq!(record_batch.schema().field(0).data_type(), &DataType::Utf8);
+        }
+    }
+
+    #[test]
+    fn should_split_decimal_text_without_rounding() {
+        assert_eq!(
+            as_decimal(&json!("12345678901234567.89")),
+            Some((1234567890123456789, 2))
+        );
+        assert_eq!(as_decimal(&json!("-0.050")), Some((-50, 3)));
+        assert_eq!(as_decimal(&json!("+7")), Some((7, 0)));
+        assert_eq!(as_decimal(&json!("1.5e3")), Some((1500, 0)));
+        assert_eq!(as_decimal(&json!("25E-3")), Some((25, 3)));
+        assert_eq!(as_decimal(&json!(2.5)), Some((25, 1)));
+        assert_eq!(as_decimal(&json!("1.2.3")), None);
+        assert_eq!(as_decimal(&json!("abc")), None);
+    }
+}
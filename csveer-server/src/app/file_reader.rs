@@ -0,0 +1,241 @@
+use std::{
+    io::{BufRead, BufReader, Read, Split},
+    iter::{Enumerate, Skip},
+};
+
+use anyhow::Context;
+
+use crate::{
+    app::rejected_rows::RejectedRow,
+    config::server::AppError,
+    data::file_source::{FileFormat, FileSource, FixedWidthColumn},
+};
+
+#[derive(Debug)]
+pub struct FileRow {
+    pub line: usize,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum FileRecord {
+    Row(FileRow),
+    Rejected(RejectedRow),
+}
+
+// Records are read one at a time, so a file never has to be held in memory
+// as a whole. Only failures to read the file itself end the iteration with
+// an error, malformed lines are rejected records
+pub struct FileReader<'a, R: Read> {
+    pub headers: Option<Vec<String>>,
+    records: Records<'a, R>,
+}
+
+enum Records<'a, R: Read> {
+    Delimited(csv::StringRecordsIntoIter<R>),
+    FixedWidth {
+        lines: Skip<Enumerate<Split<BufReader<R>>>>,
+        columns: &'a [FixedWidthColumn],
+        minimum_length: usize,
+    },
+}
+
+pub fn open_file<R: Read>(
+    file_source: &FileSource,
+    reader: R,
+) -> anyhow::Result<FileReader<'_, R>, AppError> {
+    match &file_source.format {
+        Some(FileFormat::Delimited { delimiter }) => {
+            open_delimited(reader, *delimiter, file_source.headers)
+        }
+        Some(FileFormat::FixedWidth { columns }) => {
+            Ok(open_fixed_width(reader, columns, file_source.headers))
+        }
+        None => open_delimited(reader, ',', file_source.headers),
+    }
+}
+
+// Columns of a file with neither a header row nor a schema are named after
+// their position, so the widest row has to be known before the rows are
+// read. Zero when the column names are known without reading the rows
+pub fn widest_row<R: Read>(file_source: &FileSource, reader: R) -> anyhow::Result<usize, AppError> {
+    let has_column_names = file_source.headers
+        || file_source.schema.is_some()
+        || matches!(file_source.format, Some(FileFormat::FixedWidth { .. }));
+    if has_column_names {
+        return Ok(0);
+    }
+
+    let mut widest_row = 0;
+    for record in open_file(file_source, reader)? {
+        if let FileRecord::Row(row) = record? {
+            widest_row = widest_row.max(row.values.len());
+        }
+    }
+    Ok(widest_row)
+}
+
+pub fn read_headers(
+    file_source: &FileSource,
+    contents: &[u8],
+) -> anyhow::Result<Option<Vec<String>>, AppError> {
+    let delimiter = match &file_source.format {
+        Some(FileFormat::Delimited { delimiter }) => *delimiter,
+        Some(FileFormat::FixedWidth { .. }) => return Ok(None),
+        None => ',',
+    };
+    if !file_source.headers {
+        return Ok(None);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(contents);
+    let headers = reader
+        .headers()
+        .context("Reading header row of delimited file")?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+
+    Ok(Some(headers))
+}
+
+fn open_delimited<'a, R: Read>(
+    reader: R,
+    delimiter: char,
+    has_headers: bool,
+) -> anyhow::Result<FileReader<'a, R>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(has_headers)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers = match has_headers {
+        true => Some(
+            reader
+                .headers()
+                .context("Reading header row of delimited file")?
+                .iter()
+                .map(String::from)
+                .collect(),
+        ),
+        false => None,
+    };
+
+    Ok(FileReader {
+        headers,
+        records: Records::Delimited(reader.into_records()),
+    })
+}
+
+// The header line of a fixed width file is only informative, the column
+// names always come from the format definition.
+fn open_fixed_width<R: Read>(
+    reader: R,
+    columns: &[FixedWidthColumn],
+    has_headers: bool,
+) -> FileReader<'_, R> {
+    let skipped_lines = match has_headers {
+        true => 1,
+        false => 0,
+    };
+    let minimum_length = columns.iter().map(|c| c.start).max().unwrap_or(0) as usize + 1;
+
+    FileReader {
+        headers: Some(columns.iter().map(|c| c.name.clone()).collect()),
+        records: Records::FixedWidth {
+            lines: BufReader::new(reader)
+                .split(b'\n')
+                .enumerate()
+                .skip(skipped_lines),
+            columns,
+            minimum_length,
+        },
+    }
+}
+
+impl<R: Read> Iterator for FileReader<'_, R> {
+    type Item = anyhow::Result<FileRecord, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.records {
+            Records::Delimited(records) => records.next().map(|record| match record {
+                Ok(record) => Ok(FileRecord::Row(FileRow {
+                    line: record.position().map_or(0, |p| p.line() as usize),
+                    values: record.iter().map(String::from).collect(),
+                })),
+                Err(err) if err.is_io_error() => Err(anyhow::Error::new(err)
+                    .context("Reading delimited file")
+                    .into()),
+                Err(err) => Ok(FileRecord::Rejected(RejectedRow {
+                    row: err.position().map_or(0, |p| p.line() as usize),
+                    column: None,
+                    reason: err.to_string(),
+                })),
+            }),
+            Records::FixedWidth {
+                lines,
+                columns,
+                minimum_length,
+            } => loop {
+                let (idx, line) = lines.next()?;
+                let line = match line.context("Reading fixed width file").and_then(|line| {
+                    String::from_utf8(line).context("Fixed width file is not valid UTF-8")
+                }) {
+                    Ok(line) => line,
+                    Err(err) => return Some(Err(err.into())),
+                };
+                let line = line.strip_suffix('\r').unwrap_or(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Some(Ok(read_fixed_width_line(
+                    idx + 1,
+                    line,
+                    columns,
+                    *minimum_length,
+                )));
+            },
+        }
+    }
+}
+
+fn read_fixed_width_line(
+    line_number: usize,
+    line: &str,
+    columns: &[FixedWidthColumn],
+    minimum_length: usize,
+) -> FileRecord {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() < minimum_length {
+        return FileRecord::Rejected(RejectedRow {
+            row: line_number,
+            column: None,
+            reason: format!(
+                "Line has {} characters, expected at least {}",
+                chars.len(),
+                minimum_length
+            ),
+        });
+    }
+    let values = columns
+        .iter()
+        .map(|column| {
+            let start = column.start as usize;
+            let end = (start + column.length as usize).min(chars.len());
+            let value: String = chars[start..end].iter().collect();
+            match column.trim {
+                true => value.trim().to_string(),
+                false => value,
+            }
+        })
+        .collect();
+    FileRecord::Row(FileRow {
+        line: line_number,
+        values,
+    })
+}
@@ -1,19 +1,41 @@
+use aws_sdk_sqs::Client as SQSClient;
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
 };
-use sqlx::PgPool;
+use serde::Deserialize;
+use sqlx::{PgConnection, PgPool};
 use tracing::{info, instrument};
 
 use crate::{
-    app::context::validate_context_name,
-    config::server::AppError,
+    app::{
+        configuration_version::{
+            diff_configurations, version_not_found, ConfigurationDiff, VersionDiffParams,
+        },
+        context::{validate_context_name, DeletionParams},
+        data_dispatch_queue::release_held_dispatch_messages,
+        file_destination::validate_file_source_destinations,
+        header_drift::initial_header_signature,
+    },
+    config::{masking::MaskingConfig, server::AppError},
     data::{
         context::{get_context_by_name, insert_context, CreatableContext},
-        file_source::{insert_file_source, FileSource, FileSourceCreation},
+        file_destination::list_by_file_source_id,
+        file_source::{
+            delete_file_source as delete_file_source_by_id, find_by_context_and_identifier,
+            insert_file_source, list_file_sources as list_all_file_sources,
+            update_file_source as update_file_source_by_id, update_file_source_state,
+            ColumnReference, FileFormat, FileSource, FileSourceCreation,
+        },
+        file_source_version::{
+            find_by_file_source_id_and_version, insert_file_source_version,
+            list_by_file_source_id as list_versions_by_file_source_id, FileSourceVersion,
+        },
     },
 };
 
+use super::validators;
+
 pub fn validate_file_source(
     creatable_file_source: &FileSourceCreation,
 ) -> anyhow::Result<(), AppError> {
@@ -24,6 +46,14 @@ pub fn validate_file_source(
         ));
     }
 
+    // Taken by the reference dataset routes of every context
+    if creatable_file_source.identifier == "reference-datasets" {
+        return Err(AppError::DetailedValidation(
+            String::from("Invalid file source identifier"),
+            vec![String::from("'reference-datasets' is reserved.")],
+        ));
+    }
+
     for char in creatable_file_source.identifier.chars() {
         if !&char.is_digit(36) && &char != &'-' {
             return Err(AppError::DetailedValidation(
@@ -39,30 +69,92 @@ pub fn validate_file_source(
     }
 
     if let Some(columns) = &creatable_file_source.hide_columns {
+        // Without a header row, schema or fixed width definition there is
+        // nothing to resolve column names against
+        let has_column_names = creatable_file_source.headers
+            || creatable_file_source.schema.is_some()
+            || matches!(
+                creatable_file_source.format,
+                Some(FileFormat::FixedWidth { .. })
+            );
         for col in columns {
-            if col.is_negative() {
+            match col {
+                ColumnReference::Index(idx) if idx.is_negative() => {
+                    return Err(AppError::DetailedValidation(
+                        String::from("Invalid hide column value"),
+                        vec![format!("{} is not a valid column index", idx)],
+                    ));
+                }
+                ColumnReference::Name(name) if name.is_empty() => {
+                    return Err(AppError::DetailedValidation(
+                        String::from("Invalid hide column value"),
+                        vec![String::from("Column name should not be blank")],
+                    ));
+                }
+                ColumnReference::Name(name) if !has_column_names => {
+                    return Err(AppError::DetailedValidation(
+                        String::from("Invalid hide column value"),
+                        vec![format!(
+                            "'{}' cannot be resolved, the file source has no column names",
+                            name
+                        )],
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    match &creatable_file_source.format {
+        Some(FileFormat::Delimited { delimiter }) => {
+            if !delimiter.is_ascii() || ['\n', '\r', '"'].contains(delimiter) {
                 return Err(AppError::DetailedValidation(
-                    String::from("Invalid hide column value"),
-                    vec![format!("{} is not a valid column index", col)],
+                    String::from("Invalid delimited format configuration"),
+                    vec![format!("'{}' cannot be used as a delimiter", delimiter)],
                 ));
             }
         }
+        Some(FileFormat::FixedWidth { columns }) => {
+            validators::fixed_width_format::validate(columns)?
+        }
+        None => {
+            info!("No file format set. File will be read as comma delimited.")
+        }
     }
 
-    Ok(())
-}
+    if let Some(schema) = &creatable_file_source.schema {
+        validators::schema::validate(schema, &creatable_file_source.format)?
+    }
 
-#[instrument(skip(db, creatable_file_source))]
-pub async fn create_file_source(
-    State(db): State<PgPool>,
-    Json(creatable_file_source): Json<FileSourceCreation>,
-) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
-    validate_context_name(&creatable_file_source.context)?;
-    validate_file_source(&creatable_file_source)?;
+    if let Some(error_threshold) = &creatable_file_source.error_threshold {
+        validators::error_threshold::validate(error_threshold)?
+    }
 
-    let mut tx = db.begin().await?;
+    if creatable_file_source.header_drift_policy.is_some()
+        && (!creatable_file_source.headers
+            || matches!(
+                creatable_file_source.format,
+                Some(FileFormat::FixedWidth { .. })
+            ))
+    {
+        return Err(AppError::DetailedValidation(
+            String::from("Invalid header drift policy"),
+            vec![String::from(
+                "Header drift can only be detected on delimited files with a header row",
+            )],
+        ));
+    }
+
+    Ok(())
+}
 
-    if get_context_by_name(&creatable_file_source.context, &mut *tx)
+// Stores an already validated file source as its first version, creating its
+// context when it does not exist yet
+pub async fn insert_new_file_source(
+    creatable_file_source: FileSourceCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSource, AppError> {
+    if get_context_by_name(&creatable_file_source.context, executor)
         .await
         .is_none()
     {
@@ -72,14 +164,341 @@ pub async fn create_file_source(
         );
         insert_context(
             CreatableContext::new(creatable_file_source.context.clone()),
-            &mut *tx,
+            executor,
         )
         .await?;
     }
 
-    let created_file_source = insert_file_source(creatable_file_source, &mut *tx).await?;
+    let header_signature = initial_header_signature(&creatable_file_source);
+    let configuration = serde_json::to_value(&creatable_file_source)?;
+    let created_file_source =
+        insert_file_source(creatable_file_source, header_signature, executor).await?;
+    insert_file_source_version(
+        &created_file_source.id,
+        &created_file_source.version,
+        configuration,
+        executor,
+    )
+    .await?;
 
+    Ok(created_file_source)
+}
+
+#[instrument(skip(db, creatable_file_source))]
+pub async fn create_file_source(
+    State(db): State<PgPool>,
+    Json(creatable_file_source): Json<FileSourceCreation>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    validate_context_name(&creatable_file_source.context)?;
+    validate_file_source(&creatable_file_source)?;
+
+    let mut tx = db.begin().await?;
+    let created_file_source = insert_new_file_source(creatable_file_source, &mut tx).await?;
     tx.commit().await?;
 
     Ok((StatusCode::CREATED, Json(created_file_source)))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct FileSourceListParams {
+    pub context: Option<String>,
+}
+
+pub async fn find_file_source(
+    context: &str,
+    identifier: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSource, AppError> {
+    find_by_context_and_identifier(context, identifier, executor)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "File source with context {} and identifier {} does not exist.",
+                context, identifier
+            ))
+        })
+}
+
+#[instrument(skip(db))]
+pub async fn list_file_sources(
+    State(db): State<PgPool>,
+    Query(params): Query<FileSourceListParams>,
+) -> anyhow::Result<(StatusCode, Json<Vec<FileSource>>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_sources = list_all_file_sources(params.context.as_deref(), &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(file_sources)))
+}
+
+#[instrument(skip(db))]
+pub async fn get_file_source(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &identifier, &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(file_source)))
+}
+
+// Every update stores the new configuration as the next version of the
+// file source
+pub async fn apply_file_source_update(
+    file_source: FileSource,
+    file_source_update: FileSourceCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSource, AppError> {
+    // A declared schema replaces the signature, otherwise the one learned from
+    // previous uploads is kept while the file still has a header row
+    let header_signature =
+        initial_header_signature(&file_source_update).or(match file_source_update.headers {
+            true => file_source.header_signature,
+            false => None,
+        });
+    let configuration = serde_json::to_value(&file_source_update)?;
+    let updated_file_source = update_file_source_by_id(
+        &file_source.id,
+        file_source_update,
+        header_signature,
+        executor,
+    )
+    .await?;
+    insert_file_source_version(
+        &updated_file_source.id,
+        &updated_file_source.version,
+        configuration,
+        executor,
+    )
+    .await?;
+
+    Ok(updated_file_source)
+}
+
+// The context and identifier locate the uploads of the file source, so
+// they cannot be changed by an update
+#[instrument(skip(db, file_source_update))]
+pub async fn update_file_source(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+    Json(file_source_update): Json<FileSourceCreation>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    if file_source_update.context != context || file_source_update.identifier != identifier {
+        return Err(AppError::Validation(String::from(
+            "The context and identifier of a file source cannot be changed.",
+        )));
+    }
+    validate_file_source(&file_source_update)?;
+
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &identifier, &mut tx).await?;
+    let updated_file_source =
+        apply_file_source_update(file_source, file_source_update, &mut tx).await?;
+    validate_file_source_destinations(&updated_file_source, &masking_config, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(updated_file_source)))
+}
+
+#[instrument(skip(db))]
+pub async fn list_file_source_versions(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<Vec<FileSourceVersion>>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &identifier, &mut conn).await?;
+    let versions = list_versions_by_file_source_id(&file_source.id, &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(versions)))
+}
+
+pub async fn find_file_source_version(
+    file_source: &FileSource,
+    version: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSourceVersion, AppError> {
+    find_by_file_source_id_and_version(&file_source.id, version, executor)
+        .await?
+        .ok_or_else(|| {
+            version_not_found(version, &format!("file source {}", file_source.identifier))
+        })
+}
+
+#[instrument(skip(db))]
+pub async fn diff_file_source_versions(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+    Query(params): Query<VersionDiffParams>,
+) -> anyhow::Result<(StatusCode, Json<ConfigurationDiff>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = find_file_source(&context, &identifier, &mut conn).await?;
+    let from = find_file_source_version(&file_source, &params.from, &mut conn).await?;
+    let to = find_file_source_version(&file_source, &params.to, &mut conn).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ConfigurationDiff {
+            from_version: from.version,
+            to_version: to.version,
+            changes: diff_configurations(&from.configuration, &to.configuration),
+        }),
+    ))
+}
+
+// Rolling back applies the configuration of an older version as a new
+// version, so the history is never rewritten. The file source keeps its
+// current context, which may have been renamed since
+#[instrument(skip(db))]
+pub async fn rollback_file_source(
+    State(db): State<PgPool>,
+    Path((context, identifier, version)): Path<(String, String, i32)>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &identifier, &mut tx).await?;
+    let file_source_version = find_file_source_version(&file_source, &version, &mut tx).await?;
+
+    let mut file_source_update: FileSourceCreation =
+        serde_json::from_value(file_source_version.configuration).map_err(|err| {
+            AppError::Validation(format!(
+                "Version {} of file source {} can no longer be applied. {}",
+                version, identifier, err
+            ))
+        })?;
+    file_source_update.context = context;
+    file_source_update.identifier = identifier;
+    validate_file_source(&file_source_update)?;
+
+    info!(
+        "Rolling back file source {} from version {} to version {}",
+        file_source.identifier, file_source.version, version
+    );
+    let masking_config = envy::from_env::<MaskingConfig>()?;
+    let updated_file_source =
+        apply_file_source_update(file_source, file_source_update, &mut tx).await?;
+    validate_file_source_destinations(&updated_file_source, &masking_config, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(updated_file_source)))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StateSwitch {
+    Pause,
+    Resume,
+    Enable,
+    Disable,
+}
+
+impl StateSwitch {
+    // The `(enabled, paused)` state after switching
+    pub fn apply(&self, enabled: bool, paused: bool) -> (bool, bool) {
+        match self {
+            StateSwitch::Pause => (enabled, true),
+            StateSwitch::Resume => (enabled, false),
+            StateSwitch::Enable => (true, paused),
+            StateSwitch::Disable => (false, paused),
+        }
+    }
+}
+
+// Resuming a file source releases the messages held by its destinations,
+// except the ones that are still paused themselves. Resuming it again
+// retries the messages that could not be released
+async fn switch_file_source(
+    db: PgPool,
+    sqs_client: SQSClient,
+    context: String,
+    identifier: String,
+    state_switch: StateSwitch,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &identifier, &mut tx).await?;
+    let (enabled, paused) = state_switch.apply(file_source.enabled, file_source.paused);
+
+    info!(
+        "Switching file source {} of context {} with {:?}",
+        identifier, context, state_switch
+    );
+    let updated_file_source =
+        update_file_source_state(&file_source.id, enabled, paused, &mut tx).await?;
+    tx.commit().await?;
+
+    if matches!(state_switch, StateSwitch::Resume) {
+        let mut conn = db.acquire().await?;
+        for file_destination in list_by_file_source_id(&file_source.id, &mut conn).await? {
+            if !file_destination.paused {
+                release_held_dispatch_messages(&sqs_client, &file_destination, &mut conn).await?;
+            }
+        }
+    }
+
+    Ok((StatusCode::OK, Json(updated_file_source)))
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn pause_file_source(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    switch_file_source(db, sqs_client, context, identifier, StateSwitch::Pause).await
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn resume_file_source(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    switch_file_source(db, sqs_client, context, identifier, StateSwitch::Resume).await
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn enable_file_source(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    switch_file_source(db, sqs_client, context, identifier, StateSwitch::Enable).await
+}
+
+#[instrument(skip(db, sqs_client))]
+pub async fn disable_file_source(
+    State(db): State<PgPool>,
+    State(sqs_client): State<SQSClient>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<FileSource>), AppError> {
+    switch_file_source(db, sqs_client, context, identifier, StateSwitch::Disable).await
+}
+
+// A file source with destinations is only deleted when `cascade` is set,
+// which deletes the destinations and their dispatch history with it
+#[instrument(skip(db))]
+pub async fn delete_file_source(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+    Query(params): Query<DeletionParams>,
+) -> anyhow::Result<StatusCode, AppError> {
+    let mut tx = db.begin().await?;
+    let file_source = find_file_source(&context, &identifier, &mut tx).await?;
+
+    let file_destinations = list_by_file_source_id(&file_source.id, &mut tx).await?;
+    if !file_destinations.is_empty() && !params.cascade.unwrap_or(false) {
+        return Err(AppError::Validation(format!(
+            "File source {} still has {} file destinations. Delete them first or set cascade=true.",
+            identifier,
+            file_destinations.len()
+        )));
+    }
+
+    info!(
+        "Deleting file source {} of context {} with {} file destinations",
+        identifier,
+        context,
+        file_destinations.len()
+    );
+    delete_file_source_by_id(&file_source.id, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
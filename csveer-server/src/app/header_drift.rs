@@ -0,0 +1,147 @@
+use aws_sdk_s3::Client as S3Client;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::{error, info, instrument, warn};
+
+use crate::{
+    app::file_reader::read_headers,
+    commons::file_storage::{retrieve_file_head, PENDING_CSV_FILES_BUCKET},
+    config::server::AppError,
+    data::{
+        file_source::{
+            find_by_context_and_identifier, update_header_signature, FileFormat, FileSource,
+            FileSourceCreation, HeaderDriftPolicy,
+        },
+        header_drift_event::{
+            insert_header_drift_event, list_by_file_source_id, HeaderDriftEvent,
+            HeaderDriftEventCreation,
+        },
+    },
+};
+
+const HEADER_READ_BYTES: usize = 64 * 1024;
+
+pub fn initial_header_signature(creatable_file_source: &FileSourceCreation) -> Option<Vec<String>> {
+    if !creatable_file_source.headers
+        || matches!(
+            creatable_file_source.format,
+            Some(FileFormat::FixedWidth { .. })
+        )
+    {
+        return None;
+    }
+    creatable_file_source
+        .schema
+        .as_ref()
+        .map(|schema| schema.iter().map(|column| column.name.clone()).collect())
+}
+
+// Returns whether the file should still be dispatched to the destinations
+#[instrument(skip(s3_client, file_source, executor))]
+pub async fn check_header_drift(
+    s3_client: &S3Client,
+    file_source: &FileSource,
+    object_key: &str,
+    file_name: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<bool, AppError> {
+    if !file_source.headers {
+        return Ok(true);
+    }
+
+    let contents = retrieve_file_head(
+        s3_client,
+        PENDING_CSV_FILES_BUCKET,
+        object_key,
+        HEADER_READ_BYTES,
+    )
+    .await?;
+    let found_headers = match read_headers(file_source, &contents)? {
+        Some(headers) => headers,
+        None => return Ok(true),
+    };
+
+    let expected_headers = match &file_source.header_signature {
+        Some(signature) => signature,
+        None => {
+            info!(
+                "Storing header signature {:?} from first upload of file source {}",
+                found_headers, file_source.identifier
+            );
+            update_header_signature(&file_source.id, &found_headers, executor).await?;
+            return Ok(true);
+        }
+    };
+
+    if *expected_headers == found_headers {
+        return Ok(true);
+    }
+
+    let policy = file_source
+        .header_drift_policy
+        .unwrap_or(HeaderDriftPolicy::Warn);
+    let event = insert_header_drift_event(
+        HeaderDriftEventCreation {
+            file_source_id: file_source.id,
+            file_name: file_name.to_string(),
+            expected_headers: expected_headers.clone(),
+            found_headers: found_headers.clone(),
+            policy,
+        },
+        executor,
+    )
+    .await?;
+
+    match policy {
+        HeaderDriftPolicy::Reject => {
+            error!(
+                "Header drift detected on file {} (event {}). Expected {:?}, found {:?}. File will not be dispatched.",
+                file_name, event.id, expected_headers, found_headers
+            );
+            Ok(false)
+        }
+        HeaderDriftPolicy::Warn => {
+            warn!(
+                "Header drift detected on file {} (event {}). Expected {:?}, found {:?}.",
+                file_name, event.id, expected_headers, found_headers
+            );
+            Ok(true)
+        }
+        HeaderDriftPolicy::AcceptAndUpdate => {
+            info!(
+                "Header drift detected on file {} (event {}). Header signature updated to {:?}.",
+                file_name, event.id, found_headers
+            );
+            update_header_signature(&file_source.id, &found_headers, executor).await?;
+            Ok(true)
+        }
+    }
+}
+
+#[instrument(skip(db))]
+pub async fn list_header_drift_events(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<Vec<HeaderDriftEvent>>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = match find_by_context_and_identifier(&context, &identifier, &mut conn).await?
+    {
+        Some(fs) => fs,
+        None => {
+            let message = format!(
+                "File source with context {} and identifier {} does not exist.",
+                context, identifier
+            );
+            info!(message);
+            return Err(AppError::Validation(message));
+        }
+    };
+
+    let events = list_by_file_source_id(&file_source.id, &mut conn).await?;
+
+    Ok((StatusCode::OK, Json(events)))
+}
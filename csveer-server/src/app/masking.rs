@@ -0,0 +1,313 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::types::JsonValue;
+
+use crate::{
+    config::{masking::MaskingConfig, server::AppError},
+    data::{
+        data_dispatch::MaskedColumn,
+        file_destination::{ColumnMasking, MaskingStrategy},
+        file_source::{ColumnReference, ColumnType},
+    },
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MASK_CHARACTER: char = '*';
+
+enum Mask<'a> {
+    Redact,
+    KeepLast(usize),
+    Hash(&'a [u8]),
+    FormatPreserving(&'a [u8]),
+}
+
+pub struct CompiledMasking<'a> {
+    columns: Vec<(usize, Mask<'a>)>,
+    pub masked_columns: Vec<MaskedColumn>,
+}
+
+impl<'a> CompiledMasking<'a> {
+    pub fn new(
+        maskings: &'a [ColumnMasking],
+        column_names: &[String],
+        column_types: &mut [Option<ColumnType>],
+        masking_config: &'a MaskingConfig,
+    ) -> anyhow::Result<Self, AppError> {
+        let mut columns = Vec::with_capacity(maskings.len());
+        let mut masked_columns = Vec::with_capacity(maskings.len());
+
+        for masking in maskings {
+            let column = match &masking.column {
+                ColumnReference::Index(idx) => Some(*idx as usize),
+                ColumnReference::Name(name) => column_names.iter().position(|c| c == name),
+            }
+            .filter(|column| *column < column_names.len())
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Masked column {} does not exist. Found: {}",
+                    masking.column,
+                    column_names.join(", ")
+                ))
+            })?;
+
+            let mask = match &masking.strategy {
+                MaskingStrategy::Redact => Mask::Redact,
+                MaskingStrategy::KeepLast { characters } => Mask::KeepLast(*characters as usize),
+                MaskingStrategy::Hash { key } => Mask::Hash(find_key(masking_config, key)?),
+                MaskingStrategy::FormatPreserving { key } => {
+                    Mask::FormatPreserving(find_key(masking_config, key)?)
+                }
+            };
+            // Apart from redacted ones, masked values are always written as text
+            if !matches!(mask, Mask::Redact) {
+                column_types[column] = None;
+            }
+
+            masked_columns.push(MaskedColumn {
+                column: column_names[column].clone(),
+                strategy: masking.strategy.name().to_string(),
+            });
+            columns.push((column, mask));
+        }
+
+        Ok(Self {
+            columns,
+            masked_columns,
+        })
+    }
+
+    pub fn apply(&self, row: &mut [JsonValue]) {
+        for (column, mask) in &self.columns {
+            let value = match row.get(*column) {
+                Some(JsonValue::Null) | None => continue,
+                Some(JsonValue::String(text)) => text.clone(),
+                Some(other) => other.to_string(),
+            };
+            row[*column] = match mask {
+                Mask::Redact => JsonValue::Null,
+                Mask::KeepLast(characters) => JsonValue::String(keep_last(&value, *characters)),
+                Mask::Hash(key) => JsonValue::String(hex::encode(keyed_digest(key, &value, 0))),
+                Mask::FormatPreserving(key) => JsonValue::String(format_preserving(key, &value)),
+            };
+        }
+    }
+}
+
+fn find_key<'a>(
+    masking_config: &'a MaskingConfig,
+    key: &str,
+) -> anyhow::Result<&'a [u8], AppError> {
+    masking_config
+        .key(key)
+        .ok_or_else(|| AppError::Validation(format!("Masking key '{}' is not configured", key)))
+}
+
+fn keep_last(value: &str, characters: usize) -> String {
+    let length = value.chars().count();
+    value
+        .chars()
+        .enumerate()
+        .map(|(idx, c)| match idx + characters < length {
+            true => MASK_CHARACTER,
+            false => c,
+        })
+        .collect()
+}
+
+fn keyed_digest(key: &[u8], value: &str, counter: u32) -> Vec<u8> {
+    // SAFETY: HMAC accepts keys of any length
+    let mut mac = HmacSha256::new_from_slice(key).unwrap();
+    mac.update(value.as_bytes());
+    if counter > 0 {
+        mac.update(&counter.to_be_bytes());
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Deterministically replaces digits with digits and letters with letters of
+// the same case, leaving separators in place, so masked values still pass
+// format checks downstream
+fn format_preserving(key: &[u8], value: &str) -> String {
+    let mut stream: Vec<u8> = Vec::new();
+    let mut counter = 0;
+    value
+        .chars()
+        .enumerate()
+        .map(|(idx, c)| {
+            while stream.len() <= idx {
+                stream.extend(keyed_digest(key, value, counter));
+                counter += 1;
+            }
+            let shift = stream[idx] as u32;
+            match c {
+                '0'..='9' => shift_within(c, '0', 10, shift),
+                'a'..='z' => shift_within(c, 'a', 26, shift),
+                'A'..='Z' => shift_within(c, 'A', 26, shift),
+                other => other,
+            }
+        })
+        .collect()
+}
+
+fn shift_within(c: char, first: char, range: u32, shift: u32) -> char {
+    let offset = (c as u32 - first as u32 + shift) % range;
+    // SAFETY: the result stays within the ASCII range of `first`
+    char::from_u32(first as u32 + offset).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::types::JsonValue;
+
+    use super::{keep_last, CompiledMasking};
+    use crate::{
+        config::masking::MaskingConfig,
+        data::{
+            file_destination::{ColumnMasking, MaskingStrategy},
+            file_source::{ColumnReference, ColumnType},
+        },
+    };
+
+    fn masking_config() -> MaskingConfig {
+        MaskingConfig {
+            masking_keys: vec![String::from("primary:secret"), String::from("other:s3cr3t")],
+        }
+    }
+
+    fn masking(strategy: MaskingStrategy) -> Vec<ColumnMasking> {
+        vec![ColumnMasking {
+            column: ColumnReference::Name(String::from("card")),
+            strategy,
+        }]
+    }
+
+    fn mask(strategy: MaskingStrategy, value: JsonValue) -> JsonValue {
+        let masking_config = masking_config();
+        let maskings = masking(strategy);
+        let compiled = CompiledMasking::new(
+            &maskings,
+            &[String::from("card")],
+            &mut [None],
+            &masking_config,
+        )
+        .unwrap();
+        let mut row = vec![value];
+        compiled.apply(&mut row);
+        row.remove(0)
+    }
+
+    fn hash(key: &str) -> MaskingStrategy {
+        MaskingStrategy::Hash {
+            key: String::from(key),
+        }
+    }
+
+    fn format_preserving(key: &str) -> MaskingStrategy {
+        MaskingStrategy::FormatPreserving {
+            key: String::from(key),
+        }
+    }
+
+    #[test]
+    fn should_keep_last_characters() {
+        assert_eq!(keep_last("4111-1111", 4), "*****1111");
+        assert_eq!(keep_last("çãõ", 1), "**õ");
+        assert_eq!(keep_last("12", 4), "12");
+    }
+
+    #[test]
+    fn should_hash_with_configured_key() {
+        let value = json!("4111111111111111");
+        let hashed = mask(hash("primary"), value.clone());
+
+        assert_eq!(hashed, mask(hash("primary"), value.clone()));
+        assert_ne!(hashed, mask(hash("other"), value.clone()));
+        assert_eq!(hashed.as_str().map(str::len), Some(64));
+    }
+
+    #[test]
+    fn should_preserve_format_of_value() {
+        let masked = mask(format_preserving("primary"), json!("AB-12cd"));
+        let masked = masked.as_str().unwrap();
+
+        assert_eq!(
+            masked,
+            mask(format_preserving("primary"), json!("AB-12cd"))
+                .as_str()
+                .unwrap()
+        );
+        assert_eq!(masked.len(), 7);
+        assert_eq!(&masked[2..3], "-");
+        assert!(masked[..2].chars().all(|c| c.is_ascii_uppercase()));
+        assert!(masked[3..5].chars().all(|c| c.is_ascii_digit()));
+        assert!(masked[5..].chars().all(|c| c.is_ascii_lowercase()));
+    }
+
+    #[test]
+    fn should_mask_non_text_values_as_text_and_skip_nulls() {
+        assert_eq!(
+            mask(MaskingStrategy::KeepLast { characters: 2 }, json!(12345)),
+            json!("***45")
+        );
+        assert_eq!(mask(hash("primary"), JsonValue::Null), JsonValue::Null);
+        assert_eq!(
+            mask(MaskingStrategy::Redact, json!("secret")),
+            JsonValue::Null
+        );
+    }
+
+    #[test]
+    fn should_only_keep_column_type_of_redacted_columns() {
+        let masking_config = masking_config();
+        let integer = || ColumnType::Integer {
+            min: None,
+            max: None,
+        };
+
+        let redact = masking(MaskingStrategy::Redact);
+        let mut column_types = [Some(integer())];
+        CompiledMasking::new(
+            &redact,
+            &[String::from("card")],
+            &mut column_types,
+            &masking_config,
+        )
+        .unwrap();
+        assert!(column_types[0].is_some());
+
+        let keep_last = masking(MaskingStrategy::KeepLast { characters: 2 });
+        let mut column_types = [Some(integer())];
+        CompiledMasking::new(
+            &keep_last,
+            &[String::from("card")],
+            &mut column_types,
+            &masking_config,
+        )
+        .unwrap();
+        assert!(column_types[0].is_none());
+    }
+
+    #[test]
+    fn should_fail_on_unknown_key_or_column() {
+        let masking_config = masking_config();
+        let unknown_key = masking(hash("missing"));
+        assert!(CompiledMasking::new(
+            &unknown_key,
+            &[String::from("card")],
+            &mut [None],
+            &masking_config,
+        )
+        .is_err());
+
+        let redact = masking(MaskingStrategy::Redact);
+        assert!(CompiledMasking::new(
+            &redact,
+            &[String::from("account")],
+            &mut [None],
+            &masking_config,
+        )
+        .is_err());
+    }
+}
@@ -1,7 +1,27 @@
+pub mod aggregation;
+pub mod batch_window;
+pub mod configuration_document;
+pub mod configuration_version;
 pub mod context;
+pub mod data_dispatch;
 pub mod data_dispatch_queue;
+pub mod deduplication;
+pub mod destination_preview;
+pub mod dispatch_pipeline;
+pub mod expressions;
 pub mod file_destination;
+pub mod file_ingestion;
 pub mod file_ingestion_queue;
 pub mod file_input;
+pub mod file_output;
+pub mod file_reader;
 pub mod file_source;
+pub mod header_drift;
+pub mod masking;
+pub mod reference_dataset;
+pub mod rejected_rows;
+pub mod schema_inference;
+pub mod schema_validation;
+pub mod sorting;
+pub mod transformations;
 pub mod validators;
@@ -0,0 +1,186 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use axum::{
+    extract::{Multipart, Path, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument};
+
+use crate::{
+    config::server::AppError,
+    data::{
+        file_destination::Transformation,
+        reference_dataset::{
+            find_by_context_and_identifier, upsert_reference_dataset, ReferenceDataset,
+            ReferenceDatasetCreation,
+        },
+    },
+};
+
+pub type ReferenceDatasets = HashMap<String, ReferenceDataset>;
+
+fn validate_identifier(identifier: &str) -> anyhow::Result<(), AppError> {
+    if identifier.is_empty() {
+        return Err(AppError::Validation(String::from(
+            "Reference dataset identifier should not be blank.",
+        )));
+    }
+
+    for char in identifier.chars() {
+        if !&char.is_digit(36) && &char != &'-' {
+            return Err(AppError::Validation(format!(
+                "Reference dataset identifier should only contain numbers or charaters. Found char '{}'",
+                &char
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_reference_csv(
+    contents: &[u8],
+) -> anyhow::Result<(Vec<String>, Vec<Vec<String>>), AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(contents);
+    let columns: Vec<String> = reader
+        .headers()
+        .context("Reading header row of reference dataset")?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+
+    let mut issues = Vec::new();
+    let mut seen = HashSet::new();
+    for column in &columns {
+        if column.is_empty() {
+            issues.push(String::from("Column names should not be blank"));
+        } else if !seen.insert(column) {
+            issues.push(format!("Column '{}' is duplicated", column));
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        match record {
+            Ok(record) => rows.push(record.iter().map(|value| value.to_string()).collect()),
+            Err(err) => issues.push(format!("Row {}: {}", idx + 1, err)),
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("Invalid reference dataset"),
+            issues,
+        ));
+    }
+
+    Ok((columns, rows))
+}
+
+#[instrument(skip(db, multipart))]
+pub async fn upload_reference_dataset(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+    mut multipart: Multipart,
+) -> anyhow::Result<(StatusCode, Json<ReferenceDataset>), AppError> {
+    validate_identifier(&identifier)?;
+
+    let contents = match multipart.next_field().await {
+        Ok(Some(field)) => field.bytes().await?,
+        _ => {
+            return Err(AppError::Validation(String::from(
+                "A CSV file with headers should be provided as reference dataset",
+            )))
+        }
+    };
+    let (columns, rows) = parse_reference_csv(&contents)?;
+
+    info!(
+        "Storing reference dataset {} of context {} with {} rows",
+        identifier,
+        context,
+        rows.len()
+    );
+
+    let mut conn = db.acquire().await?;
+    let reference_dataset = upsert_reference_dataset(
+        ReferenceDatasetCreation {
+            context,
+            identifier,
+            columns,
+            rows,
+        },
+        &mut conn,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(reference_dataset)))
+}
+
+#[instrument(skip(db))]
+pub async fn get_reference_dataset(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+) -> anyhow::Result<(StatusCode, Json<ReferenceDataset>), AppError> {
+    let mut conn = db.acquire().await?;
+    match find_by_context_and_identifier(&context, &identifier, &mut conn).await? {
+        Some(reference_dataset) => Ok((StatusCode::OK, Json(reference_dataset))),
+        None => Err(AppError::Validation(format!(
+            "Reference dataset with context {} and identifier {} does not exist.",
+            context, identifier
+        ))),
+    }
+}
+
+// Loads every dataset used by the lookups of a destination, checking the
+// columns they read still exist in the dataset
+pub async fn load_reference_datasets(
+    context: &str,
+    transformations: &[Transformation],
+    executor: &mut PgConnection,
+) -> anyhow::Result<ReferenceDatasets, AppError> {
+    let mut reference_datasets = ReferenceDatasets::new();
+
+    for transformation in transformations {
+        let (dataset, reference_column, fields) = match transformation {
+            Transformation::Lookup {
+                dataset,
+                reference_column,
+                fields,
+                ..
+            } => (dataset, reference_column, fields),
+            _ => continue,
+        };
+
+        if !reference_datasets.contains_key(dataset) {
+            let reference_dataset = find_by_context_and_identifier(context, dataset, executor)
+                .await?
+                .ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Reference dataset {} does not exist in context {}.",
+                        dataset, context
+                    ))
+                })?;
+            reference_datasets.insert(dataset.clone(), reference_dataset);
+        }
+
+        let reference_dataset = &reference_datasets[dataset];
+        for column in std::iter::once(reference_column).chain(fields) {
+            if !reference_dataset.columns.contains(column) {
+                return Err(AppError::Validation(format!(
+                    "Reference dataset {} has no column '{}'. Found: {}",
+                    dataset,
+                    column,
+                    reference_dataset.columns.join(", ")
+                )));
+            }
+        }
+    }
+
+    Ok(reference_datasets)
+}
@@ -0,0 +1,58 @@
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{config::server::AppError, data::file_source::ErrorThreshold};
+
+pub const REJECTS_FILE_SUFFIX: &str = ".rejects.csv";
+
+#[derive(Debug, Serialize)]
+pub struct RejectedRow {
+    pub row: usize,
+    pub column: Option<String>,
+    pub reason: String,
+}
+
+// Rejects are kept per destination, since each one can type and transform
+// the same file differently
+pub fn rejects_file_name(object_key: &str, file_destination: &str) -> String {
+    match object_key.rsplit_once('/') {
+        Some((prefix, file_name)) => format!(
+            "{}/{}/{}{}",
+            prefix, file_destination, file_name, REJECTS_FILE_SUFFIX
+        ),
+        None => format!("{}/{}{}", file_destination, object_key, REJECTS_FILE_SUFFIX),
+    }
+}
+
+pub fn encode_rejected_rows(rejected_rows: &[RejectedRow]) -> anyhow::Result<Vec<u8>, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for rejected_row in rejected_rows {
+        writer
+            .serialize(rejected_row)
+            .context("Writing rejected row")?;
+    }
+    let contents = writer
+        .into_inner()
+        .context("Finishing rejected rows file")?;
+    Ok(contents)
+}
+
+pub fn exceeds_threshold(
+    threshold: &Option<ErrorThreshold>,
+    rejected_rows: usize,
+    total_rows: usize,
+) -> bool {
+    if rejected_rows == 0 {
+        return false;
+    }
+
+    match threshold {
+        Some(ErrorThreshold::Absolute { max_rejected_rows }) => {
+            rejected_rows > *max_rejected_rows as usize
+        }
+        Some(ErrorThreshold::Percentage {
+            max_rejected_percentage,
+        }) => (rejected_rows as f64 * 100.0 / total_rows as f64) > *max_rejected_percentage,
+        None => true,
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Multipart, Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{error, info, instrument};
+
+use crate::{
+    app::file_reader::{open_file, FileRecord},
+    config::server::AppError,
+    data::file_source::{
+        find_by_context_and_identifier, ColumnSchema, ColumnType, FileFormat, FileSource,
+    },
+};
+
+const SAMPLE_ROWS: usize = 1000;
+const CANDIDATE_DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+const CANDIDATE_DATE_FORMATS: [&str; 6] = [
+    "%Y-%m-%d", "%d/%m/%Y", "%m/%d/%Y", "%Y/%m/%d", "%d-%m-%Y", "%Y%m%d",
+];
+
+#[derive(Debug, Serialize)]
+pub struct EncodingInference {
+    name: String,
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DialectInference {
+    delimiter: char,
+    quote: char,
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeaderInference {
+    present: bool,
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnInference {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    nullable: bool,
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaInference {
+    encoding: EncodingInference,
+    dialect: Option<DialectInference>,
+    headers: HeaderInference,
+    columns: Vec<ColumnInference>,
+    schema: Vec<ColumnSchema>,
+}
+
+#[instrument(skip(db, multipart))]
+pub async fn infer_schema(
+    State(db): State<PgPool>,
+    Path((context, identifier)): Path<(String, String)>,
+    mut multipart: Multipart,
+) -> anyhow::Result<(StatusCode, Json<SchemaInference>), AppError> {
+    let mut conn = db.acquire().await?;
+    let file_source = match find_by_context_and_identifier(&context, &identifier, &mut conn).await?
+    {
+        Some(fs) => fs,
+        None => {
+            let message = format!(
+                "No file source found for context {} and identifier {}",
+                context, identifier
+            );
+            error!(message);
+            return Err(AppError::Validation(message));
+        }
+    };
+
+    let sample = match multipart.next_field().await {
+        Ok(Some(field)) => field.bytes().await?,
+        _ => {
+            return Err(AppError::Validation(String::from(
+                "A sample file should be provided to infer its schema",
+            )))
+        }
+    };
+
+    info!(
+        "Inferring schema of a {} bytes sample for file source {}",
+        sample.len(),
+        file_source.identifier
+    );
+
+    Ok((StatusCode::OK, Json(infer(&file_source, &sample)?)))
+}
+
+fn infer(file_source: &FileSource, sample: &[u8]) -> anyhow::Result<SchemaInference, AppError> {
+    let (encoding, text) = detect_encoding(sample);
+
+    let (dialect, rows) = match &file_source.format {
+        Some(FileFormat::FixedWidth { .. }) => {
+            let rows = open_file(file_source, text.as_bytes())?
+                .filter_map(|record| match record {
+                    Ok(FileRecord::Row(row)) => Some(Ok(row.values)),
+                    Ok(FileRecord::Rejected(_)) => None,
+                    Err(err) => Some(Err(err)),
+                })
+                .take(SAMPLE_ROWS)
+                .collect::<Result<_, _>>()?;
+            (None, rows)
+        }
+        _ => {
+            let dialect = detect_dialect(&text);
+            let rows = split_rows(&text, dialect.delimiter);
+            (Some(dialect), rows)
+        }
+    };
+
+    if rows.is_empty() {
+        return Err(AppError::Validation(String::from(
+            "Sample file has no rows to infer a schema from",
+        )));
+    }
+
+    let column_count = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let headers = match &file_source.format {
+        // Fixed width column names come from the format definition
+        Some(FileFormat::FixedWidth { .. }) => HeaderInference {
+            present: false,
+            confidence: 1.0,
+        },
+        _ => detect_headers(&rows, column_count),
+    };
+
+    let (header_row, data_rows) = match headers.present {
+        true => (Some(&rows[0]), &rows[1..]),
+        false => (None, &rows[..]),
+    };
+
+    let mut columns = Vec::with_capacity(column_count);
+    let mut schema = Vec::with_capacity(column_count);
+    for idx in 0..column_count {
+        let name = match (&file_source.format, header_row) {
+            (Some(FileFormat::FixedWidth { columns }), _) => columns[idx].name.clone(),
+            (_, Some(header_row)) => header_row
+                .get(idx)
+                .filter(|name| !name.trim().is_empty())
+                .map(|name| name.trim().to_string())
+                .unwrap_or_else(|| format!("column_{}", idx)),
+            _ => format!("column_{}", idx),
+        };
+        let values: Vec<&str> = data_rows
+            .iter()
+            .map(|row| row.get(idx).map_or("", |v| v.trim()))
+            .collect();
+        let (column_type, confidence) = infer_column_type(&values);
+        let nullable = values.iter().any(|v| v.is_empty());
+
+        columns.push(ColumnInference {
+            name: name.clone(),
+            kind: type_name(&column_type).to_string(),
+            nullable,
+            confidence,
+        });
+        schema.push(ColumnSchema {
+            name,
+            column_type,
+            nullable,
+        });
+    }
+
+    Ok(SchemaInference {
+        encoding,
+        dialect,
+        headers,
+        columns,
+        schema,
+    })
+}
+
+fn detect_encoding(sample: &[u8]) -> (EncodingInference, String) {
+    if let Some(without_bom) = sample.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        if let Ok(text) = std::str::from_utf8(without_bom) {
+            return (
+                EncodingInference {
+                    name: String::from("UTF-8-BOM"),
+                    confidence: 1.0,
+                },
+                text.to_string(),
+            );
+        }
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(text) => {
+            // Pure ASCII is valid in most encodings, so UTF-8 is only a guess
+            let confidence = match text.is_ascii() {
+                true => 0.8,
+                false => 1.0,
+            };
+            (
+                EncodingInference {
+                    name: String::from("UTF-8"),
+                    confidence,
+                },
+                text.to_string(),
+            )
+        }
+        Err(_) => (
+            EncodingInference {
+                name: String::from("ISO-8859-1"),
+                confidence: 0.5,
+            },
+            sample.iter().map(|b| *b as char).collect(),
+        ),
+    }
+}
+
+fn detect_dialect(text: &str) -> DialectInference {
+    let mut best = DialectInference {
+        delimiter: ',',
+        quote: '"',
+        confidence: 0.0,
+    };
+
+    for delimiter in CANDIDATE_DELIMITERS {
+        let rows = split_rows(text, delimiter);
+        if rows.is_empty() {
+            continue;
+        }
+
+        let mut field_counts: HashMap<usize, usize> = HashMap::new();
+        for row in &rows {
+            *field_counts.entry(row.len()).or_default() += 1;
+        }
+        let (mode, occurrences) = field_counts
+            .into_iter()
+            .max_by_key(|(count, occurrences)| (*occurrences, *count))
+            .unwrap();
+        if mode < 2 {
+            continue;
+        }
+
+        let confidence = occurrences as f64 / rows.len() as f64;
+        if confidence > best.confidence {
+            best = DialectInference {
+                delimiter,
+                quote: '"',
+                confidence,
+            };
+        }
+    }
+
+    best
+}
+
+fn split_rows(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes())
+        .records()
+        .take(SAMPLE_ROWS)
+        .filter_map(|record| record.ok())
+        .map(|record| record.iter().map(String::from).collect())
+        .collect()
+}
+
+fn detect_headers(rows: &[Vec<String>], column_count: usize) -> HeaderInference {
+    if rows.len() < 2 {
+        return HeaderInference {
+            present: false,
+            confidence: 0.5,
+        };
+    }
+
+    let first_row = &rows[0];
+    let mut typed_columns = 0;
+    let mut header_like_columns = 0;
+
+    for idx in 0..column_count {
+        let values: Vec<&str> = rows[1..]
+            .iter()
+            .map(|row| row.get(idx).map_or("", |v| v.trim()))
+            .collect();
+        let (column_type, _) = infer_column_type(&values);
+        if matches!(
+            column_type,
+            ColumnType::String { .. } | ColumnType::Enum { .. }
+        ) {
+            continue;
+        }
+        typed_columns += 1;
+        let first_value = first_row.get(idx).map_or("", |v| v.trim());
+        if !value_matches(&column_type, first_value) {
+            header_like_columns += 1;
+        }
+    }
+
+    if typed_columns > 0 {
+        let ratio = header_like_columns as f64 / typed_columns as f64;
+        return HeaderInference {
+            present: ratio >= 0.5,
+            confidence: match ratio >= 0.5 {
+                true => ratio,
+                false => 1.0 - ratio,
+            },
+        };
+    }
+
+    // Only text columns: a header is likely if the first row values are
+    // unique and none of them repeats further down the file
+    let first_values: HashSet<&String> = first_row.iter().collect();
+    let repeated = rows[1..]
+        .iter()
+        .any(|row| row.iter().any(|value| first_values.contains(value)));
+    HeaderInference {
+        present: first_values.len() == first_row.len() && !repeated,
+        confidence: 0.6,
+    }
+}
+
+fn infer_column_type(values: &[&str]) -> (ColumnType, f64) {
+    let non_empty: Vec<&str> = values.iter().copied().filter(|v| !v.is_empty()).collect();
+    if non_empty.is_empty() {
+        return (string_type(), 0.0);
+    }
+
+    let mut candidates = vec![
+        ColumnType::Boolean,
+        ColumnType::Integer {
+            min: None,
+            max: None,
+        },
+        ColumnType::Decimal {
+            min: None,
+            max: None,
+        },
+    ];
+    candidates.extend(
+        CANDIDATE_DATE_FORMATS
+            .iter()
+            .map(|format| ColumnType::Date {
+                format: format.to_string(),
+            }),
+    );
+
+    for candidate in candidates {
+        let matches = non_empty
+            .iter()
+            .filter(|value| value_matches(&candidate, value))
+            .count();
+        let confidence = matches as f64 / non_empty.len() as f64;
+        if confidence >= 0.95 {
+            return (candidate, confidence);
+        }
+    }
+
+    let distinct: HashSet<&str> = non_empty.iter().copied().collect();
+    if non_empty.len() >= 10 && distinct.len() <= 5 && distinct.len() * 2 <= non_empty.len() {
+        let mut values: Vec<String> = distinct.into_iter().map(String::from).collect();
+        values.sort();
+        let confidence = 1.0 - (values.len() as f64 / non_empty.len() as f64);
+        return (ColumnType::Enum { values }, confidence);
+    }
+
+    (string_type(), 1.0)
+}
+
+fn value_matches(column_type: &ColumnType, value: &str) -> bool {
+    match column_type {
+        ColumnType::Boolean => matches!(
+            value.to_lowercase().as_str(),
+            "true" | "false" | "yes" | "no"
+        ),
+        ColumnType::Integer { .. } => !has_leading_zero(value) && value.parse::<i64>().is_ok(),
+        ColumnType::Decimal { .. } => {
+            !has_leading_zero(value) && value.parse::<f64>().is_ok_and(|n| n.is_finite())
+        }
+        ColumnType::Date { format } => NaiveDate::parse_from_str(value, format).is_ok(),
+        ColumnType::String { .. } => true,
+        ColumnType::Enum { values } => values.iter().any(|v| v == value),
+    }
+}
+
+// Leading zeros usually mean codes, such as account numbers, not numbers
+fn has_leading_zero(value: &str) -> bool {
+    let digits = value.trim_start_matches('-');
+    digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.")
+}
+
+fn string_type() -> ColumnType {
+    ColumnType::String {
+        regex: None,
+        min_length: None,
+        max_length: None,
+    }
+}
+
+fn type_name(column_type: &ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::String { .. } => "String",
+        ColumnType::Integer { .. } => "Integer",
+        ColumnType::Decimal { .. } => "Decimal",
+        ColumnType::Date { .. } => "Date",
+        ColumnType::Boolean => "Boolean",
+        ColumnType::Enum { .. } => "Enum",
+    }
+}
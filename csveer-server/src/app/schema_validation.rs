@@ -0,0 +1,166 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use sqlx::types::JsonValue;
+
+use crate::{
+    app::{file_reader::FileRow, rejected_rows::RejectedRow},
+    config::server::AppError,
+    data::file_source::{ColumnSchema, ColumnType},
+};
+
+struct CompiledColumn<'a> {
+    schema: &'a ColumnSchema,
+    regex: Option<Regex>,
+}
+
+pub struct CompiledSchema<'a> {
+    columns: Vec<CompiledColumn<'a>>,
+}
+
+impl<'a> CompiledSchema<'a> {
+    pub fn new(schema: &'a [ColumnSchema]) -> anyhow::Result<Self, AppError> {
+        let mut columns = Vec::with_capacity(schema.len());
+        for column in schema {
+            let regex = match &column.column_type {
+                ColumnType::String {
+                    regex: Some(regex), ..
+                } => Some(Regex::new(regex)?),
+                _ => None,
+            };
+            columns.push(CompiledColumn {
+                schema: column,
+                regex,
+            });
+        }
+        Ok(Self { columns })
+    }
+
+    pub fn apply(&self, row: FileRow) -> Result<Vec<JsonValue>, Vec<RejectedRow>> {
+        if row.values.len() != self.columns.len() {
+            return Err(vec![RejectedRow {
+                row: row.line,
+                column: None,
+                reason: format!(
+                    "Expected {} columns, found {}",
+                    self.columns.len(),
+                    row.values.len()
+                ),
+            }]);
+        }
+
+        let mut values = Vec::with_capacity(row.values.len());
+        let mut errors = Vec::new();
+
+        for (column, value) in self.columns.iter().zip(row.values) {
+            match column.apply(value) {
+                Ok(value) => values.push(value),
+                Err(reason) => errors.push(RejectedRow {
+                    row: row.line,
+                    column: Some(column.schema.name.clone()),
+                    reason,
+                }),
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(values),
+            false => Err(errors),
+        }
+    }
+}
+
+impl CompiledColumn<'_> {
+    fn apply(&self, value: String) -> Result<JsonValue, String> {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            return match self.schema.nullable {
+                true => Ok(JsonValue::Null),
+                false => Err(String::from("Value is required")),
+            };
+        }
+
+        parse_value(&self.schema.column_type, self.regex.as_ref(), value)
+    }
+}
+
+pub fn parse_value(
+    column_type: &ColumnType,
+    regex: Option<&Regex>,
+    value: String,
+) -> Result<JsonValue, String> {
+    let trimmed = value.trim();
+    match column_type {
+        ColumnType::String {
+            min_length,
+            max_length,
+            ..
+        } => {
+            let length = value.chars().count() as i32;
+            if min_length.is_some_and(|min| length < min) {
+                return Err(format!(
+                    "Value '{}' is shorter than {} characters",
+                    value,
+                    min_length.unwrap()
+                ));
+            }
+            if max_length.is_some_and(|max| length > max) {
+                return Err(format!(
+                    "Value '{}' is longer than {} characters",
+                    value,
+                    max_length.unwrap()
+                ));
+            }
+            if let Some(regex) = regex {
+                if !regex.is_match(&value) {
+                    return Err(format!(
+                        "Value '{}' does not match pattern '{}'",
+                        value,
+                        regex.as_str()
+                    ));
+                }
+            }
+            Ok(JsonValue::String(value))
+        }
+        ColumnType::Integer { min, max } => {
+            let number: i64 = trimmed
+                .parse()
+                .map_err(|_| format!("Value '{}' is not a valid integer", value))?;
+            if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                return Err(format!("Value {} is out of the allowed range", number));
+            }
+            Ok(JsonValue::from(number))
+        }
+        ColumnType::Decimal { min, max } => {
+            let number: f64 = trimmed
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| format!("Value '{}' is not a valid decimal", value))?;
+            if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                return Err(format!("Value {} is out of the allowed range", number));
+            }
+            // The validated text is kept so no precision is lost on the way out
+            Ok(JsonValue::String(
+                trimmed.trim_start_matches('+').to_string(),
+            ))
+        }
+        ColumnType::Date { format } => {
+            let date = NaiveDate::parse_from_str(trimmed, format)
+                .map_err(|_| format!("Value '{}' is not a date in format '{}'", value, format))?;
+            Ok(JsonValue::String(date.format("%Y-%m-%d").to_string()))
+        }
+        ColumnType::Boolean => match trimmed.to_lowercase().as_str() {
+            "true" | "1" | "yes" | "y" => Ok(JsonValue::Bool(true)),
+            "false" | "0" | "no" | "n" => Ok(JsonValue::Bool(false)),
+            _ => Err(format!("Value '{}' is not a valid boolean", value)),
+        },
+        ColumnType::Enum { values } => match values.iter().any(|v| v == trimmed) {
+            true => Ok(JsonValue::String(trimmed.to_string())),
+            false => Err(format!(
+                "Value '{}' is not one of {}",
+                value,
+                values.join(", ")
+            )),
+        },
+    }
+}
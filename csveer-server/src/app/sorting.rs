@@ -0,0 +1,386 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Lines, Seek, SeekFrom, Write},
+};
+
+use anyhow::Context;
+use sqlx::types::JsonValue;
+
+use crate::{
+    config::server::AppError,
+    data::{
+        file_destination::{SortDirection, SortKey},
+        file_source::ColumnReference,
+    },
+};
+
+pub struct CompiledSort {
+    keys: Vec<(usize, SortDirection)>,
+}
+
+impl CompiledSort {
+    pub fn new(sort_by: &[SortKey], column_names: &[String]) -> anyhow::Result<Self, AppError> {
+        let keys = sort_by
+            .iter()
+            .map(|key| {
+                match &key.column {
+                    ColumnReference::Index(idx) => Some(*idx as usize),
+                    ColumnReference::Name(name) => column_names.iter().position(|c| c == name),
+                }
+                .filter(|column| *column < column_names.len())
+                .map(|column| (column, key.direction.unwrap_or_default()))
+                .ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Sort column {} does not exist. Found: {}",
+                        key.column,
+                        column_names.join(", ")
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // The sort is stable, so rows with equal keys keep the order of the file
+    pub fn apply(&self, rows: &mut [Vec<JsonValue>]) {
+        if self.keys.is_empty() {
+            return;
+        }
+        rows.sort_by(|left, right| self.compare_rows(left, right));
+    }
+
+    fn compare_rows(&self, left: &[JsonValue], right: &[JsonValue]) -> Ordering {
+        for (column, direction) in &self.keys {
+            let ordering = compare_nullable(left.get(*column), right.get(*column));
+            let ordering = match direction {
+                SortDirection::Ascending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+pub const SORT_RUN_ROWS: usize = 100_000;
+
+// Rows are sorted in memory in runs of at most `run_rows`. Once a run is
+// full it is spilled to a temporary file, and the sorted runs are merged
+// back when the rows are read, so only one run and one row per spilled run
+// are held in memory
+pub struct SortRuns {
+    run_rows: usize,
+    run: Vec<Vec<JsonValue>>,
+    spilled_runs: Vec<File>,
+}
+
+impl SortRuns {
+    pub fn new(run_rows: usize) -> Self {
+        Self {
+            run_rows: run_rows.max(1),
+            run: Vec::new(),
+            spilled_runs: Vec::new(),
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        sort: &CompiledSort,
+        row: Vec<JsonValue>,
+    ) -> anyhow::Result<(), AppError> {
+        self.run.push(row);
+        if self.run.len() >= self.run_rows {
+            self.spill(sort)?;
+        }
+        Ok(())
+    }
+
+    pub fn into_sorted(mut self, sort: &CompiledSort) -> anyhow::Result<SortedRows<'_>, AppError> {
+        if self.spilled_runs.is_empty() {
+            sort.apply(&mut self.run);
+            return Ok(SortedRows::InMemory(self.run.into_iter()));
+        }
+        if !self.run.is_empty() {
+            self.spill(sort)?;
+        }
+
+        let mut runs: Vec<Lines<BufReader<File>>> = self
+            .spilled_runs
+            .into_iter()
+            .map(|file| BufReader::new(file).lines())
+            .collect();
+        let mut heads = BinaryHeap::with_capacity(runs.len());
+        for (run, lines) in runs.iter_mut().enumerate() {
+            if let Some(row) = read_row(lines)? {
+                heads.push(MergeHead { row, run, sort });
+            }
+        }
+
+        Ok(SortedRows::Merged { runs, heads })
+    }
+
+    fn spill(&mut self, sort: &CompiledSort) -> anyhow::Result<(), AppError> {
+        sort.apply(&mut self.run);
+        let file = tempfile::tempfile().context("Creating sort run file")?;
+        let mut writer = BufWriter::new(file);
+        for row in self.run.drain(..) {
+            serde_json::to_writer(&mut writer, &row).context("Writing sort run")?;
+            writer.write_all(b"\n").context("Writing sort run")?;
+        }
+        let mut file = writer.into_inner().context("Finishing sort run")?;
+        file.seek(SeekFrom::Start(0))
+            .context("Rewinding sort run")?;
+        self.spilled_runs.push(file);
+        Ok(())
+    }
+}
+
+pub enum SortedRows<'a> {
+    InMemory(std::vec::IntoIter<Vec<JsonValue>>),
+    Merged {
+        runs: Vec<Lines<BufReader<File>>>,
+        heads: BinaryHeap<MergeHead<'a>>,
+    },
+}
+
+impl Iterator for SortedRows<'_> {
+    type Item = anyhow::Result<Vec<JsonValue>, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedRows::InMemory(rows) => rows.next().map(Ok),
+            SortedRows::Merged { runs, heads } => {
+                let head = heads.pop()?;
+                match read_row(&mut runs[head.run]) {
+                    Ok(Some(row)) => heads.push(MergeHead {
+                        row,
+                        run: head.run,
+                        sort: head.sort,
+                    }),
+                    Ok(None) => {}
+                    Err(err) => return Some(Err(err)),
+                }
+                Some(Ok(head.row))
+            }
+        }
+    }
+}
+
+pub struct MergeHead<'a> {
+    row: Vec<JsonValue>,
+    run: usize,
+    sort: &'a CompiledSort,
+}
+
+// The heap pops its greatest entry, so the order is reversed. Equal rows
+// come out of the earliest run first, which keeps the merge stable
+impl Ord for MergeHead<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort
+            .compare_rows(&self.row, &other.row)
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+impl PartialOrd for MergeHead<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MergeHead<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeHead<'_> {}
+
+fn read_row(
+    lines: &mut Lines<BufReader<File>>,
+) -> anyhow::Result<Option<Vec<JsonValue>>, AppError> {
+    match lines.next() {
+        Some(line) => {
+            let line = line.context("Reading sort run")?;
+            Ok(Some(
+                serde_json::from_str(&line).context("Reading row from sort run")?,
+            ))
+        }
+        None => Ok(None),
+    }
+}
+
+// Missing values sort after every other value
+fn compare_nullable(left: Option<&JsonValue>, right: Option<&JsonValue>) -> Ordering {
+    let present = |value: Option<&JsonValue>| {
+        value.filter(|value| match value {
+            JsonValue::Null => false,
+            JsonValue::String(text) => !text.is_empty(),
+            _ => true,
+        })
+    };
+    match (present(left), present(right)) {
+        (Some(left), Some(right)) => compare_values(left, right),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// Numbers, including untyped numeric text, are compared by value and
+// anything else by its text, which keeps ISO dates in chronological order.
+// Numbers rank before text, so a column mixing both still has a total order
+pub fn compare_values(left: &JsonValue, right: &JsonValue) -> Ordering {
+    match (as_f64(left), as_f64(right)) {
+        (Some(left), Some(right)) => left.total_cmp(&right),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => as_text(left).cmp(&as_text(right)),
+    }
+}
+
+fn as_f64(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(n) => n.as_f64(),
+        JsonValue::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::types::JsonValue;
+
+    use super::{CompiledSort, SortRuns};
+    use crate::data::{
+        file_destination::{SortDirection, SortKey},
+        file_source::ColumnReference,
+    };
+
+    fn sort(keys: &[(i32, SortDirection)]) -> CompiledSort {
+        let sort_by: Vec<SortKey> = keys
+            .iter()
+            .map(|(column, direction)| SortKey {
+                column: ColumnReference::Index(*column),
+                direction: Some(*direction),
+            })
+            .collect();
+        CompiledSort::new(&sort_by, &[String::from("value"), String::from("line")]).unwrap()
+    }
+
+    fn values(rows: &[Vec<JsonValue>]) -> Vec<JsonValue> {
+        rows.iter().map(|row| row[0].clone()).collect()
+    }
+
+    #[test]
+    fn should_sort_numbers_by_value() {
+        let mut rows = vec![
+            vec![json!("10")],
+            vec![json!(9)],
+            vec![json!("-2.5")],
+            vec![json!(100)],
+        ];
+        sort(&[(0, SortDirection::Ascending)]).apply(&mut rows);
+
+        assert_eq!(
+            values(&rows),
+            vec![json!("-2.5"), json!(9), json!("10"), json!(100)]
+        );
+    }
+
+    #[test]
+    fn should_sort_numbers_before_text_in_mixed_columns() {
+        let mut rows: Vec<Vec<JsonValue>> = ["1a", "10", "b", "NaN", "9", "1a", "-1"]
+            .iter()
+            .map(|value| vec![json!(value)])
+            .collect();
+        sort(&[(0, SortDirection::Ascending)]).apply(&mut rows);
+
+        assert_eq!(
+            values(&rows),
+            vec![
+                json!("-1"),
+                json!("9"),
+                json!("10"),
+                json!("NaN"),
+                json!("1a"),
+                json!("1a"),
+                json!("b")
+            ]
+        );
+    }
+
+    #[test]
+    fn should_sort_missing_values_last_in_both_directions() {
+        let rows = vec![
+            vec![json!(null)],
+            vec![json!(2)],
+            vec![json!("")],
+            vec![json!(1)],
+        ];
+
+        let mut ascending = rows.clone();
+        sort(&[(0, SortDirection::Ascending)]).apply(&mut ascending);
+        assert_eq!(values(&ascending)[..2], [json!(1), json!(2)]);
+
+        let mut descending = rows;
+        sort(&[(0, SortDirection::Descending)]).apply(&mut descending);
+        assert_eq!(values(&descending)[..2], [json!(2), json!(1)]);
+    }
+
+    #[test]
+    fn should_keep_file_order_of_equal_keys() {
+        let mut rows = vec![
+            vec![json!("b"), json!(1)],
+            vec![json!("a"), json!(2)],
+            vec![json!("b"), json!(3)],
+            vec![json!("a"), json!(4)],
+        ];
+        sort(&[(0, SortDirection::Ascending)]).apply(&mut rows);
+
+        let lines: Vec<JsonValue> = rows.iter().map(|row| row[1].clone()).collect();
+        assert_eq!(lines, vec![json!(2), json!(4), json!(1), json!(3)]);
+    }
+
+    #[test]
+    fn should_merge_spilled_runs_in_order() {
+        let sort = sort(&[(0, SortDirection::Descending)]);
+        let rows: Vec<Vec<JsonValue>> = [3, 7, 1, 7, 5, 2, 9]
+            .iter()
+            .enumerate()
+            .map(|(line, value)| vec![json!(value), json!(line)])
+            .collect();
+        let mut expected = rows.clone();
+        sort.apply(&mut expected);
+
+        let mut runs = SortRuns::new(2);
+        for row in rows {
+            runs.push(&sort, row).unwrap();
+        }
+        let sorted: Vec<Vec<JsonValue>> = runs
+            .into_sorted(&sort)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(sorted, expected);
+    }
+}
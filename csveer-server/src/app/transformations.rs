@@ -0,0 +1,700 @@
+use std::{collections::HashMap, fmt::Write};
+
+use chrono::NaiveDate;
+use regex::Regex;
+use sqlx::types::JsonValue;
+
+use crate::{
+    app::{
+        expressions::{BoundExpression, Expression},
+        reference_dataset::ReferenceDatasets,
+        rejected_rows::RejectedRow,
+        schema_validation::parse_value,
+    },
+    config::server::AppError,
+    data::{
+        file_destination::{MissingLookupPolicy, Transformation},
+        file_source::{ColumnReference, ColumnType},
+    },
+};
+
+enum Step<'a> {
+    Computed {
+        expression: BoundExpression,
+    },
+    Cast {
+        column: usize,
+        column_type: &'a ColumnType,
+        regex: Option<Regex>,
+    },
+    Trim {
+        column: usize,
+    },
+    UpperCase {
+        column: usize,
+    },
+    LowerCase {
+        column: usize,
+    },
+    ReformatDate {
+        column: usize,
+        from_format: &'a str,
+        to_format: &'a str,
+    },
+    Constant {
+        value: &'a str,
+    },
+    Concatenate {
+        columns: Vec<usize>,
+        separator: &'a str,
+    },
+    Split {
+        column: usize,
+        separator: &'a str,
+        parts: usize,
+    },
+    DefaultIfEmpty {
+        column: usize,
+        value: JsonValue,
+    },
+    Lookup {
+        column: usize,
+        dataset: &'a str,
+        rows: HashMap<&'a str, &'a [String]>,
+        fields: Vec<usize>,
+        on_missing: MissingLookupPolicy,
+    },
+}
+
+pub enum TransformationError {
+    Rejected(RejectedRow),
+    // Aborts the dispatch of the whole file
+    Failed(String),
+}
+
+impl From<RejectedRow> for TransformationError {
+    fn from(rejected_row: RejectedRow) -> Self {
+        TransformationError::Rejected(rejected_row)
+    }
+}
+
+pub struct CompiledTransformations<'a> {
+    input_width: usize,
+    steps: Vec<Step<'a>>,
+    pub column_names: Vec<String>,
+    pub column_types: Vec<Option<ColumnType>>,
+}
+
+impl<'a> CompiledTransformations<'a> {
+    // Resolves every column reference against the columns produced by the
+    // previous steps, so a step can use a column renamed or added before it
+    pub fn new(
+        transformations: &'a [Transformation],
+        column_names: Vec<String>,
+        column_types: Vec<Option<ColumnType>>,
+        reference_datasets: &'a ReferenceDatasets,
+    ) -> anyhow::Result<Self, AppError> {
+        let mut compiled = Self {
+            input_width: column_names.len(),
+            steps: Vec::with_capacity(transformations.len()),
+            column_names,
+            column_types,
+        };
+
+        for transformation in transformations {
+            match transformation {
+                Transformation::RenameColumn { column, name } => {
+                    let column = compiled.resolve(column)?;
+                    compiled.column_names[column] = name.clone();
+                }
+                Transformation::CastType { column, to } => {
+                    let column = compiled.resolve(column)?;
+                    let regex = match to {
+                        ColumnType::String {
+                            regex: Some(regex), ..
+                        } => Some(Regex::new(regex)?),
+                        _ => None,
+                    };
+                    compiled.column_types[column] = Some(to.clone());
+                    compiled.steps.push(Step::Cast {
+                        column,
+                        column_type: to,
+                        regex,
+                    });
+                }
+                Transformation::Trim { column } => {
+                    let column = compiled.resolve(column)?;
+                    compiled.steps.push(Step::Trim { column });
+                }
+                Transformation::UpperCase { column } => {
+                    let column = compiled.resolve(column)?;
+                    compiled.steps.push(Step::UpperCase { column });
+                }
+                Transformation::LowerCase { column } => {
+                    let column = compiled.resolve(column)?;
+                    compiled.steps.push(Step::LowerCase { column });
+                }
+                Transformation::ReformatDate {
+                    column,
+                    from_format,
+                    to_format,
+                } => {
+                    let column = compiled.resolve(column)?;
+                    compiled.column_types[column] = None;
+                    compiled.steps.push(Step::ReformatDate {
+                        column,
+                        from_format,
+                        to_format,
+                    });
+                }
+                Transformation::ConstantColumn { name, value } => {
+                    compiled.add_column(name);
+                    compiled.steps.push(Step::Constant { value });
+                }
+                Transformation::ConcatenateColumns {
+                    columns,
+                    separator,
+                    name,
+                } => {
+                    let columns = columns
+                        .iter()
+                        .map(|column| compiled.resolve(column))
+                        .collect::<Result<_, _>>()?;
+                    compiled.add_column(name);
+                    compiled
+                        .steps
+                        .push(Step::Concatenate { columns, separator });
+                }
+                Transformation::SplitColumn {
+                    column,
+                    separator,
+                    names,
+                } => {
+                    let column = compiled.resolve(column)?;
+                    for name in names {
+                        compiled.add_column(name);
+                    }
+                    compiled.steps.push(Step::Split {
+                        column,
+                        separator,
+                        parts: names.len(),
+                    });
+                }
+                Transformation::DefaultIfEmpty { column, value } => {
+                    let column = compiled.resolve(column)?;
+                    let value = match &compiled.column_types[column] {
+                        Some(column_type) => parse_value(column_type, None, value.clone())
+                            .map_err(|reason| {
+                                AppError::Validation(format!(
+                                    "Default value of column '{}' is invalid. {}",
+                                    compiled.column_names[column], reason
+                                ))
+                            })?,
+                        None => JsonValue::String(value.clone()),
+                    };
+                    compiled.steps.push(Step::DefaultIfEmpty { column, value });
+                }
+                Transformation::ComputedColumn { name, expression } => {
+                    let (column_type, expression) = Expression::parse(expression)
+                        .and_then(|parsed| {
+                            let column_type = parsed.computed_column_type(
+                                Some(&compiled.column_names),
+                                &compiled.column_types,
+                            )?;
+                            Ok((column_type, parsed.bind(&compiled.column_names)?))
+                        })
+                        .map_err(|err| {
+                            AppError::Validation(format!(
+                                "Computed column '{}' is invalid. {}",
+                                name, err
+                            ))
+                        })?;
+                    compiled.add_column(name);
+                    let column = compiled.column_types.len() - 1;
+                    compiled.column_types[column] = column_type;
+                    compiled.steps.push(Step::Computed { expression });
+                }
+                Transformation::Lookup {
+                    column,
+                    dataset,
+                    reference_column,
+                    fields,
+                    on_missing,
+                } => {
+                    let column = compiled.resolve(column)?;
+                    let reference_dataset = reference_datasets.get(dataset).ok_or_else(|| {
+                        AppError::Validation(format!(
+                            "Reference dataset {} was not loaded",
+                            dataset
+                        ))
+                    })?;
+                    let dataset_column = |name: &String| {
+                        reference_dataset
+                            .columns
+                            .iter()
+                            .position(|c| c == name)
+                            .ok_or_else(|| {
+                                AppError::Validation(format!(
+                                    "Reference dataset {} has no column '{}'. Found: {}",
+                                    dataset,
+                                    name,
+                                    reference_dataset.columns.join(", ")
+                                ))
+                            })
+                    };
+                    let key = dataset_column(reference_column)?;
+                    let fields = fields
+                        .iter()
+                        .map(dataset_column)
+                        .collect::<Result<Vec<usize>, _>>()?;
+
+                    // The first row of a duplicated key wins
+                    let mut rows = HashMap::with_capacity(reference_dataset.rows.len());
+                    for row in &reference_dataset.rows {
+                        if let Some(key) = row.get(key) {
+                            rows.entry(key.as_str()).or_insert(row.as_slice());
+                        }
+                    }
+
+                    for field in &fields {
+                        let name = reference_dataset.columns[*field].clone();
+                        compiled.add_column(&name);
+                    }
+                    compiled.steps.push(Step::Lookup {
+                        column,
+                        dataset,
+                        rows,
+                        fields,
+                        on_missing: on_missing.unwrap_or_default(),
+                    });
+                }
+            }
+        }
+
+        Ok(compiled)
+    }
+
+    pub fn apply(
+        &self,
+        line: usize,
+        mut row: Vec<JsonValue>,
+    ) -> Result<Vec<JsonValue>, TransformationError> {
+        if self.steps.is_empty() {
+            return Ok(row);
+        }
+        // Added columns are appended, so every row needs the same width
+        row.resize(self.input_width, JsonValue::Null);
+
+        for step in &self.steps {
+            match step {
+                Step::Computed { expression } => {
+                    let value = expression.evaluate(&row);
+                    row.push(value);
+                }
+                Step::Cast {
+                    column,
+                    column_type,
+                    regex,
+                } => {
+                    row[*column] = match as_text(&row[*column]) {
+                        Some(text) if !text.trim().is_empty() => {
+                            parse_value(column_type, regex.as_ref(), text)
+                                .map_err(|reason| self.reject(line, *column, reason))?
+                        }
+                        _ => JsonValue::Null,
+                    }
+                }
+                Step::Trim { column } => {
+                    if let JsonValue::String(value) = &row[*column] {
+                        row[*column] = JsonValue::String(value.trim().to_string());
+                    }
+                }
+                Step::UpperCase { column } => {
+                    if let JsonValue::String(value) = &row[*column] {
+                        row[*column] = JsonValue::String(value.to_uppercase());
+                    }
+                }
+                Step::LowerCase { column } => {
+                    if let JsonValue::String(value) = &row[*column] {
+                        row[*column] = JsonValue::String(value.to_lowercase());
+                    }
+                }
+                Step::ReformatDate {
+                    column,
+                    from_format,
+                    to_format,
+                } => {
+                    if let Some(text) = as_text(&row[*column]).filter(|t| !t.trim().is_empty()) {
+                        let date =
+                            NaiveDate::parse_from_str(text.trim(), from_format).map_err(|_| {
+                                self.reject(
+                                    line,
+                                    *column,
+                                    format!(
+                                        "Value '{}' is not a date in format '{}'",
+                                        text, from_format
+                                    ),
+                                )
+                            })?;
+                        // Formatting fails instead of panicking on fields a
+                        // date does not have
+                        let mut formatted = String::new();
+                        write!(formatted, "{}", date.format(to_format)).map_err(|_| {
+                            self.reject(
+                                line,
+                                *column,
+                                format!(
+                                    "Date '{}' cannot be written in format '{}'",
+                                    text, to_format
+                                ),
+                            )
+                        })?;
+                        row[*column] = JsonValue::String(formatted);
+                    }
+                }
+                Step::Constant { value } => row.push(JsonValue::String(value.to_string())),
+                Step::Concatenate { columns, separator } => {
+                    let value = columns
+                        .iter()
+                        .map(|column| as_text(&row[*column]).unwrap_or_default())
+                        .collect::<Vec<String>>()
+                        .join(separator);
+                    row.push(JsonValue::String(value));
+                }
+                Step::Split {
+                    column,
+                    separator,
+                    parts,
+                } => {
+                    let mut values: Vec<JsonValue> = match as_text(&row[*column]) {
+                        Some(text) => text
+                            .splitn(*parts, *separator)
+                            .map(|part| JsonValue::String(part.to_string()))
+                            .collect(),
+                        None => Vec::new(),
+                    };
+                    values.resize(*parts, JsonValue::Null);
+                    row.extend(values);
+                }
+                Step::DefaultIfEmpty { column, value } => {
+                    let is_empty = match &row[*column] {
+                        JsonValue::Null => true,
+                        JsonValue::String(text) => text.trim().is_empty(),
+                        _ => false,
+                    };
+                    if is_empty {
+                        row[*column] = value.clone();
+                    }
+                }
+                Step::Lookup {
+                    column,
+                    dataset,
+                    rows,
+                    fields,
+                    on_missing,
+                } => {
+                    let key = as_text(&row[*column]).unwrap_or_default();
+                    match rows.get(key.as_str()) {
+                        Some(found) => row.extend(fields.iter().map(|field| {
+                            found
+                                .get(*field)
+                                .map_or(JsonValue::Null, |value| JsonValue::String(value.clone()))
+                        })),
+                        None => {
+                            let reason = format!(
+                                "Value '{}' was not found in reference dataset {}",
+                                key, dataset
+                            );
+                            match on_missing {
+                                MissingLookupPolicy::Null => {
+                                    row.extend(fields.iter().map(|_| JsonValue::Null))
+                                }
+                                MissingLookupPolicy::RejectRow => {
+                                    return Err(self.reject(line, *column, reason).into())
+                                }
+                                MissingLookupPolicy::FailFile => {
+                                    return Err(TransformationError::Failed(format!(
+                                        "Row {}: {}",
+                                        line, reason
+                                    )))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(row)
+    }
+
+    fn resolve(&self, reference: &ColumnReference) -> anyhow::Result<usize, AppError> {
+        let column = match reference {
+            ColumnReference::Index(idx) => Some(*idx as usize),
+            ColumnReference::Name(name) => self.column_names.iter().position(|c| c == name),
+        };
+        column
+            .filter(|column| *column < self.column_names.len())
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Transformation column {} does not exist. Found: {}",
+                    reference,
+                    self.column_names.join(", ")
+                ))
+            })
+    }
+
+    fn add_column(&mut self, name: &str) {
+        self.column_names.push(name.to_string());
+        self.column_types.push(None);
+    }
+
+    fn reject(&self, line: usize, column: usize, reason: String) -> RejectedRow {
+        RejectedRow {
+            row: line,
+            column: Some(self.column_names[column].clone()),
+            reason,
+        }
+    }
+}
+
+fn as_text(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use sqlx::types::JsonValue;
+
+    use super::{CompiledTransformations, TransformationError};
+    use crate::{
+        app::reference_dataset::ReferenceDatasets,
+        data::{file_destination::Transformation, reference_dataset::ReferenceDataset},
+    };
+
+    fn transformations(definitions: JsonValue) -> Vec<Transformation> {
+        serde_json::from_value(definitions).unwrap()
+    }
+
+    fn column_names() -> Vec<String> {
+        vec![String::from("account"), String::from("name")]
+    }
+
+    fn reference_datasets() -> ReferenceDatasets {
+        let mut reference_datasets = ReferenceDatasets::new();
+        reference_datasets.insert(
+            String::from("holders"),
+            ReferenceDataset {
+                id: 1,
+                context: String::from("banking"),
+                identifier: String::from("holders"),
+                columns: vec![String::from("account"), String::from("holder")],
+                row_count: 2,
+                rows: vec![
+                    vec![String::from("A1"), String::from("Ada")],
+                    vec![String::from("A1"), String::from("Alan")],
+                ],
+                created_at: Utc::now(),
+                updated_at: None,
+            },
+        );
+        reference_datasets
+    }
+
+    fn lookup(on_missing: &str) -> Vec<Transformation> {
+        transformations(json!([{
+            "type": "Lookup",
+            "column": "account",
+            "dataset": "holders",
+            "reference_column": "account",
+            "fields": ["holder"],
+            "on_missing": on_missing,
+        }]))
+    }
+
+    #[test]
+    fn should_resolve_columns_added_or_renamed_by_previous_steps() {
+        let transformations = transformations(json!([
+            { "type": "RenameColumn", "column": "name", "name": "full_name" },
+            { "type": "Trim", "column": "full_name" },
+            { "type": "ConstantColumn", "name": "country", "value": "PT" },
+            { "type": "ConcatenateColumns", "columns": ["account", 2], "separator": "-", "name": "key" },
+            { "type": "SplitColumn", "column": "full_name", "separator": " ", "names": ["first", "last", "other"] },
+        ]));
+        let reference_datasets = ReferenceDatasets::new();
+        let compiled = CompiledTransformations::new(
+            &transformations,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+
+        assert_eq!(
+            compiled.column_names,
+            vec![
+                "account",
+                "full_name",
+                "country",
+                "key",
+                "first",
+                "last",
+                "other"
+            ]
+        );
+        let row = compiled
+            .apply(1, vec![json!("A1"), json!(" Ada Lovelace ")])
+            .ok()
+            .unwrap();
+        assert_eq!(
+            row,
+            vec![
+                json!("A1"),
+                json!("Ada Lovelace"),
+                json!("PT"),
+                json!("A1-PT"),
+                json!("Ada"),
+                json!("Lovelace"),
+                JsonValue::Null
+            ]
+        );
+    }
+
+    #[test]
+    fn should_fail_on_unknown_column() {
+        let transformations = transformations(json!([{ "type": "Trim", "column": "missing" }]));
+        let reference_datasets = ReferenceDatasets::new();
+
+        assert!(CompiledTransformations::new(
+            &transformations,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_reject_row_failing_cast() {
+        let transformations = transformations(json!([
+            { "type": "CastType", "column": "account", "to": { "type": "Integer" } },
+        ]));
+        let reference_datasets = ReferenceDatasets::new();
+        let compiled = CompiledTransformations::new(
+            &transformations,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+
+        let row = compiled.apply(1, vec![json!(" 12 "), json!("Ada")]).ok();
+        assert_eq!(row, Some(vec![json!(12), json!("Ada")]));
+        match compiled.apply(2, vec![json!("A1"), json!("Ada")]) {
+            Err(TransformationError::Rejected(rejected_row)) => {
+                assert_eq!(rejected_row.row, 2);
+                assert_eq!(rejected_row.column.as_deref(), Some("account"));
+            }
+            _ => panic!("Row should be rejected"),
+        }
+    }
+
+    #[test]
+    fn should_type_default_of_cast_column() {
+        let transformations = transformations(json!([
+            { "type": "CastType", "column": "account", "to": { "type": "Integer" } },
+            { "type": "DefaultIfEmpty", "column": "account", "value": "0" },
+        ]));
+        let reference_datasets = ReferenceDatasets::new();
+        let compiled = CompiledTransformations::new(
+            &transformations,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+
+        let row = compiled.apply(1, vec![json!(" "), json!("Ada")]).ok();
+        assert_eq!(row, Some(vec![json!(0), json!("Ada")]));
+    }
+
+    #[test]
+    fn should_reject_date_written_with_time_fields() {
+        let transformations = transformations(json!([{
+            "type": "ReformatDate",
+            "column": "account",
+            "from_format": "%Y-%m-%d",
+            "to_format": "%d/%m/%Y %H:%M",
+        }]));
+        let reference_datasets = ReferenceDatasets::new();
+        let compiled = CompiledTransformations::new(
+            &transformations,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            compiled.apply(1, vec![json!("2024-02-29"), json!("Ada")]),
+            Err(TransformationError::Rejected(_))
+        ));
+    }
+
+    #[test]
+    fn should_look_up_first_row_of_key() {
+        let transformations = lookup("Null");
+        let reference_datasets = reference_datasets();
+        let compiled = CompiledTransformations::new(
+            &transformations,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+
+        let found = compiled.apply(1, vec![json!("A1"), json!("x")]).ok();
+        assert_eq!(found, Some(vec![json!("A1"), json!("x"), json!("Ada")]));
+        let missing = compiled.apply(2, vec![json!("B2"), json!("x")]).ok();
+        assert_eq!(
+            missing,
+            Some(vec![json!("B2"), json!("x"), JsonValue::Null])
+        );
+    }
+
+    #[test]
+    fn should_reject_or_fail_on_missing_lookup_key() {
+        let reference_datasets = reference_datasets();
+        let reject_row = lookup("RejectRow");
+        let compiled = CompiledTransformations::new(
+            &reject_row,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+        assert!(matches!(
+            compiled.apply(1, vec![json!("B2"), json!("x")]),
+            Err(TransformationError::Rejected(_))
+        ));
+
+        let fail_file = lookup("FailFile");
+        let compiled = CompiledTransformations::new(
+            &fail_file,
+            column_names(),
+            vec![None, None],
+            &reference_datasets,
+        )
+        .unwrap();
+        assert!(matches!(
+            compiled.apply(1, vec![json!("B2"), json!("x")]),
+            Err(TransformationError::Failed(_))
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::server::AppError,
+    data::{
+        file_destination::{AggregateFunction, AggregateMetric},
+        file_source::ColumnReference,
+    },
+};
+
+pub fn validate(metrics: &[AggregateMetric]) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+    let mut names: HashSet<&str> = HashSet::new();
+
+    if metrics.is_empty() {
+        issues.push(String::from("No aggregate metric provided"));
+    }
+
+    for metric in metrics {
+        if metric.name.is_empty() {
+            issues.push(String::from("Metric name should not be blank"));
+        } else if !names.insert(&metric.name) {
+            issues.push(format!("Metric name '{}' is duplicated", metric.name));
+        }
+
+        match (&metric.column, metric.function) {
+            (Some(ColumnReference::Index(idx)), _) if idx.is_negative() => {
+                issues.push(format!("{} is not a valid column index", idx))
+            }
+            (Some(ColumnReference::Name(name)), _) if name.is_empty() => {
+                issues.push(String::from("Column name should not be blank"))
+            }
+            (None, AggregateFunction::Sum | AggregateFunction::Min | AggregateFunction::Max) => {
+                issues.push(format!(
+                    "Metric '{}' should reference the column it aggregates",
+                    metric.name
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("File destination aggregation is invalid"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
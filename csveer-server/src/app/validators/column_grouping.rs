@@ -1,34 +1,40 @@
 use std::collections::HashSet;
 
-use crate::config::server::AppError;
+use crate::{config::server::AppError, data::file_source::ColumnReference};
 
-pub fn validate(columns: &[i32]) -> anyhow::Result<(), AppError> {
+pub fn validate(columns: &[ColumnReference]) -> anyhow::Result<(), AppError> {
     if columns.is_empty() {
         return Err(AppError::DetailedValidation(
             String::from("Column grouping configuration is invalid"),
-            vec![String::from("No column index provided")],
+            vec![String::from("No column index or name provided")],
         ));
     }
 
-    let mut col_set: HashSet<&i32> = HashSet::new();
+    let mut col_set: HashSet<&ColumnReference> = HashSet::new();
 
     for (idx, col) in columns.iter().enumerate() {
-        if col.is_negative() {
-            return Err(AppError::DetailedValidation(
-                String::from("Column grouping configuration is invalid"),
-                vec![format!(
-                    "Column index at position {} cannot be negative",
-                    idx
-                )],
-            ));
+        match col {
+            ColumnReference::Index(col_idx) if col_idx.is_negative() => {
+                return Err(AppError::DetailedValidation(
+                    String::from("Column grouping configuration is invalid"),
+                    vec![format!(
+                        "Column index at position {} cannot be negative",
+                        idx
+                    )],
+                ));
+            }
+            ColumnReference::Name(name) if name.is_empty() => {
+                return Err(AppError::DetailedValidation(
+                    String::from("Column grouping configuration is invalid"),
+                    vec![format!("Column name at position {} cannot be blank", idx)],
+                ));
+            }
+            _ => {}
         }
         if col_set.contains(col) {
             return Err(AppError::DetailedValidation(
                 String::from("Column grouping configuration is invalid"),
-                vec![format!(
-                    "Column index {} appears twice in columns list",
-                    col
-                )],
+                vec![format!("Column {} appears twice in columns list", col)],
             ));
         }
         col_set.insert(col);
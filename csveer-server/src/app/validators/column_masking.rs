@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::{masking::MaskingConfig, server::AppError},
+    data::{
+        file_destination::{ColumnMasking, MaskingStrategy},
+        file_source::ColumnReference,
+    },
+};
+
+pub fn validate(
+    maskings: &[ColumnMasking],
+    masking_config: &MaskingConfig,
+) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+    let mut columns: HashSet<&ColumnReference> = HashSet::new();
+
+    for masking in maskings {
+        match &masking.column {
+            ColumnReference::Index(idx) if idx.is_negative() => {
+                issues.push(format!("{} is not a valid column index", idx))
+            }
+            ColumnReference::Name(name) if name.is_empty() => {
+                issues.push(String::from("Column name should not be blank"))
+            }
+            column => {
+                if !columns.insert(column) {
+                    issues.push(format!("Column {} is masked more than once", column));
+                }
+            }
+        }
+
+        match &masking.strategy {
+            MaskingStrategy::Redact => {}
+            MaskingStrategy::KeepLast { characters } => {
+                if characters.is_negative() {
+                    issues.push(format!(
+                        "Column {} cannot keep a negative number of characters",
+                        masking.column
+                    ));
+                }
+            }
+            MaskingStrategy::Hash { key } | MaskingStrategy::FormatPreserving { key } => {
+                if masking_config.key(key).is_none() {
+                    issues.push(format!(
+                        "Masking key '{}' of column {} is not configured",
+                        key, masking.column
+                    ));
+                }
+            }
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("File destination masking is invalid"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
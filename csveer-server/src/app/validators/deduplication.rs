@@ -0,0 +1,47 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::server::AppError,
+    data::{file_destination::DeduplicationConfiguration, file_source::ColumnReference},
+};
+
+pub fn validate(deduplication: &DeduplicationConfiguration) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+    let mut columns: HashSet<&ColumnReference> = HashSet::new();
+
+    if deduplication.columns.is_empty() {
+        issues.push(String::from("No key column index or name provided"));
+    }
+
+    for column in &deduplication.columns {
+        match column {
+            ColumnReference::Index(idx) if idx.is_negative() => {
+                issues.push(format!("{} is not a valid column index", idx))
+            }
+            ColumnReference::Name(name) if name.is_empty() => {
+                issues.push(String::from("Column name should not be blank"))
+            }
+            column => {
+                if !columns.insert(column) {
+                    issues.push(format!("Column {} appears twice in key columns", column));
+                }
+            }
+        }
+    }
+
+    if deduplication.retention_hours <= 0 {
+        issues.push(format!(
+            "Retention window should be a positive number of hours. Provided: {}",
+            deduplication.retention_hours
+        ));
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("File destination deduplication is invalid"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::{config::server::AppError, data::file_source::ErrorThreshold};
+
+pub fn validate(error_threshold: &ErrorThreshold) -> anyhow::Result<(), AppError> {
+    match error_threshold {
+        ErrorThreshold::Absolute { max_rejected_rows } => {
+            if max_rejected_rows.is_negative() {
+                return Err(AppError::DetailedValidation(
+                    String::from("Invalid error threshold configuration"),
+                    vec![format!(
+                        "Maximum rejected rows should not be negative. Provided: {}",
+                        max_rejected_rows
+                    )],
+                ));
+            }
+        }
+        ErrorThreshold::Percentage {
+            max_rejected_percentage,
+        } => {
+            if !(0.0..=100.0).contains(max_rejected_percentage) {
+                return Err(AppError::DetailedValidation(
+                    String::from("Invalid error threshold configuration"),
+                    vec![format!(
+                        "Maximum rejected percentage should be between 0 and 100. Provided: {}",
+                        max_rejected_percentage
+                    )],
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use crate::{config::server::AppError, data::file_destination::FileOutputFormat};
+
+pub fn validate(format: &FileOutputFormat) -> anyhow::Result<(), AppError> {
+    if let FileOutputFormat::Parquet {
+        row_group_size: Some(row_group_size),
+        ..
+    } = format
+    {
+        if row_group_size.is_negative() || row_group_size == &0 {
+            return Err(AppError::DetailedValidation(
+                String::from("Invalid parquet output format configuration"),
+                vec![format!(
+                    "Row group size should be a positive number. Provided: {}",
+                    row_group_size
+                )],
+            ));
+        }
+    }
+
+    Ok(())
+}
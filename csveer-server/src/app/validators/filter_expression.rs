@@ -0,0 +1,15 @@
+use crate::{
+    app::expressions::Expression, config::server::AppError, data::file_source::ColumnType,
+};
+
+pub fn validate(
+    filter: &str,
+    column_names: Option<&[String]>,
+    column_types: &[Option<ColumnType>],
+) -> anyhow::Result<(), AppError> {
+    Expression::parse(filter)
+        .and_then(|expression| expression.check_filter(column_names, column_types))
+        .map_err(|err| {
+            AppError::DetailedValidation(String::from("Invalid filter expression"), vec![err])
+        })
+}
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use crate::{config::server::AppError, data::file_source::FixedWidthColumn};
+
+pub fn validate(columns: &[FixedWidthColumn]) -> anyhow::Result<(), AppError> {
+    if columns.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("Fixed width format configuration is invalid"),
+            vec![String::from("No column definition provided")],
+        ));
+    }
+
+    let mut names: HashSet<&String> = HashSet::new();
+
+    for (idx, column) in columns.iter().enumerate() {
+        if column.name.is_empty() {
+            return Err(AppError::DetailedValidation(
+                String::from("Fixed width format configuration is invalid"),
+                vec![format!(
+                    "Column name at position {} should not be blank",
+                    idx
+                )],
+            ));
+        }
+        if names.contains(&column.name) {
+            return Err(AppError::DetailedValidation(
+                String::from("Fixed width format configuration is invalid"),
+                vec![format!(
+                    "Column name '{}' appears twice in columns list",
+                    column.name
+                )],
+            ));
+        }
+        names.insert(&column.name);
+
+        if column.start.is_negative() {
+            return Err(AppError::DetailedValidation(
+                String::from("Fixed width format configuration is invalid"),
+                vec![format!(
+                    "Column '{}' cannot start at a negative position",
+                    column.name
+                )],
+            ));
+        }
+        if column.length <= 0 {
+            return Err(AppError::DetailedValidation(
+                String::from("Fixed width format configuration is invalid"),
+                vec![format!(
+                    "Column '{}' should have a positive length. Provided: {}",
+                    column.name, column.length
+                )],
+            ));
+        }
+    }
+
+    let mut sorted_columns: Vec<&FixedWidthColumn> = columns.iter().collect();
+    sorted_columns.sort_by_key(|c| c.start);
+
+    let mut expected_start = 0;
+    for column in sorted_columns {
+        if column.start < expected_start {
+            return Err(AppError::DetailedValidation(
+                String::from("Fixed width format configuration is invalid"),
+                vec![format!(
+                    "Column '{}' starting at {} overlaps with the previous column, which ends at {}",
+                    column.name, column.start, expected_start
+                )],
+            ));
+        }
+        if column.start > expected_start {
+            return Err(AppError::DetailedValidation(
+                String::from("Fixed width format configuration is invalid"),
+                vec![format!(
+                    "Gap found between positions {} and {}, before column '{}'",
+                    expected_start, column.start, column.name
+                )],
+            ));
+        }
+        expected_start = match column.start.checked_add(column.length) {
+            Some(end) => end,
+            None => {
+                return Err(AppError::DetailedValidation(
+                    String::from("Fixed width format configuration is invalid"),
+                    vec![format!(
+                        "Column '{}' ends past the largest supported position",
+                        column.name
+                    )],
+                ))
+            }
+        };
+    }
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::path::Path;
+
+use crate::config::server::AppError;
+
+pub fn validate(directory: &String) -> anyhow::Result<(), AppError> {
+    if directory.is_empty() {
+        return Err(AppError::DetailedValidation(
+            "Invalid directory for local file system destination".to_string(),
+            vec!["Directory should not be empty".to_string()],
+        ));
+    }
+
+    if !Path::new(directory).is_absolute() {
+        return Err(AppError::DetailedValidation(
+            "Invalid directory for local file system destination".to_string(),
+            vec![format!(
+                "Directory '{}' should be an absolute path",
+                directory
+            )],
+        ));
+    }
+
+    Ok(())
+}
@@ -1,3 +1,16 @@
+pub mod aggregation;
 pub mod column_grouping;
+pub mod column_masking;
+pub mod deduplication;
+pub mod error_threshold;
+pub mod file_output_format;
+pub mod filter_expression;
 pub mod fixed_batching;
+pub mod fixed_width_format;
+pub mod local_file_system_destination;
+pub mod s3_destination;
+pub mod schema;
+pub mod sort_by;
 pub mod sqs_destination;
+pub mod time_window_batching;
+pub mod transformations;
@@ -0,0 +1,36 @@
+use crate::config::server::AppError;
+
+pub fn validate(bucket: &String, prefix: &Option<String>) -> anyhow::Result<(), AppError> {
+    if bucket.len() < 3 || bucket.len() > 63 {
+        return Err(AppError::DetailedValidation(
+            "Invalid bucket for S3 destination".to_string(),
+            vec![format!(
+                "Bucket name should have between 3 and 63 characters. Provided: '{}'",
+                bucket
+            )],
+        ));
+    }
+
+    for char in bucket.chars() {
+        if !char.is_ascii_lowercase() && !char.is_ascii_digit() && char != '-' && char != '.' {
+            return Err(AppError::DetailedValidation(
+                "Invalid bucket for S3 destination".to_string(),
+                vec![format!(
+                    "Bucket name should only contain lowercase characters, numbers, dots or hyphens. Found char '{}'",
+                    char
+                )],
+            ));
+        }
+    }
+
+    if let Some(prefix) = prefix {
+        if prefix.starts_with('/') {
+            return Err(AppError::DetailedValidation(
+                "Invalid prefix for S3 destination".to_string(),
+                vec![format!("Prefix '{}' should not start with '/'", prefix)],
+            ));
+        }
+    }
+
+    Ok(())
+}
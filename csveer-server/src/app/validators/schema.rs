@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+
+use chrono::format::{Item, StrftimeItems};
+use regex::Regex;
+
+use crate::{
+    config::server::AppError,
+    data::file_source::{ColumnSchema, ColumnType, FileFormat},
+};
+
+pub fn validate(
+    schema: &[ColumnSchema],
+    format: &Option<FileFormat>,
+) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+
+    if schema.is_empty() {
+        issues.push(String::from("No column provided"));
+    }
+
+    let mut names: HashSet<&String> = HashSet::new();
+
+    for (idx, column) in schema.iter().enumerate() {
+        if column.name.is_empty() {
+            issues.push(format!(
+                "Column name at position {} should not be blank",
+                idx
+            ));
+        } else if !names.insert(&column.name) {
+            issues.push(format!(
+                "Column name '{}' appears twice in schema",
+                column.name
+            ));
+        }
+
+        issues.extend(column_type_issues(&column.name, &column.column_type));
+    }
+
+    if let Some(FileFormat::FixedWidth { columns }) = format {
+        let fixed_width_names: Vec<&String> = columns.iter().map(|c| &c.name).collect();
+        let schema_names: Vec<&String> = schema.iter().map(|c| &c.name).collect();
+        if fixed_width_names != schema_names {
+            issues.push(String::from(
+                "Schema columns should match the fixed width column definitions, in the same order",
+            ));
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("File source schema is invalid"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn column_type_issues(name: &str, column_type: &ColumnType) -> Vec<String> {
+    let mut issues: Vec<String> = Vec::new();
+
+    match column_type {
+        ColumnType::String {
+            regex,
+            min_length,
+            max_length,
+        } => {
+            if let Some(regex) = regex {
+                if let Err(err) = Regex::new(regex) {
+                    issues.push(format!(
+                        "Column '{}' has an invalid regex '{}': {}",
+                        name, regex, err
+                    ));
+                }
+            }
+            if min_length.is_some_and(|min| min.is_negative()) {
+                issues.push(format!(
+                    "Column '{}' cannot have a negative minimum length",
+                    name
+                ));
+            }
+            if let (Some(min), Some(max)) = (min_length, max_length) {
+                if min > max {
+                    issues.push(format!(
+                        "Column '{}' has a minimum length of {} greater than its maximum length of {}",
+                        name, min, max
+                    ));
+                }
+            }
+        }
+        ColumnType::Integer { min, max } => {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    issues.push(format!(
+                        "Column '{}' has a minimum of {} greater than its maximum of {}",
+                        name, min, max
+                    ));
+                }
+            }
+        }
+        ColumnType::Decimal { min, max } => {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    issues.push(format!(
+                        "Column '{}' has a minimum of {} greater than its maximum of {}",
+                        name, min, max
+                    ));
+                }
+            }
+        }
+        ColumnType::Date { format } => {
+            if format.is_empty()
+                || StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+            {
+                issues.push(format!(
+                    "Column '{}' has an invalid date format '{}'",
+                    name, format
+                ));
+            }
+        }
+        ColumnType::Boolean => {}
+        ColumnType::Enum { values } => {
+            if values.is_empty() {
+                issues.push(format!(
+                    "Column '{}' should list at least one enum value",
+                    name
+                ));
+            }
+            let mut value_set: HashSet<&String> = HashSet::new();
+            for value in values {
+                if !value_set.insert(value) {
+                    issues.push(format!(
+                        "Enum value '{}' appears twice in column '{}'",
+                        value, name
+                    ));
+                }
+            }
+        }
+    }
+
+    issues
+}
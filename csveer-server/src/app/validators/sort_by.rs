@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::server::AppError,
+    data::{file_destination::SortKey, file_source::ColumnReference},
+};
+
+pub fn validate(sort_by: &[SortKey]) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+    let mut columns: HashSet<&ColumnReference> = HashSet::new();
+
+    if sort_by.is_empty() {
+        issues.push(String::from("No sort column index or name provided"));
+    }
+
+    for key in sort_by {
+        match &key.column {
+            ColumnReference::Index(idx) if idx.is_negative() => {
+                issues.push(format!("{} is not a valid column index", idx))
+            }
+            ColumnReference::Name(name) if name.is_empty() => {
+                issues.push(String::from("Column name should not be blank"))
+            }
+            column => {
+                if !columns.insert(column) {
+                    issues.push(format!("Column {} appears twice in sort columns", column));
+                }
+            }
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("File destination sorting is invalid"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use crate::config::server::AppError;
+
+pub fn validate(max_rows: &i32, max_wait_seconds: &i32) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+
+    if max_rows <= &0 {
+        issues.push(format!(
+            "Maximum rows should be a positive number. Provided: {}",
+            max_rows
+        ));
+    }
+
+    if max_wait_seconds <= &0 {
+        issues.push(format!(
+            "Maximum wait should be a positive number of seconds. Provided: {}",
+            max_wait_seconds
+        ));
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("Invalid time window batch configuration"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
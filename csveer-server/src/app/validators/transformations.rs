@@ -0,0 +1,189 @@
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+
+use crate::{
+    app::{expressions::Expression, validators::schema::column_type_issues},
+    config::server::AppError,
+    data::{file_destination::Transformation, file_source::ColumnReference},
+};
+
+pub fn validate(transformations: &[Transformation]) -> anyhow::Result<(), AppError> {
+    let mut issues: Vec<String> = Vec::new();
+
+    for (idx, transformation) in transformations.iter().enumerate() {
+        let step = idx + 1;
+        match transformation {
+            Transformation::RenameColumn { column, name } => {
+                reference_issues(step, column, &mut issues);
+                name_issues(step, name, &mut issues);
+            }
+            Transformation::CastType { column, to } => {
+                reference_issues(step, column, &mut issues);
+                for issue in column_type_issues(&column.to_string(), to) {
+                    issues.push(format!("Step {}: {}", step, issue));
+                }
+            }
+            Transformation::Trim { column }
+            | Transformation::UpperCase { column }
+            | Transformation::LowerCase { column }
+            | Transformation::DefaultIfEmpty { column, .. } => {
+                reference_issues(step, column, &mut issues);
+            }
+            Transformation::ReformatDate {
+                column,
+                from_format,
+                to_format,
+            } => {
+                reference_issues(step, column, &mut issues);
+                for format in [from_format, to_format] {
+                    if format.is_empty()
+                        || StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+                    {
+                        issues.push(format!(
+                            "Step {}: '{}' is not a valid date format",
+                            step, format
+                        ));
+                    }
+                }
+                // A date has no time or offset to write
+                if StrftimeItems::new(to_format).any(|item| is_time_item(&item)) {
+                    issues.push(format!(
+                        "Step {}: '{}' should only hold date fields",
+                        step, to_format
+                    ));
+                }
+            }
+            Transformation::ComputedColumn { name, expression } => {
+                name_issues(step, name, &mut issues);
+                if let Err(err) = Expression::parse(expression)
+                    .and_then(|parsed| parsed.computed_column_type(None, &[]))
+                {
+                    issues.push(format!("Step {}: {}", step, err));
+                }
+            }
+            Transformation::ConstantColumn { name, .. } => {
+                name_issues(step, name, &mut issues);
+            }
+            Transformation::ConcatenateColumns { columns, name, .. } => {
+                if columns.len() < 2 {
+                    issues.push(format!(
+                        "Step {}: at least two columns are needed to concatenate",
+                        step
+                    ));
+                }
+                for column in columns {
+                    reference_issues(step, column, &mut issues);
+                }
+                name_issues(step, name, &mut issues);
+            }
+            Transformation::SplitColumn {
+                column,
+                separator,
+                names,
+            } => {
+                reference_issues(step, column, &mut issues);
+                if separator.is_empty() {
+                    issues.push(format!("Step {}: separator should not be blank", step));
+                }
+                if names.len() < 2 {
+                    issues.push(format!(
+                        "Step {}: a split column should produce at least two columns",
+                        step
+                    ));
+                }
+                for name in names {
+                    name_issues(step, name, &mut issues);
+                }
+            }
+            Transformation::Lookup {
+                column,
+                dataset,
+                reference_column,
+                fields,
+                ..
+            } => {
+                reference_issues(step, column, &mut issues);
+                if dataset.is_empty() {
+                    issues.push(format!("Step {}: dataset should not be blank", step));
+                }
+                if reference_column.is_empty() {
+                    issues.push(format!(
+                        "Step {}: reference column should not be blank",
+                        step
+                    ));
+                }
+                if fields.is_empty() {
+                    issues.push(format!(
+                        "Step {}: at least one field should be looked up",
+                        step
+                    ));
+                }
+                for field in fields {
+                    name_issues(step, field, &mut issues);
+                }
+            }
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(AppError::DetailedValidation(
+            String::from("File destination transformations are invalid"),
+            issues,
+        ));
+    }
+
+    Ok(())
+}
+
+fn reference_issues(step: usize, column: &ColumnReference, issues: &mut Vec<String>) {
+    match column {
+        ColumnReference::Index(idx) if idx.is_negative() => issues.push(format!(
+            "Step {}: {} is not a valid column index",
+            step, idx
+        )),
+        ColumnReference::Name(name) if name.is_empty() => {
+            issues.push(format!("Step {}: column name should not be blank", step))
+        }
+        _ => {}
+    }
+}
+
+fn name_issues(step: usize, name: &str, issues: &mut Vec<String>) {
+    if name.is_empty() {
+        issues.push(format!(
+            "Step {}: new column name should not be blank",
+            step
+        ));
+    }
+}
+
+// Anything but date fields, literals and padding: times, offsets and
+// combined date and time fields
+fn is_time_item(item: &Item) -> bool {
+    match item {
+        Item::Numeric(numeric, _) => !matches!(
+            numeric,
+            Numeric::Year
+                | Numeric::YearDiv100
+                | Numeric::YearMod100
+                | Numeric::IsoYear
+                | Numeric::IsoYearDiv100
+                | Numeric::IsoYearMod100
+                | Numeric::Month
+                | Numeric::Day
+                | Numeric::WeekFromSun
+                | Numeric::WeekFromMon
+                | Numeric::IsoWeek
+                | Numeric::NumDaysFromSun
+                | Numeric::WeekdayFromMon
+                | Numeric::Ordinal
+        ),
+        Item::Fixed(fixed) => !matches!(
+            fixed,
+            Fixed::ShortMonthName
+                | Fixed::LongMonthName
+                | Fixed::ShortWeekdayName
+                | Fixed::LongWeekdayName
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use futures_util::TryStreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::{
+    multipart::{Form, Part},
+    Body, Client, RequestBuilder, Url,
+};
+use serde_json::Value;
+use tokio_util::io::ReaderStream;
+
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct ApiClient {
+    client: Client,
+    server: Url,
+}
+
+impl ApiClient {
+    pub fn new(server: &str) -> anyhow::Result<Self> {
+        let server =
+            Url::parse(server).with_context(|| format!("Parsing server URL {}", server))?;
+        if server.cannot_be_a_base() {
+            bail!("Server URL {} cannot be a base", server);
+        }
+        Ok(Self {
+            client: Client::new(),
+            server,
+        })
+    }
+
+    // Segments are percent-encoded, so names holding `/`, `?` or `#` stay
+    // within their own segment
+    fn url(&self, segments: &[&str], query: &[(&str, &str)]) -> anyhow::Result<Url> {
+        let mut url = self.server.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Server URL {} cannot be a base", self.server))?
+            .pop_if_empty()
+            .extend(segments);
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+
+    pub async fn get(&self, segments: &[&str], query: &[(&str, &str)]) -> anyhow::Result<Value> {
+        send(self.client.get(self.url(segments, query)?)).await
+    }
+
+    pub async fn get_text(
+        &self,
+        segments: &[&str],
+        query: &[(&str, &str)],
+    ) -> anyhow::Result<String> {
+        let url = self.url(segments, query)?;
+        let res = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .with_context(|| format!("Calling {}", url))?;
+        let status = res.status();
+        let text = res.text().await?;
+        if !status.is_success() {
+            bail!(error_message(status, &text));
+        }
+        Ok(text)
+    }
+
+    pub async fn post(&self, segments: &[&str], body: &Value) -> anyhow::Result<Value> {
+        send(self.client.post(self.url(segments, &[])?).json(body)).await
+    }
+
+    pub async fn post_text(
+        &self,
+        segments: &[&str],
+        query: &[(&str, &str)],
+        content_type: &str,
+        body: String,
+    ) -> anyhow::Result<Value> {
+        send(
+            self.client
+                .post(self.url(segments, query)?)
+                .header("Content-Type", content_type)
+                .body(body),
+        )
+        .await
+    }
+
+    // The file is streamed from disk in chunks, the progress bar moving as
+    // each chunk is handed over to the connection
+    pub async fn upload(&self, segments: &[&str], file_path: &Path) -> anyhow::Result<Value> {
+        let file = tokio::fs::File::open(file_path)
+            .await
+            .with_context(|| format!("Opening {}", file_path.display()))?;
+        let length = file
+            .metadata()
+            .await
+            .with_context(|| format!("Reading {}", file_path.display()))?
+            .len();
+        let file_name = file_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("{} has no file name", file_path.display()))?
+            .to_string();
+
+        let progress_bar = ProgressBar::new(length);
+        progress_bar.set_style(
+            ProgressStyle::with_template(
+                "{msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+            )?
+            .progress_chars("=> "),
+        );
+        progress_bar.set_message(file_name.clone());
+
+        let chunk_progress = progress_bar.clone();
+        let body = Body::wrap_stream(
+            ReaderStream::with_capacity(file, UPLOAD_CHUNK_SIZE)
+                .inspect_ok(move |chunk| chunk_progress.inc(chunk.len() as u64)),
+        );
+        let form = Form::new().part(
+            "file",
+            Part::stream_with_length(body, length).file_name(file_name),
+        );
+
+        let result = send(self.client.post(self.url(segments, &[])?).multipart(form)).await;
+        progress_bar.finish();
+        result
+    }
+}
+
+async fn send(request: RequestBuilder) -> anyhow::Result<Value> {
+    let res = request.send().await.context("Calling the csveer server")?;
+    let status = res.status();
+    let text = res.text().await?;
+    if !status.is_success() {
+        bail!(error_message(status, &text));
+    }
+    if text.is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str(&text).context("Parsing the response of the csveer server")
+}
+
+// Error responses carry a message and, for validation errors, the details
+fn error_message(status: reqwest::StatusCode, text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(body) if body["message"].is_string() => {
+            let mut message = format!("{}: {}", status, body["message"].as_str().unwrap());
+            if let Some(details) = body["details"].as_array() {
+                for detail in details {
+                    message.push_str(&format!("\n  - {}", detail.as_str().unwrap_or_default()));
+                }
+            }
+            message
+        }
+        _ => format!("{}: {}", status, text),
+    }
+}
@@ -0,0 +1,351 @@
+mod client;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+
+use crate::client::ApiClient;
+
+// Command-line client for the csveer HTTP API
+#[derive(Parser)]
+#[command(name = "csveerctl", version, about = "Manage a csveer server")]
+struct Cli {
+    /// Base URL of the csveer server
+    #[arg(
+        long,
+        global = true,
+        env = "CSVEER_SERVER",
+        default_value = "http://localhost:7000"
+    )]
+    server: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create and list contexts
+    #[command(subcommand)]
+    Context(ContextCommand),
+    /// Create, list and get file sources
+    #[command(subcommand)]
+    Source(SourceCommand),
+    /// Create, list and get file destinations
+    #[command(subcommand)]
+    Destination(DestinationCommand),
+    /// Upload files to a file source
+    Upload {
+        context: String,
+        source: String,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Follow and replay file ingestions
+    #[command(subcommand)]
+    Ingestion(IngestionCommand),
+    /// Apply a YAML or JSON configuration document
+    Apply {
+        file: PathBuf,
+        /// Delete sources and destinations missing from the document
+        #[arg(long)]
+        prune: bool,
+        /// Only show the changes the document would make
+        #[arg(long)]
+        plan: bool,
+    },
+    /// Print the configuration of the server as a document
+    Export {
+        #[arg(long, value_enum, default_value_t = DocumentFormat::Yaml)]
+        format: DocumentFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContextCommand {
+    Create { name: String },
+    List,
+}
+
+#[derive(Subcommand)]
+enum SourceCommand {
+    /// Create a file source from a YAML or JSON definition
+    Create {
+        file: PathBuf,
+    },
+    List {
+        #[arg(long)]
+        context: Option<String>,
+    },
+    Get {
+        context: String,
+        identifier: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DestinationCommand {
+    /// Create a file destination from a YAML or JSON definition
+    Create {
+        context: String,
+        source: String,
+        file: PathBuf,
+    },
+    List {
+        context: String,
+        source: String,
+    },
+    Get {
+        context: String,
+        source: String,
+        identifier: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum IngestionCommand {
+    Get {
+        id: i32,
+    },
+    /// Poll an ingestion until it is completed or failed
+    Watch {
+        id: i32,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+    Replay {
+        id: i32,
+        /// Only replay to these destinations, every destination otherwise
+        #[arg(long = "destination")]
+        destinations: Vec<String>,
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DocumentFormat {
+    Yaml,
+    Json,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let result = match ApiClient::new(&cli.server) {
+        Ok(client) => run(&client, cli.command).await,
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(client: &ApiClient, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Context(ContextCommand::Create { name }) => {
+            print_json(&client.post(&["context"], &json!({ "name": name })).await?)
+        }
+        Command::Context(ContextCommand::List) => print_json(&client.get(&["context"], &[]).await?),
+        Command::Source(SourceCommand::Create { file }) => {
+            print_json(&client.post(&["source"], &read_definition(&file)?).await?)
+        }
+        Command::Source(SourceCommand::List { context }) => {
+            let query = match &context {
+                Some(context) => vec![("context", context.as_str())],
+                None => Vec::new(),
+            };
+            print_json(&client.get(&["source"], &query).await?)
+        }
+        Command::Source(SourceCommand::Get {
+            context,
+            identifier,
+        }) => print_json(&client.get(&[&context, &identifier], &[]).await?),
+        Command::Destination(DestinationCommand::Create {
+            context,
+            source,
+            file,
+        }) => print_json(
+            &client
+                .post(
+                    &[&context, &source, "destination"],
+                    &read_definition(&file)?,
+                )
+                .await?,
+        ),
+        Command::Destination(DestinationCommand::List { context, source }) => {
+            print_json(&client.get(&[&context, &source, "destination"], &[]).await?)
+        }
+        Command::Destination(DestinationCommand::Get {
+            context,
+            source,
+            identifier,
+        }) => print_json(
+            &client
+                .get(&[&context, &source, "destination", &identifier], &[])
+                .await?,
+        ),
+        Command::Upload {
+            context,
+            source,
+            files,
+        } => {
+            for file in files {
+                let result = client.upload(&[&context, &source, "upload"], &file).await?;
+                print_json(&result)?;
+            }
+            Ok(())
+        }
+        Command::Ingestion(IngestionCommand::Get { id }) => {
+            print_json(&client.get(&["ingestions", &id.to_string()], &[]).await?)
+        }
+        Command::Ingestion(IngestionCommand::Watch { id, interval }) => {
+            watch_ingestion(client, id, Duration::from_secs(interval.max(1))).await
+        }
+        Command::Ingestion(IngestionCommand::Replay {
+            id,
+            destinations,
+            dry_run,
+        }) => {
+            let replay = json!({
+                "file_destinations": match destinations.is_empty() {
+                    true => Value::Null,
+                    false => json!(destinations),
+                },
+                "dry_run": dry_run,
+            });
+            print_json(
+                &client
+                    .post(&["ingestions", &id.to_string(), "replay"], &replay)
+                    .await?,
+            )
+        }
+        Command::Apply { file, prune, plan } => {
+            let document = std::fs::read_to_string(&file)
+                .with_context(|| format!("Reading {}", file.display()))?;
+            let result = client
+                .post_text(
+                    &["import"],
+                    &[("prune", &prune.to_string()), ("plan", &plan.to_string())],
+                    "application/yaml",
+                    document,
+                )
+                .await?;
+            print_import_result(&result);
+            Ok(())
+        }
+        Command::Export { format } => {
+            let format = match format {
+                DocumentFormat::Yaml => "yaml",
+                DocumentFormat::Json => "json",
+            };
+            let document = client.get_text(&["export"], &[("format", format)]).await?;
+            println!("{}", document.trim_end());
+            Ok(())
+        }
+    }
+}
+
+// Definitions may be written in YAML, which also covers the JSON fixtures
+fn read_definition(file: &Path) -> anyhow::Result<Value> {
+    let contents =
+        std::fs::read_to_string(file).with_context(|| format!("Reading {}", file.display()))?;
+    serde_yaml::from_str(&contents).with_context(|| format!("Parsing {}", file.display()))
+}
+
+fn print_json(value: &Value) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+// Exits with an error when the ingestion does not complete, so it can gate
+// a script or a pipeline step
+async fn watch_ingestion(client: &ApiClient, id: i32, interval: Duration) -> anyhow::Result<()> {
+    let mut last_status = String::new();
+    loop {
+        let file_ingestion = client.get(&["ingestions", &id.to_string()], &[]).await?;
+        let status = file_ingestion["status"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        if status != last_status {
+            let dispatches = file_ingestion["dispatches"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            println!(
+                "{} {} ({} of {} destinations dispatched)",
+                file_ingestion["file_name"].as_str().unwrap_or_default(),
+                status,
+                dispatches.len(),
+                file_ingestion["file_destination_count"]
+                    .as_i64()
+                    .map_or(String::from("?"), |count| count.to_string())
+            );
+            last_status = status.clone();
+        }
+
+        match status.as_str() {
+            "Completed" => return Ok(()),
+            "PartiallyFailed" | "Failed" => {
+                for dispatch in file_ingestion["dispatches"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                {
+                    println!(
+                        "  {}: {}",
+                        dispatch["file_destination_identifier"]
+                            .as_str()
+                            .unwrap_or_default(),
+                        dispatch["status"].as_str().unwrap_or_default()
+                    );
+                }
+                match file_ingestion["message"].as_str() {
+                    Some(message) => bail!("Ingestion {} ended as {}: {}", id, status, message),
+                    None => bail!("Ingestion {} ended as {}", id, status),
+                }
+            }
+            _ => tokio::time::sleep(interval).await,
+        }
+    }
+}
+
+fn print_import_result(result: &Value) {
+    let changes = result["changes"].as_array().cloned().unwrap_or_default();
+    if changes.is_empty() {
+        println!("No changes");
+        return;
+    }
+
+    for change in &changes {
+        println!(
+            "{} {} {}",
+            change["action"].as_str().unwrap_or_default(),
+            change["resource"].as_str().unwrap_or_default(),
+            change["name"].as_str().unwrap_or_default()
+        );
+        for field in change["changes"].as_array().into_iter().flatten() {
+            println!(
+                "    {}: {} -> {}",
+                field["path"].as_str().unwrap_or_default(),
+                field["from"],
+                field["to"]
+            );
+        }
+    }
+
+    match result["applied"].as_bool().unwrap_or(false) {
+        true => println!("Applied {} changes", changes.len()),
+        false => println!("Planned {} changes, nothing was applied", changes.len()),
+    }
+}
@@ -1,6 +1,15 @@
+use std::{
+    fs::File,
+    io::{Seek, Write},
+};
+
 use anyhow::Context;
 use aws_sdk_s3::{operation::put_object::PutObjectOutput, primitives::ByteStream, Client};
 
+pub const PENDING_CSV_FILES_BUCKET: &str = "pending-csv-files";
+// Kept apart from the pending files so the ingestion listener never sees them
+pub const REJECTED_ROWS_BUCKET: &str = "rejected-rows";
+
 pub async fn store_file_for_ingestion(
     client: &Client,
     bucket_name: &str,
@@ -17,3 +26,113 @@ pub async fn store_file_for_ingestion(
         .with_context(|| format!("Sending S3 object to bucket {}", bucket_name))?;
     Ok(res)
 }
+
+pub async fn store_dispatched_file(
+    client: &Client,
+    bucket_name: &str,
+    file: ByteStream,
+    file_name: &str,
+) -> anyhow::Result<PutObjectOutput> {
+    let res = client
+        .put_object()
+        .set_bucket(Some(bucket_name.to_string()))
+        .set_body(Some(file))
+        .set_key(Some(file_name.to_string()))
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "Sending dispatched file {} to bucket {}",
+                file_name, bucket_name
+            )
+        })?;
+    Ok(res)
+}
+
+pub async fn retrieve_file_for_dispatch(
+    client: &Client,
+    bucket_name: &str,
+    file_name: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let res = client
+        .get_object()
+        .set_bucket(Some(bucket_name.to_string()))
+        .set_key(Some(file_name.to_string()))
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "Retrieving S3 object {} from bucket {}",
+                file_name, bucket_name
+            )
+        })?;
+    let contents = res.body.collect().await.with_context(|| {
+        format!(
+            "Reading S3 object {} from bucket {}",
+            file_name, bucket_name
+        )
+    })?;
+    Ok(contents.into_bytes().to_vec())
+}
+
+// Streams the object into a temporary file, so a file larger than memory
+// can still be read row by row
+pub async fn retrieve_file_to_disk(
+    client: &Client,
+    bucket_name: &str,
+    file_name: &str,
+) -> anyhow::Result<File> {
+    let mut res = client
+        .get_object()
+        .set_bucket(Some(bucket_name.to_string()))
+        .set_key(Some(file_name.to_string()))
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "Retrieving S3 object {} from bucket {}",
+                file_name, bucket_name
+            )
+        })?;
+    let mut file = tempfile::tempfile().context("Creating file for S3 object")?;
+    while let Some(bytes) = res.body.try_next().await.with_context(|| {
+        format!(
+            "Reading S3 object {} from bucket {}",
+            file_name, bucket_name
+        )
+    })? {
+        file.write_all(&bytes)
+            .with_context(|| format!("Writing S3 object {} to disk", file_name))?;
+    }
+    file.rewind()
+        .with_context(|| format!("Rewinding S3 object {} on disk", file_name))?;
+    Ok(file)
+}
+
+pub async fn retrieve_file_head(
+    client: &Client,
+    bucket_name: &str,
+    file_name: &str,
+    max_bytes: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let res = client
+        .get_object()
+        .set_bucket(Some(bucket_name.to_string()))
+        .set_key(Some(file_name.to_string()))
+        .set_range(Some(format!("bytes=0-{}", max_bytes - 1)))
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "Retrieving first bytes of S3 object {} from bucket {}",
+                file_name, bucket_name
+            )
+        })?;
+    let contents = res.body.collect().await.with_context(|| {
+        format!(
+            "Reading S3 object {} from bucket {}",
+            file_name, bucket_name
+        )
+    })?;
+    Ok(contents.into_bytes().to_vec())
+}
@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+// Keys are configured as a comma separated list of `name:secret` pairs, so
+// destinations only ever reference a key by its name
+#[derive(Debug, Deserialize)]
+pub struct MaskingConfig {
+    #[serde(default)]
+    pub masking_keys: Vec<String>,
+}
+
+impl MaskingConfig {
+    pub fn key(&self, name: &str) -> Option<&[u8]> {
+        self.masking_keys
+            .iter()
+            .filter_map(|entry| entry.split_once(':'))
+            .find(|(key_name, _)| *key_name == name)
+            .map(|(_, secret)| secret.as_bytes())
+    }
+}
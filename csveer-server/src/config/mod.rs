@@ -1,3 +1,4 @@
 pub mod aws;
 pub mod listeners;
+pub mod masking;
 pub mod server;
@@ -36,11 +36,23 @@ impl FromRef<AppState> for SQSClient {
 pub enum AppError {
     Validation(String),
     DetailedValidation(String, Vec<String>),
+    NotFound(String),
     Error(String),
     // DetailedError(String, Vec<String>),
     // AnyhowError(anyhow::Error),
 }
 
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Validation(m) | AppError::NotFound(m) | AppError::Error(m) => {
+                write!(f, "{}", m)
+            }
+            AppError::DetailedValidation(m, d) => write!(f, "{}: {}", m, d.join("; ")),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct ErrorResponse {
     message: String,
@@ -64,12 +76,14 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let status_code = match self {
             Self::Validation(_) | Self::DetailedValidation(_, _) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
         let error_response = match self {
             AppError::Validation(m) => ErrorResponse::new(m),
             AppError::DetailedValidation(m, d) => ErrorResponse::new_detailed(m, d),
+            AppError::NotFound(m) => ErrorResponse::new(m),
             AppError::Error(m) => ErrorResponse::new(m),
             // AppError::DetailedError(m, d) => ErrorResponse::new_detailed(m, d),
             // AppError::AnyhowError(e) => ErrorResponse::new(e.to_string()),
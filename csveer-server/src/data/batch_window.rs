@@ -0,0 +1,224 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{types::JsonValue, PgConnection};
+
+use super::file_source::ColumnType;
+
+pub struct BatchWindowCreation {
+    pub file_destination_id: i32,
+    pub group_id: String,
+    pub group_key: Option<Vec<JsonValue>>,
+    pub column_names: Vec<String>,
+    pub column_types: Vec<Option<ColumnType>>,
+    pub headers: Option<Vec<String>>,
+    pub max_rows: i32,
+    pub max_wait_seconds: i32,
+}
+
+#[derive(Serialize)]
+pub struct BatchWindow {
+    pub id: i32,
+    pub file_destination_id: i32,
+    pub group_id: String,
+    pub group_key: Option<Vec<JsonValue>>,
+    pub column_names: Vec<String>,
+    pub column_types: Vec<Option<ColumnType>>,
+    pub headers: Option<Vec<String>>,
+    pub max_rows: i32,
+    pub opened_at: DateTime<Utc>,
+    pub flush_at: DateTime<Utc>,
+}
+
+struct BatchWindowEntity {
+    id: i32,
+    file_destination_id: i32,
+    group_id: String,
+    group_key: JsonValue,
+    column_names: JsonValue,
+    column_types: JsonValue,
+    headers: JsonValue,
+    max_rows: i32,
+    opened_at: DateTime<Utc>,
+    flush_at: DateTime<Utc>,
+}
+
+impl Into<BatchWindow> for BatchWindowEntity {
+    fn into(self) -> BatchWindow {
+        return BatchWindow {
+            id: self.id,
+            file_destination_id: self.file_destination_id,
+            group_id: self.group_id,
+            group_key: serde_json::from_value(self.group_key).unwrap(),
+            column_names: serde_json::from_value(self.column_names).unwrap(),
+            column_types: serde_json::from_value(self.column_types).unwrap(),
+            headers: serde_json::from_value(self.headers).unwrap(),
+            max_rows: self.max_rows,
+            opened_at: self.opened_at,
+            flush_at: self.flush_at,
+        };
+    }
+}
+
+pub struct BatchWindowRow {
+    pub id: i64,
+    pub row: Vec<JsonValue>,
+}
+
+// Returns the open window of the group, opening one if there is none. An open
+// window keeps the flush deadline it was opened with
+pub async fn upsert_batch_window(
+    batch_window_creation: BatchWindowCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<BatchWindow> {
+    let batch_window = sqlx::query_as!(
+        BatchWindowEntity,
+        r#"
+            INSERT INTO batch_window(file_destination_id, group_id, group_key, column_names, column_types, headers, max_rows, opened_at, flush_at)
+            VALUES($1, $2, $3, $4, $5, $6, $7, NOW(), NOW() + make_interval(secs => $8))
+            ON CONFLICT (file_destination_id, group_id) DO UPDATE SET max_rows = EXCLUDED.max_rows
+            RETURNING *
+        "#,
+        batch_window_creation.file_destination_id,
+        batch_window_creation.group_id,
+        serde_json::to_value(batch_window_creation.group_key)?,
+        serde_json::to_value(batch_window_creation.column_names)?,
+        serde_json::to_value(batch_window_creation.column_types)?,
+        serde_json::to_value(batch_window_creation.headers)?,
+        batch_window_creation.max_rows,
+        batch_window_creation.max_wait_seconds as f64,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Upserting batch window")?
+    .into();
+
+    Ok(batch_window)
+}
+
+pub async fn insert_batch_window_rows(
+    batch_window_id: &i32,
+    rows: &[Vec<JsonValue>],
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    let rows: Vec<JsonValue> = rows
+        .iter()
+        .map(|row| JsonValue::Array(row.clone()))
+        .collect();
+    sqlx::query!(
+        r#"
+            INSERT INTO batch_window_row(batch_window_id, "row")
+            SELECT $1, UNNEST($2::JSONB[])
+        "#,
+        batch_window_id,
+        &rows,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Inserting rows of batch window {}", batch_window_id))?;
+
+    Ok(())
+}
+
+// Windows past their deadline or holding at least a full batch, unless their
+// destination or source is paused or disabled
+pub async fn list_flushable_batch_window_ids(
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<i32>> {
+    let batch_window_ids = sqlx::query_scalar!(
+        r#"
+            SELECT bw.id FROM batch_window bw
+            JOIN file_destination fd ON fd.id = bw.file_destination_id
+            JOIN file_source fs ON fs.id = fd.file_source_id
+            WHERE fd.enabled AND fs.enabled AND NOT fd.paused AND NOT fs.paused
+            AND (
+                bw.flush_at <= NOW()
+                OR (SELECT COUNT(*) FROM batch_window_row bwr WHERE bwr.batch_window_id = bw.id) >= bw.max_rows
+            )
+            ORDER BY bw.flush_at
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Listing flushable batch windows")?;
+
+    Ok(batch_window_ids)
+}
+
+// Windows being appended to or flushed by another transaction are skipped
+pub async fn lock_batch_window(
+    batch_window_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<BatchWindow>> {
+    let batch_window = sqlx::query_as!(
+        BatchWindowEntity,
+        r#"
+            SELECT * FROM batch_window bw WHERE bw.id = $1 FOR UPDATE SKIP LOCKED
+        "#,
+        batch_window_id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Locking batch window {}", batch_window_id))?
+    .map(|entity| entity.into());
+
+    Ok(batch_window)
+}
+
+pub async fn list_batch_window_rows(
+    batch_window_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<BatchWindowRow>> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT bwr.id, bwr."row" FROM batch_window_row bwr
+            WHERE bwr.batch_window_id = $1 ORDER BY bwr.id
+        "#,
+        batch_window_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| format!("Listing rows of batch window {}", batch_window_id))?
+    .into_iter()
+    .map(|record| BatchWindowRow {
+        id: record.id,
+        row: serde_json::from_value(record.row).unwrap(),
+    })
+    .collect();
+
+    Ok(rows)
+}
+
+pub async fn delete_batch_window_rows(
+    batch_window_row_ids: &[i64],
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM batch_window_row WHERE id = ANY($1)
+        "#,
+        batch_window_row_ids,
+    )
+    .execute(executor)
+    .await
+    .context("Deleting flushed batch window rows")?;
+
+    Ok(())
+}
+
+pub async fn delete_batch_window(
+    batch_window_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM batch_window WHERE id = $1
+        "#,
+        batch_window_id,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Deleting batch window {}", batch_window_id))?;
+
+    Ok(())
+}
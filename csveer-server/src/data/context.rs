@@ -8,6 +8,7 @@ pub struct SourceContext {
     pub id: i32,
     pub name: String,
     pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -58,3 +59,52 @@ pub async fn get_context_by_name(
 
     context
 }
+
+pub async fn list_contexts(executor: &mut PgConnection) -> anyhow::Result<Vec<SourceContext>> {
+    let contexts = sqlx::query_as!(
+        SourceContext,
+        r#"
+            SELECT * FROM context ORDER BY name
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Listing contexts")?;
+
+    Ok(contexts)
+}
+
+pub async fn update_context_name(
+    name: &str,
+    new_name: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<SourceContext> {
+    let updated_context = sqlx::query_as!(
+        SourceContext,
+        r#"
+            UPDATE context SET name = $2, updated_at = NOW()
+            WHERE name = $1 RETURNING *
+        "#,
+        name,
+        new_name,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Renaming context {} to {}", name, new_name))?;
+
+    Ok(updated_context)
+}
+
+pub async fn delete_context(name: &str, executor: &mut PgConnection) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM context WHERE name = $1
+        "#,
+        name,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Deleting context {}", name))?;
+
+    Ok(())
+}
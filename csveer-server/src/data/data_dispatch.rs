@@ -1,6 +1,6 @@
 use anyhow::Context;
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::PgConnection;
 
 #[derive(Serialize)]
@@ -8,6 +8,7 @@ pub enum DataDispatchStatus {
     PendingExecution,
     Failed,
     Finished,
+    PartialSuccess,
 }
 
 impl TryFrom<String> for DataDispatchStatus {
@@ -18,6 +19,7 @@ impl TryFrom<String> for DataDispatchStatus {
             "PendingExecution" => Ok(Self::PendingExecution),
             "Failed" => Ok(Self::Failed),
             "Finished" => Ok(Self::Finished),
+            "PartialSuccess" => Ok(Self::PartialSuccess),
             _ => Err(format!("{} is not a valid DataDispatchSource", value)),
         }
     }
@@ -29,12 +31,23 @@ impl ToString for DataDispatchStatus {
             DataDispatchStatus::PendingExecution => "PendingExecution".to_string(),
             DataDispatchStatus::Failed => "Failed".to_string(),
             DataDispatchStatus::Finished => "Finished".to_string(),
+            DataDispatchStatus::PartialSuccess => "PartialSuccess".to_string(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct MaskedColumn {
+    pub column: String,
+    pub strategy: String,
+}
+
 pub struct DataDispatchCreation {
     pub file_destination_id: i32,
+    pub file_source_version: Option<i32>,
+    pub file_destination_version: i32,
+    pub file_ingestion_id: Option<i32>,
+    pub replayed_data_dispatch_id: Option<i32>,
     pub message: String,
 }
 
@@ -44,6 +57,15 @@ pub struct DataDispatch {
     pub file_destination_id: i32,
     pub status: DataDispatchStatus,
     pub message: String,
+    pub total_rows: Option<i32>,
+    pub rejected_rows: Option<i32>,
+    pub filtered_rows: Option<i32>,
+    pub duplicate_rows: Option<i32>,
+    pub masked_columns: Option<Vec<MaskedColumn>>,
+    pub file_source_version: Option<i32>,
+    pub file_destination_version: Option<i32>,
+    pub file_ingestion_id: Option<i32>,
+    pub replayed_data_dispatch_id: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -53,8 +75,17 @@ struct DataDispatchEntity {
     file_destination_id: i32,
     status: String,
     message: String,
+    total_rows: Option<i32>,
+    rejected_rows: Option<i32>,
+    filtered_rows: Option<i32>,
+    duplicate_rows: Option<i32>,
+    masked_columns: sqlx::types::JsonValue,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    file_source_version: Option<i32>,
+    file_destination_version: Option<i32>,
+    file_ingestion_id: Option<i32>,
+    replayed_data_dispatch_id: Option<i32>,
 }
 
 impl Into<DataDispatch> for DataDispatchEntity {
@@ -64,6 +95,15 @@ impl Into<DataDispatch> for DataDispatchEntity {
             file_destination_id: self.file_destination_id,
             status: self.status.try_into().unwrap(),
             message: self.message,
+            total_rows: self.total_rows,
+            rejected_rows: self.rejected_rows,
+            filtered_rows: self.filtered_rows,
+            duplicate_rows: self.duplicate_rows,
+            masked_columns: serde_json::from_value(self.masked_columns).unwrap(),
+            file_source_version: self.file_source_version,
+            file_destination_version: self.file_destination_version,
+            file_ingestion_id: self.file_ingestion_id,
+            replayed_data_dispatch_id: self.replayed_data_dispatch_id,
             created_at: self.created_at,
             updated_at: self.updated_at,
         };
@@ -77,12 +117,16 @@ pub async fn insert_data_dispatch(
     let created_data_dispatch = sqlx::query_as!(
         DataDispatchEntity,
         r#"
-            INSERT INTO data_dispatch(file_destination_id, status, message, created_at)
-            VALUES($1, $2, $3, NOW()) RETURNING *
+            INSERT INTO data_dispatch(file_destination_id, status, message, file_source_version, file_destination_version, file_ingestion_id, replayed_data_dispatch_id, created_at)
+            VALUES($1, $2, $3, $4, $5, $6, $7, NOW()) RETURNING *
         "#,
         data_dispatch_creation.file_destination_id.clone(),
         DataDispatchStatus::PendingExecution.to_string(),
         data_dispatch_creation.message.clone(),
+        data_dispatch_creation.file_source_version,
+        data_dispatch_creation.file_destination_version,
+        data_dispatch_creation.file_ingestion_id,
+        data_dispatch_creation.replayed_data_dispatch_id,
     )
     .fetch_one(executor)
     .await
@@ -91,3 +135,269 @@ pub async fn insert_data_dispatch(
 
     Ok(created_data_dispatch)
 }
+
+pub async fn update_data_dispatch_status(
+    data_dispatch_id: &i32,
+    status: DataDispatchStatus,
+    executor: &mut PgConnection,
+) -> anyhow::Result<DataDispatch> {
+    let updated_data_dispatch = sqlx::query_as!(
+        DataDispatchEntity,
+        r#"
+            UPDATE data_dispatch SET status = $2, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        data_dispatch_id.clone(),
+        status.to_string(),
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating status of data dispatch {}", data_dispatch_id))?
+    .into();
+
+    Ok(updated_data_dispatch)
+}
+
+pub async fn update_data_dispatch_row_counts(
+    data_dispatch_id: &i32,
+    total_rows: i32,
+    rejected_rows: i32,
+    filtered_rows: i32,
+    duplicate_rows: i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<DataDispatch> {
+    let updated_data_dispatch = sqlx::query_as!(
+        DataDispatchEntity,
+        r#"
+            UPDATE data_dispatch SET total_rows = $2, rejected_rows = $3, filtered_rows = $4, duplicate_rows = $5, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        data_dispatch_id.clone(),
+        total_rows,
+        rejected_rows,
+        filtered_rows,
+        duplicate_rows,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating row counts of data dispatch {}", data_dispatch_id))?
+    .into();
+
+    Ok(updated_data_dispatch)
+}
+
+pub async fn update_data_dispatch_masked_columns(
+    data_dispatch_id: &i32,
+    masked_columns: &[MaskedColumn],
+    executor: &mut PgConnection,
+) -> anyhow::Result<DataDispatch> {
+    let updated_data_dispatch = sqlx::query_as!(
+        DataDispatchEntity,
+        r#"
+            UPDATE data_dispatch SET masked_columns = $2, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        data_dispatch_id.clone(),
+        serde_json::to_value(masked_columns)?,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Updating masked columns of data dispatch {}",
+            data_dispatch_id
+        )
+    })?
+    .into();
+
+    Ok(updated_data_dispatch)
+}
+
+pub async fn list_by_file_ingestion_id(
+    file_ingestion_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<DataDispatch>> {
+    let data_dispatches = sqlx::query_as!(
+        DataDispatchEntity,
+        r#"
+            SELECT * FROM data_dispatch dd WHERE dd.file_ingestion_id = $1 ORDER BY dd.id
+        "#,
+        file_ingestion_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Listing data dispatches of file ingestion {}",
+            file_ingestion_id
+        )
+    })?
+    .into_iter()
+    .map(|entity| entity.into())
+    .collect();
+
+    Ok(data_dispatches)
+}
+
+pub struct DataDispatchFilter {
+    pub context: Option<String>,
+    pub file_source_identifier: Option<String>,
+    pub file_destination_identifier: Option<String>,
+    pub status: Option<DataDispatchStatus>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+}
+
+// A data dispatch along with the identifiers it was dispatched for
+#[derive(Serialize)]
+pub struct DataDispatchRecord {
+    pub context: String,
+    pub file_source_identifier: String,
+    pub file_destination_identifier: String,
+    #[serde(flatten)]
+    pub data_dispatch: DataDispatch,
+}
+
+struct DataDispatchRecordEntity {
+    id: i32,
+    file_destination_id: i32,
+    status: String,
+    message: String,
+    total_rows: Option<i32>,
+    rejected_rows: Option<i32>,
+    filtered_rows: Option<i32>,
+    duplicate_rows: Option<i32>,
+    masked_columns: sqlx::types::JsonValue,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+    file_source_version: Option<i32>,
+    file_destination_version: Option<i32>,
+    file_ingestion_id: Option<i32>,
+    replayed_data_dispatch_id: Option<i32>,
+    context: String,
+    file_source_identifier: String,
+    file_destination_identifier: String,
+}
+
+impl Into<DataDispatchRecord> for DataDispatchRecordEntity {
+    fn into(self) -> DataDispatchRecord {
+        return DataDispatchRecord {
+            context: self.context,
+            file_source_identifier: self.file_source_identifier,
+            file_destination_identifier: self.file_destination_identifier,
+            data_dispatch: DataDispatchEntity {
+                id: self.id,
+                file_destination_id: self.file_destination_id,
+                status: self.status,
+                message: self.message,
+                total_rows: self.total_rows,
+                rejected_rows: self.rejected_rows,
+                filtered_rows: self.filtered_rows,
+                duplicate_rows: self.duplicate_rows,
+                masked_columns: self.masked_columns,
+                created_at: self.created_at,
+                updated_at: self.updated_at,
+                file_source_version: self.file_source_version,
+                file_destination_version: self.file_destination_version,
+                file_ingestion_id: self.file_ingestion_id,
+                replayed_data_dispatch_id: self.replayed_data_dispatch_id,
+            }
+            .into(),
+        };
+    }
+}
+
+pub async fn list_data_dispatches(
+    filter: &DataDispatchFilter,
+    limit: i64,
+    offset: i64,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<DataDispatchRecord>> {
+    let data_dispatches = sqlx::query_as!(
+        DataDispatchRecordEntity,
+        r#"
+            SELECT dd.*, fs.context, fs.identifier AS file_source_identifier, fd.identifier AS file_destination_identifier
+            FROM data_dispatch dd
+            JOIN file_destination fd ON fd.id = dd.file_destination_id
+            JOIN file_source fs ON fs.id = fd.file_source_id
+            WHERE ($1::VARCHAR IS NULL OR fs.context = $1)
+            AND ($2::VARCHAR IS NULL OR fs.identifier = $2)
+            AND ($3::VARCHAR IS NULL OR fd.identifier = $3)
+            AND ($4::VARCHAR IS NULL OR dd.status = $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR dd.created_at >= $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR dd.created_at < $6)
+            ORDER BY dd.created_at DESC, dd.id DESC
+            LIMIT $7 OFFSET $8
+        "#,
+        filter.context,
+        filter.file_source_identifier,
+        filter.file_destination_identifier,
+        filter.status.as_ref().map(|status| status.to_string()),
+        filter.created_from,
+        filter.created_to,
+        limit,
+        offset,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Listing data dispatches")?
+    .into_iter()
+    .map(|entity| entity.into())
+    .collect();
+
+    Ok(data_dispatches)
+}
+
+pub async fn count_data_dispatches(
+    filter: &DataDispatchFilter,
+    executor: &mut PgConnection,
+) -> anyhow::Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) AS "count!"
+            FROM data_dispatch dd
+            JOIN file_destination fd ON fd.id = dd.file_destination_id
+            JOIN file_source fs ON fs.id = fd.file_source_id
+            WHERE ($1::VARCHAR IS NULL OR fs.context = $1)
+            AND ($2::VARCHAR IS NULL OR fs.identifier = $2)
+            AND ($3::VARCHAR IS NULL OR fd.identifier = $3)
+            AND ($4::VARCHAR IS NULL OR dd.status = $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR dd.created_at >= $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR dd.created_at < $6)
+        "#,
+        filter.context,
+        filter.file_source_identifier,
+        filter.file_destination_identifier,
+        filter.status.as_ref().map(|status| status.to_string()),
+        filter.created_from,
+        filter.created_to,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Counting data dispatches")?;
+
+    Ok(count)
+}
+
+pub async fn find_record_by_id(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<DataDispatchRecord>> {
+    let data_dispatch = sqlx::query_as!(
+        DataDispatchRecordEntity,
+        r#"
+            SELECT dd.*, fs.context, fs.identifier AS file_source_identifier, fd.identifier AS file_destination_identifier
+            FROM data_dispatch dd
+            JOIN file_destination fd ON fd.id = dd.file_destination_id
+            JOIN file_source fs ON fs.id = fd.file_source_id
+            WHERE dd.id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Searching data dispatch {}", id))?
+    .map(|entity| entity.into());
+
+    Ok(data_dispatch)
+}
@@ -33,13 +33,14 @@ impl TryFrom<String> for DataDispatchExecutionStatus {
     }
 }
 
-struct DataDispatchExecutionCreation {
+pub struct DataDispatchExecutionCreation {
     pub data_dispatch_id: i32,
     pub status: DataDispatchExecutionStatus,
     pub message: String,
 }
 
-struct DataDispatchExecution {
+#[derive(Serialize)]
+pub struct DataDispatchExecution {
     pub id: i32,
     pub data_dispatch_id: i32,
     pub status: DataDispatchExecutionStatus,
@@ -88,3 +89,29 @@ pub async fn insert_data_dispatch_execution(
 
     Ok(created_execution)
 }
+
+pub async fn list_by_data_dispatch_id(
+    data_dispatch_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<DataDispatchExecution>> {
+    let executions = sqlx::query_as!(
+        DataDispatchExecutionEntity,
+        r#"
+            SELECT * FROM data_dispatch_execution dde WHERE dde.data_dispatch_id = $1 ORDER BY dde.created_at, dde.id
+        "#,
+        data_dispatch_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Listing executions of data dispatch {}",
+            data_dispatch_id
+        )
+    })?
+    .into_iter()
+    .map(|entity| entity.into())
+    .collect();
+
+    Ok(executions)
+}
@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use sqlx::PgConnection;
+
+pub async fn list_recent_key_hashes(
+    file_destination_id: &i32,
+    retention_hours: i32,
+    key_hashes: &[String],
+    executor: &mut PgConnection,
+) -> anyhow::Result<HashSet<String>> {
+    let dispatched_key_hashes = sqlx::query_scalar!(
+        r#"
+            SELECT drk.key_hash FROM dispatched_row_key drk
+            WHERE drk.file_destination_id = $1 AND drk.dispatched_at > NOW() - make_interval(hours => $2)
+            AND drk.key_hash = ANY($3::VARCHAR[])
+        "#,
+        file_destination_id,
+        retention_hours,
+        key_hashes,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Listing dispatched row keys of file destination {}",
+            file_destination_id
+        )
+    })?
+    .into_iter()
+    .collect();
+
+    Ok(dispatched_key_hashes)
+}
+
+pub async fn upsert_key_hashes(
+    file_destination_id: &i32,
+    key_hashes: &[String],
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO dispatched_row_key(file_destination_id, key_hash, dispatched_at)
+            SELECT $1, UNNEST($2::VARCHAR[]), NOW()
+            ON CONFLICT (file_destination_id, key_hash) DO UPDATE SET dispatched_at = NOW()
+        "#,
+        file_destination_id,
+        key_hashes,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Storing dispatched row keys of file destination {}",
+            file_destination_id
+        )
+    })?;
+
+    Ok(())
+}
+
+pub async fn delete_expired_key_hashes(
+    file_destination_id: &i32,
+    retention_hours: i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM dispatched_row_key
+            WHERE file_destination_id = $1 AND dispatched_at <= NOW() - make_interval(hours => $2)
+        "#,
+        file_destination_id,
+        retention_hours,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Deleting expired row keys of file destination {}",
+            file_destination_id
+        )
+    })?;
+
+    Ok(())
+}
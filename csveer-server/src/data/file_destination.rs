@@ -3,22 +3,197 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{types::JsonValue, PgConnection};
 
+use super::file_source::{ColumnReference, ColumnType};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ParquetCompression {
+    Uncompressed,
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FileOutputFormat {
+    Json,
+    Parquet {
+        row_group_size: Option<i32>,
+        compression: Option<ParquetCompression>,
+    },
+    ArrowIpc,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum DestinationConfiguration {
-    SQS { queue_url: String },
+    SQS {
+        queue_url: String,
+    },
+    S3 {
+        bucket: String,
+        prefix: Option<String>,
+        format: FileOutputFormat,
+    },
+    LocalFileSystem {
+        directory: String,
+        format: FileOutputFormat,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum GroupingConfiguration {
-    GroupedByColumns { columns: Vec<i32> },
+    GroupedByColumns { columns: Vec<ColumnReference> },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum BatchingConfiguration {
-    Fixed { batch_size: i32 },
+    Fixed {
+        batch_size: i32,
+    },
+    // Rows of many uploads are buffered per group until either limit is hit
+    TimeWindow {
+        max_rows: i32,
+        max_wait_seconds: i32,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Transformation {
+    RenameColumn {
+        column: ColumnReference,
+        name: String,
+    },
+    CastType {
+        column: ColumnReference,
+        to: ColumnType,
+    },
+    Trim {
+        column: ColumnReference,
+    },
+    UpperCase {
+        column: ColumnReference,
+    },
+    LowerCase {
+        column: ColumnReference,
+    },
+    ReformatDate {
+        column: ColumnReference,
+        from_format: String,
+        to_format: String,
+    },
+    ConstantColumn {
+        name: String,
+        value: String,
+    },
+    ConcatenateColumns {
+        columns: Vec<ColumnReference>,
+        separator: String,
+        name: String,
+    },
+    SplitColumn {
+        column: ColumnReference,
+        separator: String,
+        names: Vec<String>,
+    },
+    DefaultIfEmpty {
+        column: ColumnReference,
+        value: String,
+    },
+    ComputedColumn {
+        name: String,
+        expression: String,
+    },
+    Lookup {
+        column: ColumnReference,
+        dataset: String,
+        reference_column: String,
+        fields: Vec<String>,
+        on_missing: Option<MissingLookupPolicy>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum MissingLookupPolicy {
+    #[default]
+    Null,
+    RejectRow,
+    FailFile,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MaskingStrategy {
+    Redact,
+    KeepLast { characters: i32 },
+    Hash { key: String },
+    FormatPreserving { key: String },
+}
+
+impl MaskingStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaskingStrategy::Redact => "Redact",
+            MaskingStrategy::KeepLast { .. } => "KeepLast",
+            MaskingStrategy::Hash { .. } => "Hash",
+            MaskingStrategy::FormatPreserving { .. } => "FormatPreserving",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnMasking {
+    pub column: ColumnReference,
+    pub strategy: MaskingStrategy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DispatchMode {
+    Rows,
+    Aggregate { metrics: Vec<AggregateMetric> },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+// `Count` without a column counts the rows of the group, every other metric
+// ignores null and empty values of its column
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregateMetric {
+    pub name: String,
+    pub function: AggregateFunction,
+    pub column: Option<ColumnReference>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SortKey {
+    pub column: ColumnReference,
+    pub direction: Option<SortDirection>,
+}
+
+// Rows are identified by the values of `columns`. A row is skipped when a row
+// with the same key was dispatched to the destination in the last
+// `retention_hours`, or appeared earlier in the same upload
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeduplicationConfiguration {
+    pub columns: Vec<ColumnReference>,
+    pub retention_hours: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +205,15 @@ pub struct FileDestination {
     pub include_headers: bool,
     pub grouping: Option<GroupingConfiguration>,
     pub batching: Option<BatchingConfiguration>,
+    pub transformations: Option<Vec<Transformation>>,
+    pub filter: Option<String>,
+    pub masking: Option<Vec<ColumnMasking>>,
+    pub deduplication: Option<DeduplicationConfiguration>,
+    pub mode: Option<DispatchMode>,
+    pub sort_by: Option<Vec<SortKey>>,
+    pub version: i32,
+    pub enabled: bool,
+    pub paused: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -41,6 +225,12 @@ pub struct FileDestinationCreation {
     pub include_headers: bool,
     pub grouping: Option<GroupingConfiguration>,
     pub batching: Option<BatchingConfiguration>,
+    pub transformations: Option<Vec<Transformation>>,
+    pub filter: Option<String>,
+    pub masking: Option<Vec<ColumnMasking>>,
+    pub deduplication: Option<DeduplicationConfiguration>,
+    pub mode: Option<DispatchMode>,
+    pub sort_by: Option<Vec<SortKey>>,
 }
 
 struct FileDestinationEntity {
@@ -53,6 +243,15 @@ struct FileDestinationEntity {
     pub batching: JsonValue,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub transformations: JsonValue,
+    pub filter: Option<String>,
+    pub masking: JsonValue,
+    pub deduplication: JsonValue,
+    pub mode: JsonValue,
+    pub sort_by: JsonValue,
+    pub version: i32,
+    pub enabled: bool,
+    pub paused: bool,
 }
 
 impl Into<FileDestination> for FileDestinationEntity {
@@ -65,6 +264,15 @@ impl Into<FileDestination> for FileDestinationEntity {
             include_headers: self.include_headers,
             grouping: serde_json::from_value(self.grouping).unwrap(),
             batching: serde_json::from_value(self.batching).unwrap(),
+            transformations: serde_json::from_value(self.transformations).unwrap(),
+            filter: self.filter,
+            masking: serde_json::from_value(self.masking).unwrap(),
+            deduplication: serde_json::from_value(self.deduplication).unwrap(),
+            mode: serde_json::from_value(self.mode).unwrap(),
+            sort_by: serde_json::from_value(self.sort_by).unwrap(),
+            version: self.version,
+            enabled: self.enabled,
+            paused: self.paused,
             created_at: self.created_at,
             updated_at: self.updated_at,
         };
@@ -79,15 +287,21 @@ pub async fn insert_file_destination(
     let created_file_destination = sqlx::query_as!(
         FileDestinationEntity,
         r#"        
-            INSERT INTO file_destination(file_source_id, identifier, destination, include_headers, "grouping", batching, created_at)
-            VALUES($1, $2, $3, $4, $5, $6, NOW()) RETURNING *
+            INSERT INTO file_destination(file_source_id, identifier, destination, include_headers, "grouping", batching, transformations, "filter", masking, deduplication, mode, sort_by, created_at)
+            VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW()) RETURNING *
         "#,
         file_source_id.clone(),
         file_destination_creation.identifier,
         serde_json::to_value(file_destination_creation.destination)?,
         file_destination_creation.include_headers,
         serde_json::to_value(file_destination_creation.grouping)?,
-        serde_json::to_value(file_destination_creation.batching)?
+        serde_json::to_value(file_destination_creation.batching)?,
+        serde_json::to_value(file_destination_creation.transformations)?,
+        file_destination_creation.filter,
+        serde_json::to_value(file_destination_creation.masking)?,
+        serde_json::to_value(file_destination_creation.deduplication)?,
+        serde_json::to_value(file_destination_creation.mode)?,
+        serde_json::to_value(file_destination_creation.sort_by)?
     )
     .fetch_one(executor)
     .await
@@ -122,3 +336,145 @@ pub async fn list_by_file_source_id(
 
     Ok(file_destinations)
 }
+
+pub async fn find_by_id(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileDestination>> {
+    let file_destination = sqlx::query_as!(
+        FileDestinationEntity,
+        r#"
+            SELECT * FROM file_destination fd WHERE fd.id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Searching file destination {}", id))?
+    .map(|i| i.into());
+
+    Ok(file_destination)
+}
+
+// Holds a share lock on the row until the transaction ends, so a state
+// switch waits for whatever was decided on the current state
+pub async fn find_by_id_for_share(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileDestination>> {
+    let file_destination = sqlx::query_as!(
+        FileDestinationEntity,
+        r#"
+            SELECT * FROM file_destination fd WHERE fd.id = $1 FOR SHARE
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Locking file destination {}", id))?
+    .map(|i| i.into());
+
+    Ok(file_destination)
+}
+
+pub async fn find_by_file_source_id_and_identifier(
+    file_source_id: &i32,
+    identifier: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileDestination>> {
+    let file_destination = sqlx::query_as!(
+        FileDestinationEntity,
+        r#"
+            SELECT * FROM file_destination fd WHERE fd.file_source_id = $1 AND fd.identifier = $2
+        "#,
+        file_source_id.clone(),
+        identifier,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Searching file destination {} for file source {}",
+            identifier, file_source_id
+        )
+    })?
+    .map(|i| i.into());
+
+    Ok(file_destination)
+}
+
+pub async fn update_file_destination(
+    file_destination_id: &i32,
+    file_destination_update: FileDestinationCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestination> {
+    let updated_file_destination = sqlx::query_as!(
+        FileDestinationEntity,
+        r#"
+            UPDATE file_destination SET destination = $2, include_headers = $3, "grouping" = $4, batching = $5, transformations = $6, "filter" = $7, masking = $8, deduplication = $9, mode = $10, sort_by = $11, version = version + 1, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        file_destination_id.clone(),
+        serde_json::to_value(file_destination_update.destination)?,
+        file_destination_update.include_headers,
+        serde_json::to_value(file_destination_update.grouping)?,
+        serde_json::to_value(file_destination_update.batching)?,
+        serde_json::to_value(file_destination_update.transformations)?,
+        file_destination_update.filter,
+        serde_json::to_value(file_destination_update.masking)?,
+        serde_json::to_value(file_destination_update.deduplication)?,
+        serde_json::to_value(file_destination_update.mode)?,
+        serde_json::to_value(file_destination_update.sort_by)?
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating file destination {}", file_destination_id))?
+    .into();
+
+    Ok(updated_file_destination)
+}
+
+// The switches are not part of the configuration, so they neither bump the
+// version nor the update time
+pub async fn update_file_destination_state(
+    file_destination_id: &i32,
+    enabled: bool,
+    paused: bool,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestination> {
+    let updated_file_destination = sqlx::query_as!(
+        FileDestinationEntity,
+        r#"
+            UPDATE file_destination SET enabled = $2, paused = $3
+            WHERE id = $1 RETURNING *
+        "#,
+        file_destination_id,
+        enabled,
+        paused,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating state of file destination {}", file_destination_id))?
+    .into();
+
+    Ok(updated_file_destination)
+}
+
+// Dispatch history, deduplication keys and rows still buffered in batch
+// windows are removed with the destination by the foreign keys
+pub async fn delete_file_destination(
+    file_destination_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM file_destination WHERE id = $1
+        "#,
+        file_destination_id,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Deleting file destination {}", file_destination_id))?;
+
+    Ok(())
+}
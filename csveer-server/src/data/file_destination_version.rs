@@ -0,0 +1,84 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{types::JsonValue, PgConnection};
+
+#[derive(Serialize)]
+pub struct FileDestinationVersion {
+    pub id: i32,
+    pub file_destination_id: i32,
+    pub version: i32,
+    pub configuration: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn insert_file_destination_version(
+    file_destination_id: &i32,
+    version: &i32,
+    configuration: JsonValue,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileDestinationVersion> {
+    let created_version = sqlx::query_as!(
+        FileDestinationVersion,
+        r#"
+            INSERT INTO file_destination_version(file_destination_id, version, configuration, created_at)
+            VALUES($1, $2, $3, NOW()) RETURNING *
+        "#,
+        file_destination_id,
+        version,
+        configuration,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Inserting version {} of file destination {}",
+            version, file_destination_id
+        )
+    })?;
+
+    Ok(created_version)
+}
+
+pub async fn list_by_file_destination_id(
+    file_destination_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<FileDestinationVersion>> {
+    let versions = sqlx::query_as!(
+        FileDestinationVersion,
+        r#"
+            SELECT * FROM file_destination_version fdv WHERE fdv.file_destination_id = $1 ORDER BY fdv.version DESC
+        "#,
+        file_destination_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| format!("Listing versions of file destination {}", file_destination_id))?;
+
+    Ok(versions)
+}
+
+pub async fn find_by_file_destination_id_and_version(
+    file_destination_id: &i32,
+    version: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileDestinationVersion>> {
+    let file_destination_version = sqlx::query_as!(
+        FileDestinationVersion,
+        r#"
+            SELECT * FROM file_destination_version fdv WHERE fdv.file_destination_id = $1 AND fdv.version = $2
+        "#,
+        file_destination_id,
+        version,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Searching version {} of file destination {}",
+            version, file_destination_id
+        )
+    })?;
+
+    Ok(file_destination_version)
+}
@@ -0,0 +1,255 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum FileIngestionStatus {
+    Received,
+    Queued,
+    Dispatching,
+    Completed,
+    PartiallyFailed,
+    Failed,
+}
+
+impl TryFrom<String> for FileIngestionStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "Received" => Ok(Self::Received),
+            "Queued" => Ok(Self::Queued),
+            "Dispatching" => Ok(Self::Dispatching),
+            "Completed" => Ok(Self::Completed),
+            "PartiallyFailed" => Ok(Self::PartiallyFailed),
+            "Failed" => Ok(Self::Failed),
+            _ => Err(format!("{} is not a valid FileIngestionStatus", value)),
+        }
+    }
+}
+
+impl ToString for FileIngestionStatus {
+    fn to_string(&self) -> String {
+        match self {
+            FileIngestionStatus::Received => "Received".to_string(),
+            FileIngestionStatus::Queued => "Queued".to_string(),
+            FileIngestionStatus::Dispatching => "Dispatching".to_string(),
+            FileIngestionStatus::Completed => "Completed".to_string(),
+            FileIngestionStatus::PartiallyFailed => "PartiallyFailed".to_string(),
+            FileIngestionStatus::Failed => "Failed".to_string(),
+        }
+    }
+}
+
+pub struct FileIngestionCreation {
+    pub file_source_id: i32,
+    pub file_name: String,
+    pub s3_key: String,
+    pub checksum: String,
+    pub size: i64,
+}
+
+#[derive(Serialize)]
+pub struct FileIngestion {
+    pub id: i32,
+    pub file_source_id: i32,
+    pub file_name: String,
+    pub s3_key: String,
+    pub checksum: String,
+    pub size: i64,
+    pub status: FileIngestionStatus,
+    pub message: Option<String>,
+    pub file_destination_count: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+struct FileIngestionEntity {
+    id: i32,
+    file_source_id: i32,
+    file_name: String,
+    s3_key: String,
+    checksum: String,
+    size: i64,
+    status: String,
+    message: Option<String>,
+    file_destination_count: Option<i32>,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl Into<FileIngestion> for FileIngestionEntity {
+    fn into(self) -> FileIngestion {
+        return FileIngestion {
+            id: self.id,
+            file_source_id: self.file_source_id,
+            file_name: self.file_name,
+            s3_key: self.s3_key,
+            checksum: self.checksum,
+            size: self.size,
+            status: self.status.try_into().unwrap(),
+            message: self.message,
+            file_destination_count: self.file_destination_count,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        };
+    }
+}
+
+pub async fn insert_file_ingestion(
+    file_ingestion_creation: FileIngestionCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileIngestion> {
+    let created_file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            INSERT INTO file_ingestion(file_source_id, file_name, s3_key, checksum, size, status, created_at)
+            VALUES($1, $2, $3, $4, $5, $6, NOW()) RETURNING *
+        "#,
+        file_ingestion_creation.file_source_id,
+        file_ingestion_creation.file_name,
+        file_ingestion_creation.s3_key,
+        file_ingestion_creation.checksum,
+        file_ingestion_creation.size,
+        FileIngestionStatus::Received.to_string(),
+    )
+    .fetch_one(executor)
+    .await
+    .context("Inserting file ingestion record")?
+    .into();
+
+    Ok(created_file_ingestion)
+}
+
+pub async fn find_by_id(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileIngestion>> {
+    let file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            SELECT * FROM file_ingestion fi WHERE fi.id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Searching file ingestion {}", id))?
+    .map(|entity| entity.into());
+
+    Ok(file_ingestion)
+}
+
+// Uploads of a file with the same name share the S3 key, the storage event
+// belongs to the latest one that was not picked up yet
+pub async fn find_received_by_s3_key(
+    s3_key: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileIngestion>> {
+    let file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            SELECT * FROM file_ingestion fi WHERE fi.s3_key = $1 AND fi.status = $2
+            ORDER BY fi.id DESC LIMIT 1
+        "#,
+        s3_key,
+        FileIngestionStatus::Received.to_string(),
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Searching received file ingestion for {}", s3_key))?
+    .map(|entity| entity.into());
+
+    Ok(file_ingestion)
+}
+
+pub async fn lock_file_ingestion(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileIngestion>> {
+    let file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            SELECT * FROM file_ingestion fi WHERE fi.id = $1 FOR UPDATE
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Locking file ingestion {}", id))?
+    .map(|entity| entity.into());
+
+    Ok(file_ingestion)
+}
+
+pub async fn update_file_ingestion_status(
+    id: &i32,
+    status: FileIngestionStatus,
+    message: Option<String>,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileIngestion> {
+    let updated_file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            UPDATE file_ingestion SET status = $2, message = COALESCE($3, message), updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        id,
+        status.to_string(),
+        message,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating status of file ingestion {}", id))?
+    .into();
+
+    Ok(updated_file_ingestion)
+}
+
+pub async fn update_file_destination_count(
+    id: &i32,
+    file_destination_count: i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileIngestion> {
+    let updated_file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            UPDATE file_ingestion SET file_destination_count = $2, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        id,
+        file_destination_count,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating file destination count of file ingestion {}", id))?
+    .into();
+
+    Ok(updated_file_ingestion)
+}
+
+// Replays supersede the previous dispatch of a destination, only
+// destinations that were never dispatched add to the expected count
+pub async fn queue_file_ingestion_replay(
+    id: &i32,
+    additional_file_destination_count: i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileIngestion> {
+    let updated_file_ingestion = sqlx::query_as!(
+        FileIngestionEntity,
+        r#"
+            UPDATE file_ingestion SET status = $2, file_destination_count = COALESCE(file_destination_count, 0) + $3, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        id,
+        FileIngestionStatus::Queued.to_string(),
+        additional_file_destination_count,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Queueing replay of file ingestion {}", id))?
+    .into();
+
+    Ok(updated_file_ingestion)
+}
@@ -26,6 +26,107 @@ pub enum CompressionType {
     },
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ColumnReference {
+    Index(i32),
+    Name(String),
+}
+
+impl std::fmt::Display for ColumnReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnReference::Index(idx) => write!(f, "{}", idx),
+            ColumnReference::Name(name) => write!(f, "'{}'", name),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FixedWidthColumn {
+    pub name: String,
+    pub start: i32,
+    pub length: i32,
+    pub trim: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum FileFormat {
+    Delimited { delimiter: char },
+    FixedWidth { columns: Vec<FixedWidthColumn> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum ColumnType {
+    String {
+        regex: Option<String>,
+        min_length: Option<i32>,
+        max_length: Option<i32>,
+    },
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    Decimal {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    Date {
+        format: String,
+    },
+    Boolean,
+    Enum {
+        values: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnSchema {
+    pub name: String,
+    #[serde(flatten)]
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum ErrorThreshold {
+    Absolute { max_rejected_rows: i32 },
+    Percentage { max_rejected_percentage: f64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum HeaderDriftPolicy {
+    Reject,
+    Warn,
+    AcceptAndUpdate,
+}
+
+impl ToString for HeaderDriftPolicy {
+    fn to_string(&self) -> String {
+        match self {
+            HeaderDriftPolicy::Reject => "Reject".to_string(),
+            HeaderDriftPolicy::Warn => "Warn".to_string(),
+            HeaderDriftPolicy::AcceptAndUpdate => "AcceptAndUpdate".to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for HeaderDriftPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        match value.as_str() {
+            "Reject" => Ok(Self::Reject),
+            "Warn" => Ok(Self::Warn),
+            "AcceptAndUpdate" => Ok(Self::AcceptAndUpdate),
+            _ => Err(format!("{} is not a valid HeaderDriftPolicy value", value)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FileSourceCreation {
     pub context: String,
@@ -34,7 +135,11 @@ pub struct FileSourceCreation {
     pub source: SourceType,
     pub headers: bool,
     pub compression: Option<CompressionType>,
-    pub hide_columns: Option<Vec<i32>>,
+    pub hide_columns: Option<Vec<ColumnReference>>,
+    pub format: Option<FileFormat>,
+    pub schema: Option<Vec<ColumnSchema>>,
+    pub error_threshold: Option<ErrorThreshold>,
+    pub header_drift_policy: Option<HeaderDriftPolicy>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,7 +151,15 @@ pub struct FileSource {
     pub source: Option<SourceType>,
     pub headers: bool,
     pub compression: Option<CompressionType>,
-    pub hide_columns: Option<Vec<i32>>,
+    pub hide_columns: Option<Vec<ColumnReference>>,
+    pub format: Option<FileFormat>,
+    pub schema: Option<Vec<ColumnSchema>>,
+    pub error_threshold: Option<ErrorThreshold>,
+    pub header_drift_policy: Option<HeaderDriftPolicy>,
+    pub header_signature: Option<Vec<String>>,
+    pub version: i32,
+    pub enabled: bool,
+    pub paused: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -59,9 +172,17 @@ pub struct FileSourceEntity {
     source: sqlx::types::JsonValue,
     headers: bool,
     compression: sqlx::types::JsonValue,
-    hide_columns: Option<Vec<i32>>,
+    hide_columns: sqlx::types::JsonValue,
+    format: sqlx::types::JsonValue,
+    schema: sqlx::types::JsonValue,
+    error_threshold: sqlx::types::JsonValue,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    header_drift_policy: sqlx::types::JsonValue,
+    header_signature: sqlx::types::JsonValue,
+    version: i32,
+    enabled: bool,
+    paused: bool,
 }
 
 impl Into<FileSource> for FileSourceEntity {
@@ -74,7 +195,15 @@ impl Into<FileSource> for FileSourceEntity {
             source: serde_json::from_value(self.source).unwrap(),
             headers: self.headers,
             compression: serde_json::from_value(self.compression).unwrap(),
-            hide_columns: self.hide_columns,
+            hide_columns: serde_json::from_value(self.hide_columns).unwrap(),
+            format: serde_json::from_value(self.format).unwrap(),
+            schema: serde_json::from_value(self.schema).unwrap(),
+            error_threshold: serde_json::from_value(self.error_threshold).unwrap(),
+            header_drift_policy: serde_json::from_value(self.header_drift_policy).unwrap(),
+            header_signature: serde_json::from_value(self.header_signature).unwrap(),
+            version: self.version,
+            enabled: self.enabled,
+            paused: self.paused,
             created_at: self.created_at,
             updated_at: self.updated_at,
         };
@@ -83,13 +212,14 @@ impl Into<FileSource> for FileSourceEntity {
 
 pub async fn insert_file_source(
     creatable_file_source: FileSourceCreation,
+    header_signature: Option<Vec<String>>,
     executor: &mut PgConnection,
 ) -> anyhow::Result<FileSource> {
     let created_file_source = sqlx::query_as!(
         FileSourceEntity,
         r#"
-           INSERT INTO file_source(context, identifier, description, "source", headers, compression, hide_columns, created_at)
-           VALUES($1, $2, $3, $4, $5, $6, $7, NOW()) RETURNING *
+           INSERT INTO file_source(context, identifier, description, "source", headers, compression, hide_columns, format, "schema", error_threshold, header_drift_policy, header_signature, created_at)
+           VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW()) RETURNING *
         "#,
         creatable_file_source.context,
         creatable_file_source.identifier,
@@ -97,7 +227,12 @@ pub async fn insert_file_source(
         serde_json::to_value(creatable_file_source.source)?,
         creatable_file_source.headers,
         serde_json::to_value(creatable_file_source.compression)?,
-        creatable_file_source.hide_columns.as_deref()
+        serde_json::to_value(creatable_file_source.hide_columns)?,
+        serde_json::to_value(creatable_file_source.format)?,
+        serde_json::to_value(creatable_file_source.schema)?,
+        serde_json::to_value(creatable_file_source.error_threshold)?,
+        serde_json::to_value(creatable_file_source.header_drift_policy)?,
+        serde_json::to_value(header_signature)?
     )
     .fetch_one(executor)
     .await
@@ -107,6 +242,46 @@ pub async fn insert_file_source(
     Ok(created_file_source)
 }
 
+pub async fn find_by_id(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileSource>> {
+    let file_source = sqlx::query_as!(
+        FileSourceEntity,
+        r#"
+            SELECT * FROM file_source fs WHERE fs.id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Searching file source {}", id))?
+    .map(|entity| entity.into());
+
+    Ok(file_source)
+}
+
+// Like `find_by_id`, keeping the row locked against state switches until the
+// transaction ends
+pub async fn find_by_id_for_share(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileSource>> {
+    let file_source = sqlx::query_as!(
+        FileSourceEntity,
+        r#"
+            SELECT * FROM file_source fs WHERE fs.id = $1 FOR SHARE
+        "#,
+        id,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| format!("Locking file source {}", id))?
+    .map(|entity| entity.into());
+
+    Ok(file_source)
+}
+
 pub async fn find_by_context_and_identifier(
     context: &str,
     identifier: &str,
@@ -136,3 +311,163 @@ pub async fn find_by_context_and_identifier(
 
     Ok(file_source)
 }
+
+pub async fn update_header_signature(
+    file_source_id: &i32,
+    header_signature: &[String],
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSource> {
+    let updated_file_source = sqlx::query_as!(
+        FileSourceEntity,
+        r#"
+            UPDATE file_source SET header_signature = $2, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        file_source_id.clone(),
+        serde_json::to_value(header_signature)?,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Updating header signature of file source {}",
+            file_source_id
+        )
+    })?
+    .into();
+
+    Ok(updated_file_source)
+}
+
+pub async fn list_file_sources(
+    context: Option<&str>,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<FileSource>> {
+    let file_sources = sqlx::query_as!(
+        FileSourceEntity,
+        r#"
+            SELECT * FROM file_source fs
+            WHERE $1::VARCHAR IS NULL OR fs.context = $1
+            ORDER BY fs.context, fs.identifier
+        "#,
+        context,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Listing file sources")?
+    .into_iter()
+    .map(|entity| entity.into())
+    .collect();
+
+    Ok(file_sources)
+}
+
+pub async fn update_file_source(
+    file_source_id: &i32,
+    file_source_update: FileSourceCreation,
+    header_signature: Option<Vec<String>>,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSource> {
+    let updated_file_source = sqlx::query_as!(
+        FileSourceEntity,
+        r#"
+            UPDATE file_source SET description = $2, "source" = $3, headers = $4, compression = $5, hide_columns = $6, format = $7, "schema" = $8, error_threshold = $9, header_drift_policy = $10, header_signature = $11, version = version + 1, updated_at = NOW()
+            WHERE id = $1 RETURNING *
+        "#,
+        file_source_id.clone(),
+        file_source_update.description,
+        serde_json::to_value(file_source_update.source)?,
+        file_source_update.headers,
+        serde_json::to_value(file_source_update.compression)?,
+        serde_json::to_value(file_source_update.hide_columns)?,
+        serde_json::to_value(file_source_update.format)?,
+        serde_json::to_value(file_source_update.schema)?,
+        serde_json::to_value(file_source_update.error_threshold)?,
+        serde_json::to_value(file_source_update.header_drift_policy)?,
+        serde_json::to_value(header_signature)?
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating file source {}", file_source_id))?
+    .into();
+
+    Ok(updated_file_source)
+}
+
+// The switches are not part of the configuration, so they neither bump the
+// version nor the update time
+pub async fn update_file_source_state(
+    file_source_id: &i32,
+    enabled: bool,
+    paused: bool,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSource> {
+    let updated_file_source = sqlx::query_as!(
+        FileSourceEntity,
+        r#"
+            UPDATE file_source SET enabled = $2, paused = $3
+            WHERE id = $1 RETURNING *
+        "#,
+        file_source_id,
+        enabled,
+        paused,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| format!("Updating state of file source {}", file_source_id))?
+    .into();
+
+    Ok(updated_file_source)
+}
+
+pub async fn update_context(
+    context: &str,
+    new_context: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE file_source SET context = $2, updated_at = NOW() WHERE context = $1
+        "#,
+        context,
+        new_context,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Moving file sources of context {}", context))?;
+
+    Ok(())
+}
+
+// Destinations, their dispatch history and the header drift events of the
+// file source are removed with it by the foreign keys
+pub async fn delete_file_source(
+    file_source_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM file_source WHERE id = $1
+        "#,
+        file_source_id,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Deleting file source {}", file_source_id))?;
+
+    Ok(())
+}
+
+pub async fn delete_by_context(context: &str, executor: &mut PgConnection) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM file_source WHERE context = $1
+        "#,
+        context,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Deleting file sources of context {}", context))?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{types::JsonValue, PgConnection};
+
+#[derive(Serialize)]
+pub struct FileSourceVersion {
+    pub id: i32,
+    pub file_source_id: i32,
+    pub version: i32,
+    pub configuration: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn insert_file_source_version(
+    file_source_id: &i32,
+    version: &i32,
+    configuration: JsonValue,
+    executor: &mut PgConnection,
+) -> anyhow::Result<FileSourceVersion> {
+    let created_version = sqlx::query_as!(
+        FileSourceVersion,
+        r#"
+            INSERT INTO file_source_version(file_source_id, version, configuration, created_at)
+            VALUES($1, $2, $3, NOW()) RETURNING *
+        "#,
+        file_source_id,
+        version,
+        configuration,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Inserting version {} of file source {}",
+            version, file_source_id
+        )
+    })?;
+
+    Ok(created_version)
+}
+
+pub async fn list_by_file_source_id(
+    file_source_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<FileSourceVersion>> {
+    let versions = sqlx::query_as!(
+        FileSourceVersion,
+        r#"
+            SELECT * FROM file_source_version fsv WHERE fsv.file_source_id = $1 ORDER BY fsv.version DESC
+        "#,
+        file_source_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| format!("Listing versions of file source {}", file_source_id))?;
+
+    Ok(versions)
+}
+
+pub async fn find_by_file_source_id_and_version(
+    file_source_id: &i32,
+    version: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<FileSourceVersion>> {
+    let file_source_version = sqlx::query_as!(
+        FileSourceVersion,
+        r#"
+            SELECT * FROM file_source_version fsv WHERE fsv.file_source_id = $1 AND fsv.version = $2
+        "#,
+        file_source_id,
+        version,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Searching version {} of file source {}",
+            version, file_source_id
+        )
+    })?;
+
+    Ok(file_source_version)
+}
@@ -0,0 +1,99 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+use super::file_source::HeaderDriftPolicy;
+
+pub struct HeaderDriftEventCreation {
+    pub file_source_id: i32,
+    pub file_name: String,
+    pub expected_headers: Vec<String>,
+    pub found_headers: Vec<String>,
+    pub policy: HeaderDriftPolicy,
+}
+
+#[derive(Serialize)]
+pub struct HeaderDriftEvent {
+    pub id: i32,
+    pub file_source_id: i32,
+    pub file_name: String,
+    pub expected_headers: Vec<String>,
+    pub found_headers: Vec<String>,
+    pub policy: HeaderDriftPolicy,
+    pub created_at: DateTime<Utc>,
+}
+
+struct HeaderDriftEventEntity {
+    id: i32,
+    file_source_id: i32,
+    file_name: String,
+    expected_headers: sqlx::types::JsonValue,
+    found_headers: sqlx::types::JsonValue,
+    policy: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Into<HeaderDriftEvent> for HeaderDriftEventEntity {
+    fn into(self) -> HeaderDriftEvent {
+        return HeaderDriftEvent {
+            id: self.id,
+            file_source_id: self.file_source_id,
+            file_name: self.file_name,
+            expected_headers: serde_json::from_value(self.expected_headers).unwrap(),
+            found_headers: serde_json::from_value(self.found_headers).unwrap(),
+            policy: self.policy.try_into().unwrap(),
+            created_at: self.created_at,
+        };
+    }
+}
+
+pub async fn insert_header_drift_event(
+    header_drift_event_creation: HeaderDriftEventCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<HeaderDriftEvent> {
+    let created_event = sqlx::query_as!(
+        HeaderDriftEventEntity,
+        r#"
+            INSERT INTO header_drift_event(file_source_id, file_name, expected_headers, found_headers, policy, created_at)
+            VALUES($1, $2, $3, $4, $5, NOW()) RETURNING *
+        "#,
+        header_drift_event_creation.file_source_id,
+        header_drift_event_creation.file_name,
+        serde_json::to_value(header_drift_event_creation.expected_headers)?,
+        serde_json::to_value(header_drift_event_creation.found_headers)?,
+        header_drift_event_creation.policy.to_string(),
+    )
+    .fetch_one(executor)
+    .await
+    .context("Inserting header drift event")?
+    .into();
+
+    Ok(created_event)
+}
+
+pub async fn list_by_file_source_id(
+    file_source_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<HeaderDriftEvent>> {
+    let events = sqlx::query_as!(
+        HeaderDriftEventEntity,
+        r#"
+            SELECT * FROM header_drift_event hde WHERE hde.file_source_id = $1 ORDER BY hde.created_at DESC, hde.id DESC
+        "#,
+        file_source_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Listing header drift events of file source {}",
+            file_source_id
+        )
+    })?
+    .into_iter()
+    .map(|entity| entity.into())
+    .collect();
+
+    Ok(events)
+}
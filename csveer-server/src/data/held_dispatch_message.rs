@@ -0,0 +1,74 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+pub struct HeldDispatchMessage {
+    pub id: i32,
+    pub file_destination_id: i32,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn insert_held_dispatch_message(
+    file_destination_id: &i32,
+    message: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<HeldDispatchMessage> {
+    let held_message = sqlx::query_as!(
+        HeldDispatchMessage,
+        r#"
+            INSERT INTO held_dispatch_message(file_destination_id, message, created_at)
+            VALUES($1, $2, NOW()) RETURNING *
+        "#,
+        file_destination_id,
+        message,
+    )
+    .fetch_one(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Holding dispatch message for file destination {}",
+            file_destination_id
+        )
+    })?;
+
+    Ok(held_message)
+}
+
+// Oldest first, so released messages keep their original order
+pub async fn list_by_file_destination_id(
+    file_destination_id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Vec<HeldDispatchMessage>> {
+    let held_messages = sqlx::query_as!(
+        HeldDispatchMessage,
+        r#"
+            SELECT * FROM held_dispatch_message
+            WHERE file_destination_id = $1
+            ORDER BY id
+        "#,
+        file_destination_id,
+    )
+    .fetch_all(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Listing held dispatch messages of file destination {}",
+            file_destination_id
+        )
+    })?;
+
+    Ok(held_messages)
+}
+
+pub async fn delete_held_dispatch_message(
+    id: &i32,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!("DELETE FROM held_dispatch_message WHERE id = $1", id)
+        .execute(executor)
+        .await
+        .with_context(|| format!("Deleting held dispatch message {}", id))?;
+
+    Ok(())
+}
@@ -1,5 +1,13 @@
+pub mod batch_window;
 pub mod context;
 pub mod data_dispatch;
 pub mod data_dispatch_execution;
+pub mod dispatched_row_key;
 pub mod file_destination;
+pub mod file_destination_version;
+pub mod file_ingestion;
 pub mod file_source;
+pub mod file_source_version;
+pub mod header_drift_event;
+pub mod held_dispatch_message;
+pub mod reference_dataset;
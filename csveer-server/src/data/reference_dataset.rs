@@ -0,0 +1,136 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{types::JsonValue, PgConnection};
+
+pub struct ReferenceDatasetCreation {
+    pub context: String,
+    pub identifier: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferenceDataset {
+    pub id: i32,
+    pub context: String,
+    pub identifier: String,
+    pub columns: Vec<String>,
+    pub row_count: usize,
+    #[serde(skip)]
+    pub rows: Vec<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+struct ReferenceDatasetEntity {
+    id: i32,
+    context: String,
+    identifier: String,
+    columns: JsonValue,
+    rows: JsonValue,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl Into<ReferenceDataset> for ReferenceDatasetEntity {
+    fn into(self) -> ReferenceDataset {
+        let rows: Vec<Vec<String>> = serde_json::from_value(self.rows).unwrap();
+        return ReferenceDataset {
+            id: self.id,
+            context: self.context,
+            identifier: self.identifier,
+            columns: serde_json::from_value(self.columns).unwrap(),
+            row_count: rows.len(),
+            rows,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        };
+    }
+}
+
+// Uploading a dataset with an existing identifier replaces its contents
+pub async fn upsert_reference_dataset(
+    reference_dataset_creation: ReferenceDatasetCreation,
+    executor: &mut PgConnection,
+) -> anyhow::Result<ReferenceDataset> {
+    let reference_dataset = sqlx::query_as!(
+        ReferenceDatasetEntity,
+        r#"
+            INSERT INTO reference_dataset(context, identifier, columns, "rows", created_at)
+            VALUES($1, $2, $3, $4, NOW())
+            ON CONFLICT (context, identifier) DO UPDATE
+            SET columns = EXCLUDED.columns, "rows" = EXCLUDED."rows", updated_at = NOW()
+            RETURNING *
+        "#,
+        reference_dataset_creation.context,
+        reference_dataset_creation.identifier,
+        serde_json::to_value(reference_dataset_creation.columns)?,
+        serde_json::to_value(reference_dataset_creation.rows)?,
+    )
+    .fetch_one(executor)
+    .await
+    .context("Upserting reference dataset")?
+    .into();
+
+    Ok(reference_dataset)
+}
+
+pub async fn find_by_context_and_identifier(
+    context: &str,
+    identifier: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<Option<ReferenceDataset>> {
+    let reference_dataset = sqlx::query_as!(
+        ReferenceDatasetEntity,
+        r#"
+            SELECT * FROM reference_dataset rd WHERE rd.context = $1 AND rd.identifier = $2
+        "#,
+        context,
+        identifier,
+    )
+    .fetch_optional(executor)
+    .await
+    .with_context(|| {
+        format!(
+            "Finding reference dataset {} of context {}",
+            identifier, context
+        )
+    })?
+    .map(|entity| entity.into());
+
+    Ok(reference_dataset)
+}
+
+pub async fn update_context(
+    context: &str,
+    new_context: &str,
+    executor: &mut PgConnection,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE reference_dataset SET context = $2, updated_at = NOW() WHERE context = $1
+        "#,
+        context,
+        new_context,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Moving reference datasets of context {}", context))?;
+
+    Ok(())
+}
+
+pub async fn delete_by_context(context: &str, executor: &mut PgConnection) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM reference_dataset WHERE context = $1
+        "#,
+        context,
+    )
+    .execute(executor)
+    .await
+    .with_context(|| format!("Deleting reference datasets of context {}", context))?;
+
+    Ok(())
+}
@@ -1,6 +1,11 @@
 use aws_sdk_s3::Client as S3Client;
 use aws_sdk_sqs::Client as SQSClient;
-use axum::{extract::MatchedPath, http::Request, routing::post, Router};
+use axum::{
+    extract::MatchedPath,
+    http::Request,
+    routing::{get, post},
+    Router,
+};
 use config::{
     aws::AwsConfig,
     server::{AppError, AppState},
@@ -44,16 +49,135 @@ pub async fn build_app(
     };
 
     Ok(Router::new()
-        .route("/context", post(app::context::create_context))
-        .route("/source", post(app::file_source::create_file_source))
+        .route(
+            "/context",
+            post(app::context::create_context).get(app::context::list_contexts),
+        )
+        .route(
+            "/context/:name",
+            get(app::context::get_context)
+                .put(app::context::update_context)
+                .delete(app::context::delete_context),
+        )
+        .route(
+            "/source",
+            post(app::file_source::create_file_source).get(app::file_source::list_file_sources),
+        )
+        .route(
+            "/:context/:file_source",
+            get(app::file_source::get_file_source)
+                .put(app::file_source::update_file_source)
+                .delete(app::file_source::delete_file_source),
+        )
+        .route(
+            "/:context/:file_source/versions",
+            get(app::file_source::list_file_source_versions),
+        )
+        .route(
+            "/:context/:file_source/versions/diff",
+            get(app::file_source::diff_file_source_versions),
+        )
+        .route(
+            "/:context/:file_source/versions/:version/rollback",
+            post(app::file_source::rollback_file_source),
+        )
+        .route(
+            "/:context/:file_source/pause",
+            post(app::file_source::pause_file_source),
+        )
+        .route(
+            "/:context/:file_source/resume",
+            post(app::file_source::resume_file_source),
+        )
+        .route(
+            "/:context/:file_source/enable",
+            post(app::file_source::enable_file_source),
+        )
+        .route(
+            "/:context/:file_source/disable",
+            post(app::file_source::disable_file_source),
+        )
         .route(
             "/:context/:file_source/destination",
-            post(app::file_destination::create_file_destination),
+            post(app::file_destination::create_file_destination)
+                .get(app::file_destination::list_file_destinations),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier",
+            get(app::file_destination::get_file_destination)
+                .put(app::file_destination::update_file_destination)
+                .delete(app::file_destination::delete_file_destination),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/preview",
+            post(app::destination_preview::preview_file_destination),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/versions",
+            get(app::file_destination::list_file_destination_versions),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/versions/diff",
+            get(app::file_destination::diff_file_destination_versions),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/versions/:version/rollback",
+            post(app::file_destination::rollback_file_destination),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/pause",
+            post(app::file_destination::pause_file_destination),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/resume",
+            post(app::file_destination::resume_file_destination),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/enable",
+            post(app::file_destination::enable_file_destination),
+        )
+        .route(
+            "/:context/:file_source/destination/:identifier/disable",
+            post(app::file_destination::disable_file_destination),
+        )
+        .route(
+            "/export",
+            get(app::configuration_document::export_configuration),
+        )
+        .route(
+            "/import",
+            post(app::configuration_document::import_configuration),
+        )
+        .route("/dispatches", get(app::data_dispatch::list_data_dispatches))
+        .route(
+            "/dispatches/:id",
+            get(app::data_dispatch::get_data_dispatch),
+        )
+        .route(
+            "/ingestions/:id",
+            get(app::file_ingestion::get_file_ingestion),
+        )
+        .route(
+            "/ingestions/:id/replay",
+            post(app::file_ingestion::replay_file_ingestion),
         )
         .route(
             "/:context/:file_source/upload",
             post(app::file_input::file_upload),
         )
+        .route(
+            "/:context/:file_source/infer-schema",
+            post(app::schema_inference::infer_schema),
+        )
+        .route(
+            "/:context/reference-datasets/:identifier",
+            post(app::reference_dataset::upload_reference_dataset)
+                .get(app::reference_dataset::get_reference_dataset),
+        )
+        .route(
+            "/:context/:file_source/header-drifts",
+            get(app::header_drift::list_header_drift_events),
+        )
         .with_state(app_state)
         .layer(
             TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
@@ -103,6 +227,68 @@ pub async fn start_file_ingestion_listener(
     );
 }
 
+#[instrument(skip(tracker, token, db_pool, s3_client, sqs_client))]
+pub async fn start_data_dispatch_listener(
+    tracker: &TaskTracker,
+    token: CancellationToken,
+    db_pool: Pool<Postgres>,
+    s3_client: S3Client,
+    sqs_client: SQSClient,
+) {
+    let listener_span = info_span!(parent: None, "data-dispatch-listener");
+    let _span_guard = listener_span.enter();
+    let app_state = AppState {
+        db_pool,
+        s3_client,
+        sqs_client,
+    };
+    tracker.spawn(
+        async move {
+            tokio::select! {
+                res = app::data_dispatch_queue::listen_data_dispatch(&token, app_state) => {
+                    match res {
+                        Ok(_) => info!("Data dispatch listener was shut down."),
+                        Err(err) => error!("Listener execution failed. {:?}", err),
+                    }
+
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+#[instrument(skip(tracker, token, db_pool, s3_client, sqs_client))]
+pub async fn start_batch_window_flusher(
+    tracker: &TaskTracker,
+    token: CancellationToken,
+    db_pool: Pool<Postgres>,
+    s3_client: S3Client,
+    sqs_client: SQSClient,
+) {
+    let flusher_span = info_span!(parent: None, "batch-window-flusher");
+    let _span_guard = flusher_span.enter();
+    let app_state = AppState {
+        db_pool,
+        s3_client,
+        sqs_client,
+    };
+    tracker.spawn(
+        async move {
+            tokio::select! {
+                res = app::batch_window::listen_batch_windows(&token, app_state) => {
+                    match res {
+                        Ok(_) => info!("Batch window flusher was shut down."),
+                        Err(err) => error!("Flusher execution failed. {:?}", err),
+                    }
+
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
 pub async fn get_s3_client(aws_config: &AwsConfig) -> anyhow::Result<S3Client, AppError> {
     Ok(config::aws::get_s3_client(aws_config).await?)
 }
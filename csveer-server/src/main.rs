@@ -52,6 +52,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
     )
     .await;
 
+    csveer_server::start_data_dispatch_listener(
+        &tracker,
+        cancellation_token.clone(),
+        db_pool.clone(),
+        s3_client.clone(),
+        sqs_client.clone(),
+    )
+    .await;
+
+    csveer_server::start_batch_window_flusher(
+        &tracker,
+        cancellation_token.clone(),
+        db_pool.clone(),
+        s3_client.clone(),
+        sqs_client.clone(),
+    )
+    .await;
+
     tracker.close();
 
     axum::serve(listener, app)
@@ -0,0 +1,126 @@
+use reqwest::StatusCode;
+
+mod common;
+
+#[tokio::test]
+async fn test_should_plan_and_import_configuration() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/import?plan=true", addr))
+        .header("Content-Type", "application/yaml")
+        .body(include_str!(
+            "requests/configuration/import_configuration_0001.yaml"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["applied"], false);
+    assert_eq!(body["changes"].as_array().unwrap().len(), 3);
+
+    let res = client
+        .get(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = client
+        .post(format!("http://{}/import", addr))
+        .header("Content-Type", "application/yaml")
+        .body(include_str!(
+            "requests/configuration/import_configuration_0001.yaml"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["applied"], true);
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-sample-queue",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Importing the same document again has nothing left to change
+    let res = client
+        .post(format!("http://{}/import", addr))
+        .header("Content-Type", "application/yaml")
+        .body(include_str!(
+            "requests/configuration/import_configuration_0001.yaml"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert!(body["changes"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_should_export_and_prune_configuration() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!("http://{}/export?format=json", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut document: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(document["contexts"], serde_json::json!(["banking"]));
+    assert_eq!(
+        document["file_sources"][0]["identifier"],
+        "daily-transfer-csv"
+    );
+
+    document["file_sources"] = serde_json::json!([]);
+    let res = client
+        .post(format!("http://{}/import?prune=true", addr))
+        .header("Content-Type", "application/json")
+        .body(document.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["changes"][0]["action"], "delete");
+    assert_eq!(body["changes"][0]["name"], "banking/daily-transfer-csv");
+
+    let res = client
+        .get(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_import_duplicated_file_source() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/import", addr))
+        .header("Content-Type", "application/yaml")
+        .body(include_str!(
+            "requests/configuration/invalid_configuration_0001.yaml"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Invalid configuration document");
+}
@@ -121,3 +121,119 @@ async fn test_should_fail_to_insert_context_with_duplicated_name() {
         &r#"{"message":"A context with name 'test' already exists.","details":[]}"#
     );
 }
+
+#[tokio::test]
+async fn test_should_list_and_get_contexts() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/context", addr))
+        .header("Content-Type", "application/json")
+        .body(r#"{"name": "test"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .get(format!("http://{}/context", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    let res = client
+        .get(format!("http://{}/context/test", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+        .get(format!("http://{}/context/unknown", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_rename_context_along_with_its_file_sources() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(format!("http://{}/context/banking", addr))
+        .header("Content-Type", "application/json")
+        .body(r#"{"name": "retail-banking"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["name"], "retail-banking");
+    assert!(!body["updated_at"].is_null());
+
+    let res = client
+        .get(format!("http://{}/retail-banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_should_only_delete_context_with_file_sources_on_cascade() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .delete(format!("http://{}/context/banking", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = client
+        .delete(format!("http://{}/context/banking?cascade=true", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = client
+        .get(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_insert_context_with_reserved_name() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/context", addr))
+        .header("Content-Type", "application/json")
+        .body(
+            r#"
+                {"name": "context"}
+            "#,
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        &res.text().await.unwrap(),
+        &r#"{"message":"Context name 'context' is reserved.","details":[]}"#
+    );
+}
@@ -0,0 +1,75 @@
+use reqwest::StatusCode;
+
+mod common;
+
+#[tokio::test]
+async fn test_should_list_data_dispatches_page() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!(
+            "http://{}/dispatches?context=banking&file_source=daily-transfer-csv&status=Finished&from=2026-10-18T00:00:00Z&to=2026-10-19T00:00:00Z&page=1&page_size=20",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let result_str = &res.text().await.unwrap();
+    assert_eq!(
+        &result_str,
+        &r#"{"items":[],"page":1,"page_size":20,"total":0}"#
+    );
+}
+
+#[tokio::test]
+async fn test_should_fail_to_list_data_dispatches_with_invalid_query() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!(
+            "http://{}/dispatches?status=Sent&page=0&page_size=1000",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Invalid data dispatch query");
+    assert_eq!(body["details"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_list_data_dispatches_past_last_possible_page() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!(
+            "http://{}/dispatches?page=9223372036854775807&page_size=100",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_get_unknown_data_dispatch() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!("http://{}/dispatches/42", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
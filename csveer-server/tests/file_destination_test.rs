@@ -327,3 +327,1032 @@ async fn test_should_fail_to_create_sqs_destination_given_fixed_batching_with_ne
 
     assert_eq!(res.status(), StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn test_should_succesfully_create_s3_parquet_destination() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0005.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_local_file_system_arrow_destination() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0006.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_s3_destination_given_invalid_bucket() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0010.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_parquet_destination_given_row_group_size_0() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0011.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_local_file_system_destination_given_relative_directory() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0012.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_grouping_by_column_names() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0007.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_sqs_destination_given_column_grouping_with_duplicated_names() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0013.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_transformations() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0008.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_list_every_transformation_issue() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0014.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["message"],
+        "File destination transformations are invalid"
+    );
+    assert_eq!(body["details"].as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_reformat_dates_into_time_fields() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0024.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["message"],
+        "File destination transformations are invalid"
+    );
+    assert_eq!(
+        body["details"],
+        serde_json::json!(["Step 1: '%Y-%m-%d %H:%M' should only hold date fields"])
+    );
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_filter() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0009.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_file_destination_given_unparseable_filter() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0015.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Invalid filter expression");
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_file_destination_given_filter_comparing_mismatched_types() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_header_drift_policy.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0016.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Invalid filter expression");
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_masked_columns() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0010.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_list_every_masking_issue() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0017.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "File destination masking is invalid");
+    assert_eq!(body["details"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_grouping_and_filtering_by_computed_columns(
+) {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_header_drift_policy.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0011.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_file_destination_given_computed_column_with_mismatched_types() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_header_drift_policy.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0018.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_grouping_by_looked_up_column() {
+    let addr = common::prepare_for_test().await;
+    common::upload_reference_dataset(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_header_drift_policy.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0012.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_file_destination_given_lookup_on_unknown_dataset() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0012.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["message"],
+        "Reference dataset account-holders does not exist in context banking."
+    );
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_file_destination_given_lookup_of_unknown_field() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+    common::upload_reference_dataset(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0019.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_deduplication() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0013.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_list_every_deduplication_issue() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0020.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "File destination deduplication is invalid");
+    assert_eq!(body["details"].as_array().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_aggregate_mode() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0014.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_list_every_aggregation_issue() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0021.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "File destination aggregation is invalid");
+    assert_eq!(body["details"].as_array().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_sorting() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0015.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_list_every_sorting_issue() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0022.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "File destination sorting is invalid");
+    assert_eq!(body["details"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_should_succesfully_create_file_destination_with_time_window_batching() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0016.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_destination_given_time_window_without_positive_limits() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/invalid_file_destination_0023.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Invalid time window batch configuration");
+    assert_eq!(body["details"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_should_get_update_and_delete_file_destination() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0001.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    let destination_url = format!(
+        "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-sample-queue",
+        addr
+    );
+    let mut update: serde_json::Value = serde_json::from_str(include_str!(
+        "requests/file_destination/create_file_destination_0001.json"
+    ))
+    .unwrap();
+    update["batching"]["batch_size"] = serde_json::json!(50);
+    let res = client
+        .put(&destination_url)
+        .header("Content-Type", "application/json")
+        .body(update.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client.get(&destination_url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["batching"]["batch_size"], 50);
+    assert!(!body["updated_at"].is_null());
+
+    let res = client.delete(&destination_url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = client.get(&destination_url).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_create_file_destination_with_duplicated_identifier() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    for expected_status in [StatusCode::CREATED, StatusCode::BAD_REQUEST] {
+        let res = client
+            .post(format!(
+                "http://{}/banking/daily-transfer-csv/destination",
+                addr
+            ))
+            .header("Content-Type", "application/json")
+            .body(include_str!(
+                "requests/file_destination/create_file_destination_0001.json"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), expected_status);
+    }
+}
+
+#[tokio::test]
+async fn test_should_keep_versions_of_file_destination_and_roll_back() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0001.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let destination_url = format!(
+        "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-sample-queue",
+        addr
+    );
+    let mut update: serde_json::Value = serde_json::from_str(include_str!(
+        "requests/file_destination/create_file_destination_0001.json"
+    ))
+    .unwrap();
+    update["batching"]["batch_size"] = serde_json::json!(50);
+    let res = client
+        .put(&destination_url)
+        .header("Content-Type", "application/json")
+        .body(update.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+        .get(format!("{}/versions/diff?from=1&to=2", destination_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["changes"],
+        serde_json::json!([{"path": "batching.batch_size", "from": 10, "to": 50}])
+    );
+
+    let res = client
+        .post(format!("{}/versions/1/rollback", destination_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["version"], 3);
+    assert_eq!(body["batching"]["batch_size"], 10);
+
+    let res = client
+        .get(format!("{}/versions", destination_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_should_preview_saved_file_destination() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0001.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let (content_type, body) = common::multipart_file_body(
+        "daily-transfers.csv",
+        include_str!("csv_samples/basic_headerless_csv.csv"),
+    );
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-sample-queue/preview",
+            addr
+        ))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["total_rows"], 80);
+    let messages = body["messages"].as_array().unwrap();
+    let previewed_rows: u64 = messages
+        .iter()
+        .map(|message| message["row_count"].as_u64().unwrap())
+        .sum();
+    assert_eq!(previewed_rows, 80);
+    assert!(messages.iter().all(|message| {
+        message["target"] == "http://localhost.localstack.cloud:4566/000000000000/sample-queue"
+            && !message["group"].is_null()
+            && message["row_count"].as_u64().unwrap() <= 10
+    }));
+}
+
+#[tokio::test]
+async fn test_should_preview_unsaved_file_destination_configuration() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let boundary = "csveer-test-boundary";
+    let body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"configuration\"\r\nContent-Type: application/json\r\n\r\n{configuration}\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"daily-transfers.csv\"\r\nContent-Type: text/csv\r\n\r\n{contents}\r\n--{boundary}--\r\n",
+        configuration = r#"{
+            "identifier": "daily-transfer-csv-to-ledger",
+            "destination": {
+                "type": "SQS",
+                "queue_url": "http://localhost.localstack.cloud:4566/000000000000/ledger-queue"
+            },
+            "include_headers": false,
+            "batching": {"type": "Fixed", "batch_size": 25}
+        }"#,
+        contents = include_str!("csv_samples/basic_headerless_csv.csv"),
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-ledger/preview",
+            addr
+        ))
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let row_counts: Vec<u64> = body["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["row_count"].as_u64().unwrap())
+        .collect();
+    assert_eq!(row_counts, vec![25, 25, 25, 5]);
+    assert_eq!(body["messages"][0]["payload"]["rows"][0][1], "Alice");
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-ledger",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_pause_and_resume_file_destination() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0001.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["enabled"], true);
+    assert_eq!(body["paused"], false);
+
+    let destination_url = format!(
+        "http://{}/banking/daily-transfer-csv/destination/daily-transfer-csv-to-sample-queue",
+        addr
+    );
+    let res = client
+        .post(format!("{}/pause", destination_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["paused"], true);
+    assert_eq!(body["version"], 1);
+
+    let res = client
+        .post(format!("{}/resume", destination_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["paused"], false);
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination/unknown-destination/pause",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,195 @@
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+mod common;
+
+#[tokio::test]
+async fn test_should_track_uploaded_file_as_ingestion() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let contents = include_str!("csv_samples/basic_headerless_csv.csv");
+    let (content_type, body) = common::multipart_file_body("daily-transfers.csv", contents);
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/banking/daily-transfer-csv/upload", addr))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let file_ingestions = body["file_ingestions"].as_array().unwrap();
+    assert_eq!(file_ingestions.len(), 1);
+    let file_ingestion_id = file_ingestions[0]["id"].as_i64().unwrap();
+
+    let res = client
+        .get(format!("http://{}/ingestions/{}", addr, file_ingestion_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["file_name"], "daily-transfers.csv");
+    assert_eq!(
+        body["s3_key"],
+        "banking/daily-transfer-csv/daily-transfers.csv"
+    );
+    assert_eq!(body["size"], contents.len());
+    assert_eq!(body["checksum"].as_str().unwrap().len(), 64);
+    assert!(body["dispatches"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_should_fail_to_get_unknown_ingestion() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!("http://{}/ingestions/42", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_replay_ingestion_still_being_processed() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let (content_type, body) = common::multipart_file_body(
+        "daily-transfers.csv",
+        include_str!("csv_samples/basic_headerless_csv.csv"),
+    );
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/banking/daily-transfer-csv/upload", addr))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let file_ingestion_id = body["file_ingestions"][0]["id"].as_i64().unwrap();
+
+    let res = client
+        .post(format!(
+            "http://{}/ingestions/{}/replay",
+            addr, file_ingestion_id
+        ))
+        .header("Content-Type", "application/json")
+        .body(r#"{"dry_run": true}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let result_str = &res.text().await.unwrap();
+    assert_eq!(
+        &result_str,
+        &format!(
+            r#"{{"message":"File ingestion {} is still being processed.","details":[]}}"#,
+            file_ingestion_id
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_should_fail_to_replay_unknown_ingestion() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/ingestions/42/replay", addr))
+        .header("Content-Type", "application/json")
+        .body(r#"{"file_destinations": ["daily-transfer-csv-to-sample-queue"]}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_ignore_dispatched_keys_on_replay_dry_run() {
+    let (addr, db_pool) = common::prepare_for_test_with_db().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0013.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let file_destination_id = body["id"].as_i64().unwrap() as i32;
+
+    let contents = include_str!("csv_samples/basic_headerless_csv.csv");
+    let (content_type, body) = common::multipart_file_body("daily-transfers.csv", contents);
+    let res = client
+        .post(format!("http://{}/banking/daily-transfer-csv/upload", addr))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let file_ingestion_id = body["file_ingestions"][0]["id"].as_i64().unwrap() as i32;
+
+    // Every key of the file was already dispatched, as after a first dispatch
+    let key_hashes: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            let values: Vec<&str> = line.split(',').take(2).collect();
+            let key = serde_json::to_string(&values).unwrap();
+            hex::encode(Sha256::digest(key.as_bytes()))
+        })
+        .collect();
+    sqlx::query(
+        "INSERT INTO dispatched_row_key(file_destination_id, key_hash, dispatched_at)
+         SELECT $1, UNNEST($2::VARCHAR[]), NOW() ON CONFLICT DO NOTHING",
+    )
+    .bind(file_destination_id)
+    .bind(&key_hashes)
+    .execute(&db_pool)
+    .await
+    .unwrap();
+    sqlx::query("UPDATE file_ingestion SET status = 'Completed' WHERE id = $1")
+        .bind(file_ingestion_id)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let res = client
+        .post(format!(
+            "http://{}/ingestions/{}/replay",
+            addr, file_ingestion_id
+        ))
+        .header("Content-Type", "application/json")
+        .body(r#"{"dry_run": true}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let dispatch = &body["dispatches"][0];
+    assert_eq!(dispatch["queued"], false);
+    // Duplicates within the file are still skipped
+    let distinct_keys: std::collections::HashSet<&String> = key_hashes.iter().collect();
+    assert_eq!(dispatch["row_count"], distinct_keys.len());
+    assert_eq!(dispatch["batch_count"], 1);
+}
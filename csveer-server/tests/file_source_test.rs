@@ -42,6 +42,24 @@ async fn test_should_fail_with_invalid_character_on_source_identifier() {
     assert_eq!(res.status(), StatusCode::BAD_REQUEST);
 }
 
+#[tokio::test]
+async fn test_should_fail_with_reserved_source_identifier() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0012.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn test_should_fail_with_blank_source_identifier() {
     let addr = common::prepare_for_test().await;
@@ -83,3 +101,598 @@ async fn test_should_fail_with_invalid_hide_column_value() {
 
     assert_eq!(res.status(), StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn test_should_create_fixed_width_file_source() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_fixed_width.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_with_overlapping_fixed_width_columns() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0004.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_fail_with_gapped_fixed_width_columns() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0005.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_fail_with_fixed_width_column_ending_past_max_position() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0011.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_create_file_source_with_schema() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_schema.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_list_every_schema_issue() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0006.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "File source schema is invalid");
+    assert_eq!(body["details"].as_array().unwrap().len(), 6);
+}
+
+#[tokio::test]
+async fn test_should_create_file_source_with_error_threshold() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_error_threshold.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_with_error_threshold_percentage_above_100() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0007.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_fail_with_negative_error_threshold() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0008.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_create_file_source_hiding_columns_by_name() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_named_hidden_columns.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_hide_columns_by_name_without_column_names() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0009.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_store_header_signature_from_declared_schema() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_header_drift_policy.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["header_signature"],
+        serde_json::json!(["date", "from", "to", "amount"])
+    );
+    assert_eq!(body["header_drift_policy"], "Reject");
+}
+
+#[tokio::test]
+async fn test_should_fail_with_header_drift_policy_without_headers() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/invalid_file_source_0010.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_list_header_drift_events() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_header_drift_policy.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/daily-transfer-csv/header-drifts",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_should_fail_to_list_header_drift_events_of_unknown_source() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!(
+            "http://{}/banking/unknown-source/header-drifts",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_list_and_get_file_sources() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!("http://{}/source?context=banking", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    let res = client
+        .get(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["identifier"], "daily-transfer-csv");
+}
+
+#[tokio::test]
+async fn test_should_update_file_source() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(format!("http://{}/banking/daily-transfer-csv", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!("requests/file_source/update_file_source.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["description"],
+        "Daily Transfer CSV distribution, sent uncompressed"
+    );
+    assert_eq!(body["hide_columns"], serde_json::json!([2]));
+    assert!(!body["updated_at"].is_null());
+}
+
+#[tokio::test]
+async fn test_should_fail_to_update_file_source_breaking_its_destinations() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/source", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/create_file_source_with_schema.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0007.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .put(format!("http://{}/banking/daily-transfer-csv", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_source/update_file_source_renaming_column.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        body["message"],
+        "The file source would no longer match its file destinations"
+    );
+
+    // The rejected update is not kept
+    let res = client
+        .get(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["schema"][1]["name"], "from");
+}
+
+#[tokio::test]
+async fn test_should_fail_to_change_identifier_of_file_source() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(format!("http://{}/banking/weekly-transfer-csv", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!("requests/file_source/update_file_source.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_should_only_delete_file_source_with_destinations_on_cascade() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/destination",
+            addr
+        ))
+        .header("Content-Type", "application/json")
+        .body(include_str!(
+            "requests/file_destination/create_file_destination_0001.json"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .delete(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = client
+        .delete(format!(
+            "http://{}/banking/daily-transfer-csv?cascade=true",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = client
+        .get(format!("http://{}/banking/daily-transfer-csv", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_keep_versions_of_file_source_and_roll_back() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(format!("http://{}/banking/daily-transfer-csv", addr))
+        .header("Content-Type", "application/json")
+        .body(include_str!("requests/file_source/update_file_source.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/daily-transfer-csv/versions",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let versions: Vec<i64> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|version| version["version"].as_i64().unwrap())
+        .collect();
+    assert_eq!(versions, vec![2, 1]);
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/daily-transfer-csv/versions/diff?from=1&to=2",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    let changed_paths: Vec<&str> = body["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| change["path"].as_str().unwrap())
+        .collect();
+    assert!(changed_paths.contains(&"description"));
+    assert!(changed_paths.contains(&"compression"));
+    assert!(changed_paths.contains(&"hide_columns"));
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/versions/1/rollback",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["version"], 3);
+    assert_eq!(body["description"], "Daily Transfer CSV distribution");
+
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/versions/7/rollback",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_should_reject_uploads_to_disabled_file_source() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/disable",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["enabled"], false);
+    assert_eq!(body["version"], 1);
+
+    let contents = include_str!("csv_samples/basic_headerless_csv.csv");
+    let (content_type, body) = common::multipart_file_body("daily-transfers.csv", contents);
+    let res = client
+        .post(format!("http://{}/banking/daily-transfer-csv/upload", addr))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = client
+        .post(format!("http://{}/banking/daily-transfer-csv/enable", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["enabled"], true);
+}
+
+#[tokio::test]
+async fn test_should_reject_uploads_with_file_name_escaping_file_source() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let client = reqwest::Client::new();
+    let contents = include_str!("csv_samples/basic_headerless_csv.csv");
+    for file_name in ["../../etc/daily-transfers.csv", "/tmp/daily-transfers.csv"] {
+        let (content_type, body) = common::multipart_file_body(file_name, contents);
+        let res = client
+            .post(format!("http://{}/banking/daily-transfer-csv/upload", addr))
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,127 @@
+use reqwest::StatusCode;
+
+mod common;
+
+#[tokio::test]
+async fn test_should_upload_and_get_reference_dataset() {
+    let addr = common::prepare_for_test().await;
+
+    let (content_type, body) = common::multipart_file_body(
+        "account_holders_reference.csv",
+        include_str!("csv_samples/account_holders_reference.csv"),
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/reference-datasets/account-holders",
+            addr
+        ))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/reference-datasets/account-holders",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let dataset: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(
+        dataset["columns"],
+        serde_json::json!(["account", "holder", "branch"])
+    );
+    assert_eq!(dataset["row_count"], 3);
+}
+
+#[tokio::test]
+async fn test_should_replace_reference_dataset_on_upload_with_same_identifier() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    for contents in [
+        include_str!("csv_samples/account_holders_reference.csv"),
+        "account,holder\nACC-004,Max Moe\n",
+    ] {
+        let (content_type, body) =
+            common::multipart_file_body("account_holders_reference.csv", contents);
+        let res = client
+            .post(format!(
+                "http://{}/banking/reference-datasets/account-holders",
+                addr
+            ))
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    let res = client
+        .get(format!(
+            "http://{}/banking/reference-datasets/account-holders",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    let dataset: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(dataset["columns"], serde_json::json!(["account", "holder"]));
+    assert_eq!(dataset["row_count"], 1);
+    assert!(!dataset["updated_at"].is_null());
+}
+
+#[tokio::test]
+async fn test_should_fail_to_upload_reference_dataset_with_duplicated_columns() {
+    let addr = common::prepare_for_test().await;
+
+    let (content_type, body) =
+        common::multipart_file_body("accounts.csv", "account,holder,account\nACC-001,Jane,x\n");
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/reference-datasets/account-holders",
+            addr
+        ))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Invalid reference dataset");
+    assert_eq!(body["details"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_should_fail_to_get_unknown_reference_dataset() {
+    let addr = common::prepare_for_test().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!(
+            "http://{}/banking/reference-datasets/account-holders",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,110 @@
+use reqwest::StatusCode;
+
+mod common;
+
+#[tokio::test]
+async fn test_should_infer_schema_of_headerless_csv() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let (content_type, body) = common::multipart_file_body(
+        "basic_headerless_csv.csv",
+        include_str!("csv_samples/basic_headerless_csv.csv"),
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/infer-schema",
+            addr
+        ))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let inference: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(inference["dialect"]["delimiter"], ",");
+    assert_eq!(inference["headers"]["present"], false);
+
+    let types: Vec<&str> = inference["schema"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|column| column["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, vec!["Date", "String", "String", "Decimal", "String"]);
+}
+
+#[tokio::test]
+async fn test_should_infer_schema_of_semicolon_csv_with_headers() {
+    let addr = common::prepare_for_test().await;
+    common::create_file_source(&addr).await;
+
+    let (content_type, body) = common::multipart_file_body(
+        "semicolon_csv_with_headers.csv",
+        include_str!("csv_samples/semicolon_csv_with_headers.csv"),
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/infer-schema",
+            addr
+        ))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let inference: serde_json::Value = serde_json::from_str(&res.text().await.unwrap()).unwrap();
+    assert_eq!(inference["dialect"]["delimiter"], ";");
+    assert_eq!(inference["headers"]["present"], true);
+
+    let schema = inference["schema"].as_array().unwrap();
+    let names: Vec<&str> = schema
+        .iter()
+        .map(|column| column["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["branch_code", "region", "active", "opened_at", "employees"]
+    );
+
+    let types: Vec<&str> = schema
+        .iter()
+        .map(|column| column["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, vec!["String", "Enum", "Boolean", "Date", "Integer"]);
+    assert_eq!(schema[3]["format"], "%d/%m/%Y");
+}
+
+#[tokio::test]
+async fn test_should_fail_to_infer_schema_of_unknown_file_source() {
+    let addr = common::prepare_for_test().await;
+
+    let (content_type, body) = common::multipart_file_body(
+        "basic_headerless_csv.csv",
+        include_str!("csv_samples/basic_headerless_csv.csv"),
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!(
+            "http://{}/banking/daily-transfer-csv/infer-schema",
+            addr
+        ))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}